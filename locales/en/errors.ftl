@@ -0,0 +1,50 @@
+# The messages of errors rendered as problem details, keyed by their stable
+# code. The value is the title and the `detail` attribute the explanation.
+# Bad requests and bodies are explained by the rejection instead, and failed
+# validations listing the invalid fields by the `fields` attribute.
+
+not_found = Not found
+    .detail = The requested resource does not exist.
+internal_error = Internal error
+    .detail = Something went wrong on our side. Quote the correlation id when reporting it.
+invalid_token = Invalid JWT
+    .detail = The access token is missing, malformed or expired.
+validation_failed = Validation error
+    .detail = The request contains a missing or invalid value.
+    .fields = One or more fields are invalid.
+invalid_credentials = Invalid credentials
+    .detail = The credentials are wrong, or the account is not active.
+forbidden = Forbidden
+    .detail = You are not allowed to perform this action.
+email_taken = Email address is already in use
+    .detail = Another account already uses this email address.
+conflict = The resource already exists
+    .detail = A resource with the same identity already exists.
+invalid_reference = Invalid reference
+    .detail = The request refers to a resource that does not exist.
+invalid_value = Invalid value
+    .detail = The request contains a value that is not allowed.
+precondition_failed = The resource has changed since it was read
+    .detail = Fetch the resource again and retry with its current `ETag`.
+precondition_required = The If-Match header is required
+    .detail = Send the `ETag` of the version being changed in `If-Match`.
+payload_too_large = Payload too large
+    .detail = The request body exceeds the size limit.
+unsupported_media_type = Unsupported media type
+    .detail = The request body is not in a supported format.
+method_not_allowed = Method not allowed
+    .detail = The resource does not support this method, see the `Allow` header.
+bad_request = Bad request
+invalid_body = Invalid body
+
+# The messages of field errors, keyed by the code of the validator. Messages
+# validators set themselves are sent as they are, in English.
+
+validation-email = Not a valid email address
+validation-length = Too short or too long
+validation-range = Out of range
+validation-url = Not a valid URL
+validation-must_match = Does not match
+validation-regex = Not in the expected format
+validation-required = Required
+validation-invalid = Invalid value
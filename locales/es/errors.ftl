@@ -0,0 +1,42 @@
+not_found = No encontrado
+    .detail = El recurso solicitado no existe.
+internal_error = Error interno
+    .detail = Algo salió mal por nuestra parte. Indique el identificador de correlación al informar del error.
+invalid_token = JWT no válido
+    .detail = El token de acceso falta, está mal formado o ha caducado.
+validation_failed = Error de validación
+    .detail = La solicitud contiene un valor ausente o no válido.
+    .fields = Uno o más campos no son válidos.
+invalid_credentials = Credenciales no válidas
+    .detail = Las credenciales son incorrectas o la cuenta no está activa.
+forbidden = Prohibido
+    .detail = No tiene permiso para realizar esta acción.
+email_taken = La dirección de correo ya está en uso
+    .detail = Otra cuenta ya utiliza esta dirección de correo.
+conflict = El recurso ya existe
+    .detail = Ya existe un recurso con la misma identidad.
+invalid_reference = Referencia no válida
+    .detail = La solicitud hace referencia a un recurso que no existe.
+invalid_value = Valor no válido
+    .detail = La solicitud contiene un valor que no está permitido.
+precondition_failed = El recurso ha cambiado desde que se leyó
+    .detail = Vuelva a obtener el recurso y reintente con su `ETag` actual.
+precondition_required = Se requiere la cabecera If-Match
+    .detail = Envíe el `ETag` de la versión que se modifica en `If-Match`.
+payload_too_large = Contenido demasiado grande
+    .detail = El cuerpo de la solicitud supera el tamaño máximo.
+unsupported_media_type = Tipo de medio no admitido
+    .detail = El cuerpo de la solicitud no está en un formato admitido.
+method_not_allowed = Método no permitido
+    .detail = El recurso no admite este método, consulte la cabecera `Allow`.
+bad_request = Solicitud incorrecta
+invalid_body = Cuerpo no válido
+
+validation-email = Dirección de correo no válida
+validation-length = Demasiado corto o demasiado largo
+validation-range = Fuera de rango
+validation-url = URL no válida
+validation-must_match = No coincide
+validation-regex = Formato inesperado
+validation-required = Obligatorio
+validation-invalid = Valor no válido
@@ -0,0 +1,42 @@
+not_found = Introuvable
+    .detail = La ressource demandée n'existe pas.
+internal_error = Erreur interne
+    .detail = Une erreur est survenue de notre côté. Indiquez l'identifiant de corrélation en la signalant.
+invalid_token = JWT invalide
+    .detail = Le jeton d'accès est absent, mal formé ou expiré.
+validation_failed = Erreur de validation
+    .detail = La requête contient une valeur manquante ou invalide.
+    .fields = Un ou plusieurs champs sont invalides.
+invalid_credentials = Identifiants invalides
+    .detail = Les identifiants sont incorrects, ou le compte n'est pas actif.
+forbidden = Interdit
+    .detail = Vous n'êtes pas autorisé à effectuer cette action.
+email_taken = Adresse e-mail déjà utilisée
+    .detail = Un autre compte utilise déjà cette adresse e-mail.
+conflict = La ressource existe déjà
+    .detail = Une ressource de même identité existe déjà.
+invalid_reference = Référence invalide
+    .detail = La requête fait référence à une ressource qui n'existe pas.
+invalid_value = Valeur invalide
+    .detail = La requête contient une valeur qui n'est pas autorisée.
+precondition_failed = La ressource a changé depuis sa lecture
+    .detail = Récupérez à nouveau la ressource et réessayez avec son `ETag` actuel.
+precondition_required = L'en-tête If-Match est requis
+    .detail = Envoyez l'`ETag` de la version modifiée dans `If-Match`.
+payload_too_large = Contenu trop volumineux
+    .detail = Le corps de la requête dépasse la taille maximale.
+unsupported_media_type = Type de média non pris en charge
+    .detail = Le corps de la requête n'est pas dans un format pris en charge.
+method_not_allowed = Méthode non autorisée
+    .detail = La ressource ne prend pas en charge cette méthode, voir l'en-tête `Allow`.
+bad_request = Requête incorrecte
+invalid_body = Corps invalide
+
+validation-email = Adresse e-mail invalide
+validation-length = Trop court ou trop long
+validation-range = Hors limites
+validation-url = URL invalide
+validation-must_match = Ne correspond pas
+validation-regex = Format inattendu
+validation-required = Obligatoire
+validation-invalid = Valeur invalide
@@ -0,0 +1,54 @@
+BEGIN;
+
+DROP TABLE app_private.audit_log;
+
+DROP FUNCTION app.change_password(uuid, TEXT, TEXT);
+
+CREATE OR REPLACE FUNCTION app.authenticate(
+  input_email TEXT,
+  input_password TEXT
+) RETURNS app.jwt_token as $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days'
+    WHERE app_private.accounts.email = input_email
+    AND app_private.accounts.hashed_password = crypt(input_password, accounts.hashed_password)
+    RETURNING 'admin', user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+CREATE OR REPLACE FUNCTION app.validate_refresh_token(req_token uuid)
+RETURNS app.jwt_token AS
+$BODY$
+DECLARE
+  new_jwt app.jwt_token;
+BEGIN
+  UPDATE app_private.accounts
+  SET
+    refresh_token = uuid_generate_v4(),
+    refresh_token_expires = NOW() + INTERVAL '5 days'
+  WHERE
+    app_private.accounts.refresh_token = req_token
+  RETURNING 'admin', user_id, refresh_token, refresh_token_expires
+  INTO new_jwt;
+
+  IF NOT FOUND THEN
+    RETURN NULL;
+  ELSE
+    RETURN new_jwt;
+  END IF;
+END;
+$BODY$
+  LANGUAGE plpgsql
+  STRICT SECURITY DEFINER;
+
+ALTER TABLE app_private.accounts DROP COLUMN role;
+
+COMMIT;
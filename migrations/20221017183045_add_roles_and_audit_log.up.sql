@@ -0,0 +1,97 @@
+BEGIN;
+
+-- Store the role of each account instead of handing out admin privileges to every session.
+
+ALTER TABLE app_private.accounts
+ADD COLUMN role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('admin', 'user'));
+
+COMMENT ON COLUMN app_private.accounts.role IS 'The role granted to the account when it is issued a token.';
+
+CREATE OR REPLACE FUNCTION app.authenticate(
+  input_email TEXT,
+  input_password TEXT
+) RETURNS app.jwt_token as $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days'
+    WHERE app_private.accounts.email = input_email
+    AND app_private.accounts.hashed_password = crypt(input_password, accounts.hashed_password)
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+CREATE OR REPLACE FUNCTION app.validate_refresh_token(req_token uuid)
+RETURNS app.jwt_token AS
+$BODY$
+DECLARE
+  new_jwt app.jwt_token;
+BEGIN
+  UPDATE app_private.accounts
+  SET
+    refresh_token = uuid_generate_v4(),
+    refresh_token_expires = NOW() + INTERVAL '5 days'
+  WHERE
+    app_private.accounts.refresh_token = req_token
+  RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+  INTO new_jwt;
+
+  IF NOT FOUND THEN
+    RETURN NULL;
+  ELSE
+    RETURN new_jwt;
+  END IF;
+END;
+$BODY$
+  LANGUAGE plpgsql
+  STRICT SECURITY DEFINER;
+
+-- Create a function to change the password of an account, revoking its refresh token.
+
+CREATE FUNCTION app.change_password(
+  input_user_id uuid,
+  current_password TEXT,
+  new_password TEXT
+) RETURNS BOOLEAN AS $$
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      hashed_password = crypt(new_password, gen_salt('bf')),
+      refresh_token = NULL,
+      refresh_token_expires = NULL
+    WHERE app_private.accounts.user_id = input_user_id
+    AND app_private.accounts.hashed_password = crypt(current_password, accounts.hashed_password);
+
+    RETURN FOUND;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+COMMENT ON FUNCTION app.change_password(uuid, TEXT, TEXT) IS 'Change the password of an account after verifying the current one.';
+
+-- Create the audit log for privileged actions.
+
+CREATE TABLE app_private.audit_log (
+  id          uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  actor_id    uuid REFERENCES app.users(id) ON DELETE SET NULL,
+  subject_id  uuid REFERENCES app.users(id) ON DELETE SET NULL,
+  action      TEXT NOT NULL,
+  metadata    JSONB NOT NULL DEFAULT '{}',
+  created_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_actor_id_idx ON app_private.audit_log (actor_id);
+CREATE INDEX audit_log_subject_id_idx ON app_private.audit_log (subject_id);
+
+COMMENT ON TABLE app_private.audit_log IS 'A record of privileged actions taken within the application.';
+COMMENT ON COLUMN app_private.audit_log.actor_id IS 'The user who performed the action.';
+COMMENT ON COLUMN app_private.audit_log.subject_id IS 'The user the action was performed on or on behalf of.';
+COMMENT ON COLUMN app_private.audit_log.action IS 'A dotted identifier describing the action, e.g. `impersonation.start`.';
+COMMENT ON COLUMN app_private.audit_log.metadata IS 'Additional context about the action.';
+
+COMMIT;
@@ -0,0 +1,7 @@
+BEGIN;
+
+DROP FUNCTION app_private.create_session(uuid);
+DROP TABLE app_private.device_codes;
+DROP FUNCTION app_private.generate_user_code();
+
+COMMIT;
@@ -0,0 +1,58 @@
+BEGIN;
+
+-- Create a function to generate short, human friendly codes, e.g. `WDJB-MJHT`.
+-- Vowels are left out so that codes never spell out words.
+
+CREATE FUNCTION app_private.generate_user_code() RETURNS TEXT AS $$
+  SELECT overlay(
+    string_agg(substr('BCDFGHJKLMNPQRSTVWXZ', 1 + get_byte(bytes, i) % 20, 1), '' ORDER BY i)
+    placing '-' from 5 for 0
+  )
+  FROM gen_random_bytes(8) AS bytes, generate_series(0, 7) AS i;
+$$ LANGUAGE sql VOLATILE;
+
+-- Create the table of pending device authorization requests (RFC 8628).
+
+CREATE TABLE app_private.device_codes (
+  device_code     TEXT PRIMARY KEY NOT NULL DEFAULT encode(gen_random_bytes(32), 'hex'),
+  user_code       TEXT UNIQUE NOT NULL DEFAULT app_private.generate_user_code(),
+  client_id       TEXT NOT NULL,
+  user_id         uuid REFERENCES app.users(id) ON DELETE CASCADE,
+  denied          BOOLEAN NOT NULL DEFAULT FALSE,
+  interval        INTEGER NOT NULL DEFAULT 5,
+  last_polled_at  TIMESTAMP WITH TIME ZONE,
+  expires_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + INTERVAL '10 minutes',
+  created_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE app_private.device_codes IS 'Pending device authorization requests from input constrained clients.';
+COMMENT ON COLUMN app_private.device_codes.device_code IS 'The secret code the device polls the token endpoint with.';
+COMMENT ON COLUMN app_private.device_codes.user_code IS 'The code the user enters to approve the device.';
+COMMENT ON COLUMN app_private.device_codes.client_id IS 'The client that requested the authorization.';
+COMMENT ON COLUMN app_private.device_codes.user_id IS 'The user that approved the request, if it has been approved.';
+COMMENT ON COLUMN app_private.device_codes.denied IS 'Whether the user denied the request.';
+COMMENT ON COLUMN app_private.device_codes.interval IS 'The minimum number of seconds the device must wait between polls.';
+COMMENT ON COLUMN app_private.device_codes.last_polled_at IS 'The last time the device polled the token endpoint.';
+COMMENT ON COLUMN app_private.device_codes.expires_at IS 'The date when the request expires.';
+
+-- Create a function to start a session for a user that has already been authenticated.
+
+CREATE FUNCTION app_private.create_session(input_user_id uuid) RETURNS app.jwt_token AS $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days'
+    WHERE app_private.accounts.user_id = input_user_id
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+COMMENT ON FUNCTION app_private.create_session(uuid) IS 'Issue a new refresh token for a user authenticated by other means than a password.';
+
+COMMIT;
@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE app_private.webauthn_challenges;
+DROP TABLE app_private.passkeys;
+
+COMMIT;
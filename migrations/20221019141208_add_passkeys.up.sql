@@ -0,0 +1,39 @@
+BEGIN;
+
+-- Create the table of WebAuthn credentials (passkeys) registered to each account.
+
+CREATE TABLE app_private.passkeys (
+  id            uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  user_id       uuid NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  credential_id BYTEA UNIQUE NOT NULL,
+  name          TEXT,
+  passkey       JSONB NOT NULL,
+  created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  last_used_at  TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX passkeys_user_id_idx ON app_private.passkeys (user_id);
+
+COMMENT ON TABLE app_private.passkeys IS 'WebAuthn credentials that can be used to log in without a password.';
+COMMENT ON COLUMN app_private.passkeys.user_id IS 'The user the credential belongs to.';
+COMMENT ON COLUMN app_private.passkeys.credential_id IS 'The id the authenticator assigned to the credential.';
+COMMENT ON COLUMN app_private.passkeys.name IS 'A name the user gave the credential, e.g. the device it lives on.';
+COMMENT ON COLUMN app_private.passkeys.passkey IS 'The serialized credential, including its public key and signature counter.';
+COMMENT ON COLUMN app_private.passkeys.last_used_at IS 'The last time the credential was used to log in.';
+
+-- Create the table of in-flight registration and authentication ceremonies.
+
+CREATE TABLE app_private.webauthn_challenges (
+  id          uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  user_id     uuid NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  ceremony    TEXT NOT NULL CHECK (ceremony IN ('registration', 'authentication')),
+  state       JSONB NOT NULL,
+  expires_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + INTERVAL '5 minutes'
+);
+
+COMMENT ON TABLE app_private.webauthn_challenges IS 'Server side state of WebAuthn ceremonies that have been started but not finished.';
+COMMENT ON COLUMN app_private.webauthn_challenges.ceremony IS 'Whether the challenge registers a new credential or authenticates with one.';
+COMMENT ON COLUMN app_private.webauthn_challenges.state IS 'The serialized ceremony state, including the challenge.';
+COMMENT ON COLUMN app_private.webauthn_challenges.expires_at IS 'The date when the challenge can no longer be completed.';
+
+COMMIT;
@@ -0,0 +1,41 @@
+BEGIN;
+
+CREATE OR REPLACE FUNCTION app_private.create_session(input_user_id uuid) RETURNS app.jwt_token AS $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days'
+    WHERE app_private.accounts.user_id = input_user_id
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+CREATE OR REPLACE FUNCTION app.authenticate(
+  input_email TEXT,
+  input_password TEXT
+) RETURNS app.jwt_token as $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days'
+    WHERE app_private.accounts.email = input_email
+    AND app_private.accounts.hashed_password = crypt(input_password, accounts.hashed_password)
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+ALTER TABLE app_private.accounts DROP COLUMN email_verified_at;
+
+COMMIT;
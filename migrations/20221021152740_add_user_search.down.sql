@@ -0,0 +1,14 @@
+BEGIN;
+
+DROP INDEX app_private.accounts_email_trgm_idx;
+DROP INDEX app.users_full_name_trgm_idx;
+DROP INDEX app.users_search_document_idx;
+
+DROP TRIGGER _200_user_search_document ON app.users;
+DROP FUNCTION app_private.set_user_search_document();
+
+ALTER TABLE app.users DROP COLUMN search_document;
+
+DROP EXTENSION "pg_trgm";
+
+COMMIT;
@@ -0,0 +1,41 @@
+BEGIN;
+
+-- Install pg_trgm for typo tolerant matching of names and email addresses.
+
+CREATE EXTENSION "pg_trgm";
+
+-- Add a full text search document of the user's names, kept up to date by a trigger.
+
+ALTER TABLE app.users ADD COLUMN search_document tsvector;
+
+COMMENT ON COLUMN app.users.search_document IS 'The full text search document of the user’s names.';
+
+CREATE FUNCTION app_private.set_user_search_document() RETURNS TRIGGER AS $$
+BEGIN
+  new.search_document := to_tsvector('simple', coalesce(new.first_name, '') || ' ' || coalesce(new.last_name, ''));
+  return new;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER _200_user_search_document BEFORE INSERT OR UPDATE OF first_name, last_name ON app.users
+FOR EACH ROW EXECUTE PROCEDURE app_private.set_user_search_document();
+
+-- Backfill existing users without marking them as updated.
+
+ALTER TABLE app.users DISABLE TRIGGER _100_user_updated_at;
+
+UPDATE app.users
+SET search_document = to_tsvector('simple', coalesce(first_name, '') || ' ' || coalesce(last_name, ''));
+
+ALTER TABLE app.users ENABLE TRIGGER _100_user_updated_at;
+
+-- Index the search document, and trigrams of the full name and email address.
+
+CREATE INDEX users_search_document_idx ON app.users USING GIN (search_document);
+
+CREATE INDEX users_full_name_trgm_idx ON app.users
+USING GIN ((coalesce(first_name, '') || ' ' || coalesce(last_name, '')) gin_trgm_ops);
+
+CREATE INDEX accounts_email_trgm_idx ON app_private.accounts USING GIN (email gin_trgm_ops);
+
+COMMIT;
@@ -0,0 +1,105 @@
+BEGIN;
+
+-- Allow users to be deactivated or deleted without losing the history attached to them.
+
+ALTER TABLE app.users ADD COLUMN deactivated_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE app.users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+COMMENT ON COLUMN app.users.deactivated_at IS 'The time the user was deactivated by an admin, if they have been.';
+COMMENT ON COLUMN app.users.deleted_at IS 'The time the user was deleted. Deleted users are purged after a retention window.';
+
+CREATE INDEX users_deleted_at_idx ON app.users (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Create a function to check whether a user is allowed to start a session.
+
+CREATE FUNCTION app_private.is_active_user(input_user_id uuid) RETURNS BOOLEAN AS $$
+  SELECT EXISTS (
+    SELECT 1 FROM app.users
+    WHERE id = input_user_id
+    AND deactivated_at IS NULL
+    AND deleted_at IS NULL
+  );
+$$ LANGUAGE sql STABLE;
+
+-- Don't let inactive users log in or refresh their sessions.
+
+CREATE OR REPLACE FUNCTION app.authenticate(
+  input_email TEXT,
+  input_password TEXT
+) RETURNS app.jwt_token as $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days',
+      last_login = NOW()
+    WHERE app_private.accounts.email = input_email
+    AND app_private.accounts.hashed_password = crypt(input_password, accounts.hashed_password)
+    AND app_private.is_active_user(accounts.user_id)
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+CREATE OR REPLACE FUNCTION app.validate_refresh_token(req_token uuid)
+RETURNS app.jwt_token AS
+$BODY$
+DECLARE
+  new_jwt app.jwt_token;
+BEGIN
+  UPDATE app_private.accounts
+  SET
+    refresh_token = uuid_generate_v4(),
+    refresh_token_expires = NOW() + INTERVAL '5 days'
+  WHERE
+    app_private.accounts.refresh_token = req_token
+  AND app_private.is_active_user(accounts.user_id)
+  RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+  INTO new_jwt;
+
+  IF NOT FOUND THEN
+    RETURN NULL;
+  ELSE
+    RETURN new_jwt;
+  END IF;
+END;
+$BODY$
+  LANGUAGE plpgsql
+  STRICT SECURITY DEFINER;
+
+CREATE OR REPLACE FUNCTION app_private.create_session(input_user_id uuid) RETURNS app.jwt_token AS $$
+  DECLARE
+    new_jwt app.jwt_token;
+  BEGIN
+    UPDATE app_private.accounts
+    SET
+      refresh_token = uuid_generate_v4(),
+      refresh_token_expires = NOW() + INTERVAL '5 days',
+      last_login = NOW()
+    WHERE app_private.accounts.user_id = input_user_id
+    AND app_private.is_active_user(accounts.user_id)
+    RETURNING accounts.role, user_id, refresh_token, refresh_token_expires
+    INTO new_jwt;
+
+    RETURN new_jwt;
+  END;
+$$ LANGUAGE plpgsql STRICT SECURITY DEFINER;
+
+-- Create a function to permanently remove users that were deleted before the retention window.
+
+CREATE FUNCTION app_private.purge_deleted_users(retention INTERVAL) RETURNS BIGINT AS $$
+  WITH purged AS (
+    DELETE FROM app.users
+    WHERE deleted_at < NOW() - retention
+    RETURNING id
+  )
+  SELECT count(*) FROM purged;
+$$ LANGUAGE sql STRICT SECURITY DEFINER;
+
+COMMENT ON FUNCTION app_private.purge_deleted_users(INTERVAL) IS 'Hard delete users that were soft deleted longer ago than the retention window.';
+
+COMMIT;
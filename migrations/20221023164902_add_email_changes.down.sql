@@ -0,0 +1,5 @@
+BEGIN;
+
+DROP TABLE app_private.email_changes;
+
+COMMIT;
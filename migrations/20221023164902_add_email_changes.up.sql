@@ -0,0 +1,18 @@
+BEGIN;
+
+-- Create the table of email address changes that are waiting to be confirmed.
+
+CREATE TABLE app_private.email_changes (
+  user_id     uuid PRIMARY KEY NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  new_email   TEXT NOT NULL,
+  token       uuid UNIQUE NOT NULL DEFAULT uuid_generate_v4(),
+  expires_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + INTERVAL '1 day',
+  created_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE app_private.email_changes IS 'Pending email address changes. Each user can have one at a time.';
+COMMENT ON COLUMN app_private.email_changes.new_email IS 'The address the user asked to change to.';
+COMMENT ON COLUMN app_private.email_changes.token IS 'The token sent to the new address to confirm the change.';
+COMMENT ON COLUMN app_private.email_changes.expires_at IS 'The date when the token can no longer be used.';
+
+COMMIT;
@@ -0,0 +1,17 @@
+BEGIN;
+
+ALTER TABLE app.users
+  DROP CONSTRAINT users_metadata_is_object,
+  DROP CONSTRAINT users_timezone_exists,
+  DROP CONSTRAINT users_locale_format,
+  DROP CONSTRAINT users_display_name_length,
+  DROP COLUMN avatar_url,
+  DROP COLUMN avatar_key,
+  DROP COLUMN metadata,
+  DROP COLUMN timezone,
+  DROP COLUMN locale,
+  DROP COLUMN display_name;
+
+DROP FUNCTION app_private.is_timezone(TEXT);
+
+COMMIT;
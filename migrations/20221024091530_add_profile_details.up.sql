@@ -0,0 +1,36 @@
+BEGIN;
+
+-- Create a function to check timezone names against the ones the database knows about.
+
+CREATE FUNCTION app_private.is_timezone(name TEXT) RETURNS BOOLEAN AS $$
+  SELECT EXISTS (SELECT 1 FROM pg_timezone_names AS tz WHERE tz.name = is_timezone.name);
+$$ LANGUAGE sql STABLE STRICT;
+
+COMMENT ON FUNCTION app_private.is_timezone(TEXT) IS 'Whether the name is an IANA timezone name, e.g. `Europe/Paris`.';
+
+-- Add the profile details users can set about themselves.
+
+ALTER TABLE app.users
+  ADD COLUMN display_name TEXT,
+  ADD COLUMN locale TEXT,
+  ADD COLUMN timezone TEXT,
+  ADD COLUMN metadata JSONB NOT NULL DEFAULT '{}',
+  ADD COLUMN avatar_key TEXT,
+  ADD COLUMN avatar_url TEXT GENERATED ALWAYS AS (
+    CASE WHEN avatar_key IS NOT NULL THEN '/users/' || id::text || '/avatar' END
+  ) STORED;
+
+ALTER TABLE app.users
+  ADD CONSTRAINT users_display_name_length CHECK (char_length(display_name) BETWEEN 1 AND 100),
+  ADD CONSTRAINT users_locale_format CHECK (locale ~ '^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,8})*$'),
+  ADD CONSTRAINT users_timezone_exists CHECK (app_private.is_timezone(timezone)),
+  ADD CONSTRAINT users_metadata_is_object CHECK (jsonb_typeof(metadata) = 'object');
+
+COMMENT ON COLUMN app.users.display_name IS 'The name the user wants to be shown as.';
+COMMENT ON COLUMN app.users.locale IS 'The user’s preferred language, as a BCP 47 language tag.';
+COMMENT ON COLUMN app.users.timezone IS 'The user’s timezone, as an IANA timezone name.';
+COMMENT ON COLUMN app.users.metadata IS 'Free-form details about the user, validated against the configured JSON Schema.';
+COMMENT ON COLUMN app.users.avatar_key IS 'The key of the user’s avatar image in the blob store.';
+COMMENT ON COLUMN app.users.avatar_url IS 'The path the user’s avatar image is served from.';
+
+COMMIT;
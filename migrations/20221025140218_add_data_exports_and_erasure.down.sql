@@ -0,0 +1,8 @@
+BEGIN;
+
+DROP FUNCTION app_private.erase_user(uuid);
+DROP FUNCTION app_private.export_user(uuid);
+DROP TABLE app_private.erasure_requests;
+DROP TABLE app_private.data_exports;
+
+COMMIT;
@@ -0,0 +1,87 @@
+BEGIN;
+
+-- Create the table of data exports users have asked for.
+
+CREATE TABLE app_private.data_exports (
+  id            uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  user_id       uuid NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  status        TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'ready', 'failed')),
+  blob_key      TEXT,
+  created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  completed_at  TIMESTAMP WITH TIME ZONE,
+  expires_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + INTERVAL '7 days'
+);
+
+CREATE INDEX data_exports_user_id_idx ON app_private.data_exports (user_id);
+
+COMMENT ON TABLE app_private.data_exports IS 'Archives of the data held about a user, produced on request.';
+COMMENT ON COLUMN app_private.data_exports.status IS 'Whether the archive is being produced, ready to download or failed.';
+COMMENT ON COLUMN app_private.data_exports.blob_key IS 'The key of the archive in the blob store, once it is ready.';
+COMMENT ON COLUMN app_private.data_exports.expires_at IS 'The date when the archive is removed.';
+
+-- Create the table of pending erasure requests.
+
+CREATE TABLE app_private.erasure_requests (
+  user_id       uuid PRIMARY KEY NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  requested_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  erase_after   TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+COMMENT ON TABLE app_private.erasure_requests IS 'Users that asked to be erased, and can still change their mind.';
+COMMENT ON COLUMN app_private.erasure_requests.erase_after IS 'The end of the cooling-off period, after which the user is erased.';
+
+-- Create a function to gather everything held about a user.
+
+CREATE FUNCTION app_private.export_user(input_user_id uuid) RETURNS JSONB AS $$
+  SELECT jsonb_build_object(
+    'exported_at', now(),
+    'profile', (
+      SELECT to_jsonb(u) - 'search_document' - 'avatar_key'
+      FROM app.users AS u
+      WHERE u.id = input_user_id
+    ),
+    'account', (
+      SELECT to_jsonb(a) - 'user_id' - 'hashed_password' - 'refresh_token'
+      FROM app_private.accounts AS a
+      WHERE a.user_id = input_user_id
+    ),
+    'sessions', jsonb_build_object(
+      'passkeys', coalesce((
+        SELECT jsonb_agg(to_jsonb(p) - 'user_id' - 'credential_id' - 'passkey' ORDER BY p.created_at)
+        FROM app_private.passkeys AS p
+        WHERE p.user_id = input_user_id
+      ), '[]'),
+      'devices', coalesce((
+        SELECT jsonb_agg(to_jsonb(d) - 'user_id' - 'device_code' - 'user_code' ORDER BY d.created_at)
+        FROM app_private.device_codes AS d
+        WHERE d.user_id = input_user_id
+      ), '[]')
+    ),
+    'pending_email_change', (
+      SELECT to_jsonb(e) - 'user_id' - 'token'
+      FROM app_private.email_changes AS e
+      WHERE e.user_id = input_user_id
+    ),
+    'audit_log', coalesce((
+      SELECT jsonb_agg(to_jsonb(l) ORDER BY l.created_at)
+      FROM app_private.audit_log AS l
+      WHERE l.actor_id = input_user_id OR l.subject_id = input_user_id
+    ), '[]')
+  );
+$$ LANGUAGE sql STABLE STRICT SECURITY DEFINER;
+
+COMMENT ON FUNCTION app_private.export_user(uuid) IS 'Everything held about a user, except secrets such as password hashes and tokens.';
+
+-- Create a function to erase a user. See `src/privacy.rs` for the policy.
+
+CREATE FUNCTION app_private.erase_user(input_user_id uuid) RETURNS VOID AS $$
+  UPDATE app_private.audit_log
+  SET metadata = '{}'
+  WHERE actor_id = input_user_id OR subject_id = input_user_id;
+
+  DELETE FROM app.users WHERE id = input_user_id;
+$$ LANGUAGE sql STRICT SECURITY DEFINER;
+
+COMMENT ON FUNCTION app_private.erase_user(uuid) IS 'Delete a user and everything tied to them, keeping only anonymous audit entries.';
+
+COMMIT;
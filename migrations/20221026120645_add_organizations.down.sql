@@ -0,0 +1,46 @@
+BEGIN;
+
+CREATE OR REPLACE FUNCTION app_private.export_user(input_user_id uuid) RETURNS JSONB AS $$
+  SELECT jsonb_build_object(
+    'exported_at', now(),
+    'profile', (
+      SELECT to_jsonb(u) - 'search_document' - 'avatar_key'
+      FROM app.users AS u
+      WHERE u.id = input_user_id
+    ),
+    'account', (
+      SELECT to_jsonb(a) - 'user_id' - 'hashed_password' - 'refresh_token'
+      FROM app_private.accounts AS a
+      WHERE a.user_id = input_user_id
+    ),
+    'sessions', jsonb_build_object(
+      'passkeys', coalesce((
+        SELECT jsonb_agg(to_jsonb(p) - 'user_id' - 'credential_id' - 'passkey' ORDER BY p.created_at)
+        FROM app_private.passkeys AS p
+        WHERE p.user_id = input_user_id
+      ), '[]'),
+      'devices', coalesce((
+        SELECT jsonb_agg(to_jsonb(d) - 'user_id' - 'device_code' - 'user_code' ORDER BY d.created_at)
+        FROM app_private.device_codes AS d
+        WHERE d.user_id = input_user_id
+      ), '[]')
+    ),
+    'pending_email_change', (
+      SELECT to_jsonb(e) - 'user_id' - 'token'
+      FROM app_private.email_changes AS e
+      WHERE e.user_id = input_user_id
+    ),
+    'audit_log', coalesce((
+      SELECT jsonb_agg(to_jsonb(l) ORDER BY l.created_at)
+      FROM app_private.audit_log AS l
+      WHERE l.actor_id = input_user_id OR l.subject_id = input_user_id
+    ), '[]')
+  );
+$$ LANGUAGE sql STABLE STRICT SECURITY DEFINER;
+
+ALTER TABLE app_private.accounts DROP COLUMN active_org_id;
+
+DROP TABLE app.memberships;
+DROP TABLE app.organizations;
+
+COMMIT;
@@ -0,0 +1,92 @@
+BEGIN;
+
+-- Create the organizations users belong to.
+
+CREATE TABLE app.organizations (
+  id          uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  name        TEXT NOT NULL CHECK (char_length(name) BETWEEN 1 AND 100),
+  created_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at  TIMESTAMP WITH TIME ZONE
+);
+
+COMMENT ON TABLE app.organizations IS 'A customer of the application. Users see the data of the organization they are acting in.';
+COMMENT ON COLUMN app.organizations.name IS 'The name of the organization.';
+
+CREATE TRIGGER _100_organization_updated_at BEFORE UPDATE ON app.organizations
+FOR EACH ROW EXECUTE PROCEDURE app_private.set_updated_at();
+
+-- Create the memberships of users in organizations.
+
+CREATE TABLE app.memberships (
+  organization_id  uuid NOT NULL REFERENCES app.organizations(id) ON DELETE CASCADE,
+  user_id          uuid NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  role             TEXT NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+  created_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX memberships_user_id_idx ON app.memberships (user_id);
+
+COMMENT ON TABLE app.memberships IS 'The organizations each user belongs to.';
+COMMENT ON COLUMN app.memberships.role IS 'What the user may do in the organization: owners and admins manage members.';
+
+-- Remember the organization each user last acted in.
+
+ALTER TABLE app_private.accounts
+ADD COLUMN active_org_id uuid REFERENCES app.organizations(id) ON DELETE SET NULL;
+
+COMMENT ON COLUMN app_private.accounts.active_org_id IS 'The organization new access tokens are issued for.';
+
+-- Include memberships in data exports.
+
+CREATE OR REPLACE FUNCTION app_private.export_user(input_user_id uuid) RETURNS JSONB AS $$
+  SELECT jsonb_build_object(
+    'exported_at', now(),
+    'profile', (
+      SELECT to_jsonb(u) - 'search_document' - 'avatar_key'
+      FROM app.users AS u
+      WHERE u.id = input_user_id
+    ),
+    'account', (
+      SELECT to_jsonb(a) - 'user_id' - 'hashed_password' - 'refresh_token'
+      FROM app_private.accounts AS a
+      WHERE a.user_id = input_user_id
+    ),
+    'memberships', coalesce((
+      SELECT jsonb_agg(jsonb_build_object(
+        'organization_id', o.id,
+        'organization_name', o.name,
+        'role', m.role,
+        'created_at', m.created_at
+      ) ORDER BY m.created_at)
+      FROM app.memberships AS m
+      JOIN app.organizations AS o
+      ON o.id = m.organization_id
+      WHERE m.user_id = input_user_id
+    ), '[]'),
+    'sessions', jsonb_build_object(
+      'passkeys', coalesce((
+        SELECT jsonb_agg(to_jsonb(p) - 'user_id' - 'credential_id' - 'passkey' ORDER BY p.created_at)
+        FROM app_private.passkeys AS p
+        WHERE p.user_id = input_user_id
+      ), '[]'),
+      'devices', coalesce((
+        SELECT jsonb_agg(to_jsonb(d) - 'user_id' - 'device_code' - 'user_code' ORDER BY d.created_at)
+        FROM app_private.device_codes AS d
+        WHERE d.user_id = input_user_id
+      ), '[]')
+    ),
+    'pending_email_change', (
+      SELECT to_jsonb(e) - 'user_id' - 'token'
+      FROM app_private.email_changes AS e
+      WHERE e.user_id = input_user_id
+    ),
+    'audit_log', coalesce((
+      SELECT jsonb_agg(to_jsonb(l) ORDER BY l.created_at)
+      FROM app_private.audit_log AS l
+      WHERE l.actor_id = input_user_id OR l.subject_id = input_user_id
+    ), '[]')
+  );
+$$ LANGUAGE sql STABLE STRICT SECURITY DEFINER;
+
+COMMIT;
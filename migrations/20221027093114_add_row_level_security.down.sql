@@ -0,0 +1,38 @@
+BEGIN;
+
+DROP POLICY organizations_select ON app.organizations;
+ALTER TABLE app.organizations DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY memberships_select ON app.memberships;
+ALTER TABLE app.memberships DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY passkeys_select ON app_private.passkeys;
+ALTER TABLE app_private.passkeys DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY accounts_select ON app_private.accounts;
+ALTER TABLE app_private.accounts DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY users_update ON app.users;
+DROP POLICY users_select ON app.users;
+ALTER TABLE app.users DISABLE ROW LEVEL SECURITY;
+
+-- The roles are shared with other databases of the cluster, so only their
+-- privileges in this one are revoked.
+
+REVOKE ALL ON FUNCTION app.register_user(TEXT, TEXT, TEXT, TEXT) FROM app_anonymous;
+REVOKE ALL ON FUNCTION app.authenticate(TEXT, TEXT) FROM app_anonymous, app_user;
+REVOKE ALL ON FUNCTION app_private.is_timezone(TEXT) FROM app_user;
+REVOKE ALL ON FUNCTION word_similarity(TEXT, TEXT), word_similarity_op(TEXT, TEXT) FROM app_anonymous, app_user;
+REVOKE ALL ON TABLE app.memberships FROM app_anonymous, app_user;
+REVOKE ALL ON TABLE app.organizations FROM app_user;
+REVOKE ALL ON TABLE app_private.passkeys FROM app_user;
+REVOKE ALL ON TABLE app_private.accounts FROM app_user;
+REVOKE ALL ON TABLE app.users FROM app_anonymous, app_user;
+REVOKE ALL ON SCHEMA app_private FROM app_user;
+REVOKE ALL ON SCHEMA app FROM app_anonymous, app_user;
+
+DROP FUNCTION app.current_org_id();
+DROP FUNCTION app.current_user_is_admin();
+DROP FUNCTION app.current_user_id();
+
+COMMIT;
@@ -0,0 +1,124 @@
+BEGIN;
+
+-- Create roles for authenticated and unauthenticated users. Roles are shared
+-- by every database of the cluster, so they may already exist.
+
+DO $$
+  BEGIN
+    CREATE ROLE app_anonymous NOLOGIN;
+  EXCEPTION WHEN duplicate_object OR unique_violation THEN
+    NULL;
+  END;
+$$;
+
+DO $$
+  BEGIN
+    CREATE ROLE app_user NOLOGIN;
+  EXCEPTION WHEN duplicate_object OR unique_violation THEN
+    NULL;
+  END;
+$$;
+
+-- Let the role the application connects as switch to them.
+
+DO $$
+  BEGIN
+    EXECUTE format('GRANT app_anonymous, app_user TO %I', current_user);
+  END;
+$$;
+
+-- Read the claims of the request, as set by the application for each transaction.
+
+CREATE FUNCTION app.current_user_id() RETURNS uuid AS $$
+  SELECT nullif(current_setting('jwt.claims.sub', true), '')::uuid;
+$$ LANGUAGE sql STABLE;
+
+COMMENT ON FUNCTION app.current_user_id() IS 'The id of the user the request is made by, if any.';
+
+CREATE FUNCTION app.current_user_is_admin() RETURNS BOOLEAN AS $$
+  SELECT coalesce(current_setting('jwt.claims.role', true), '') = 'admin';
+$$ LANGUAGE sql STABLE;
+
+COMMENT ON FUNCTION app.current_user_is_admin() IS 'Whether the request is made by an admin acting as themself.';
+
+CREATE FUNCTION app.current_org_id() RETURNS uuid AS $$
+  SELECT m.organization_id
+  FROM app.memberships AS m
+  WHERE m.organization_id = nullif(current_setting('jwt.claims.org_id', true), '')::uuid
+  AND m.user_id = app.current_user_id();
+$$ LANGUAGE sql STABLE SECURITY DEFINER;
+
+COMMENT ON FUNCTION app.current_org_id() IS 'The organization the request acts in, if the user still belongs to it.';
+
+-- Grant the roles the least they need.
+
+GRANT USAGE ON SCHEMA app TO app_anonymous, app_user;
+GRANT USAGE ON SCHEMA app_private TO app_user;
+
+GRANT SELECT ON TABLE app.users TO app_anonymous, app_user;
+GRANT UPDATE (first_name, last_name, display_name, locale, timezone, metadata) ON TABLE app.users TO app_user;
+GRANT SELECT (user_id, email, email_verified_at, last_login, role) ON TABLE app_private.accounts TO app_user;
+GRANT SELECT (user_id, created_at, last_used_at) ON TABLE app_private.passkeys TO app_user;
+GRANT SELECT ON TABLE app.organizations TO app_user;
+GRANT SELECT ON TABLE app.memberships TO app_anonymous, app_user;
+
+GRANT EXECUTE ON FUNCTION app.current_user_id(), app.current_user_is_admin(), app.current_org_id() TO app_anonymous, app_user;
+GRANT EXECUTE ON FUNCTION app_private.is_timezone(TEXT) TO app_user;
+GRANT EXECUTE ON FUNCTION word_similarity(TEXT, TEXT), word_similarity_op(TEXT, TEXT) TO app_anonymous, app_user;
+GRANT EXECUTE ON FUNCTION app.authenticate(TEXT, TEXT) TO app_anonymous, app_user;
+GRANT EXECUTE ON FUNCTION app.register_user(TEXT, TEXT, TEXT, TEXT) TO app_anonymous;
+
+-- Only show rows the request may see, however they are queried. Table owners
+-- bypass these policies, so background jobs and migrations are unaffected.
+
+ALTER TABLE app.users ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY users_select ON app.users FOR SELECT USING (
+  id = app.current_user_id()
+  OR app.current_user_is_admin()
+  OR EXISTS (
+    SELECT 1 FROM app.memberships AS m
+    WHERE m.user_id = users.id AND m.organization_id = app.current_org_id()
+  )
+);
+
+CREATE POLICY users_update ON app.users FOR UPDATE USING (
+  id = app.current_user_id() OR app.current_user_is_admin()
+);
+
+ALTER TABLE app_private.accounts ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY accounts_select ON app_private.accounts FOR SELECT USING (
+  user_id = app.current_user_id()
+  OR app.current_user_is_admin()
+  OR EXISTS (
+    SELECT 1 FROM app.memberships AS m
+    WHERE m.user_id = accounts.user_id AND m.organization_id = app.current_org_id()
+  )
+);
+
+ALTER TABLE app_private.passkeys ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY passkeys_select ON app_private.passkeys FOR SELECT USING (
+  user_id = app.current_user_id() OR app.current_user_is_admin()
+);
+
+ALTER TABLE app.memberships ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY memberships_select ON app.memberships FOR SELECT USING (
+  user_id = app.current_user_id()
+  OR organization_id = app.current_org_id()
+  OR app.current_user_is_admin()
+);
+
+ALTER TABLE app.organizations ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY organizations_select ON app.organizations FOR SELECT USING (
+  EXISTS (
+    SELECT 1 FROM app.memberships AS m
+    WHERE m.organization_id = organizations.id AND m.user_id = app.current_user_id()
+  )
+  OR app.current_user_is_admin()
+);
+
+COMMIT;
@@ -0,0 +1,29 @@
+BEGIN;
+
+-- Create the table of invitations to join an organization.
+
+CREATE TABLE app_private.invitations (
+  id               uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  organization_id  uuid NOT NULL REFERENCES app.organizations(id) ON DELETE CASCADE,
+  email            TEXT NOT NULL,
+  role             TEXT NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+  token            uuid UNIQUE NOT NULL DEFAULT uuid_generate_v4(),
+  invited_by       uuid REFERENCES app.users(id) ON DELETE SET NULL,
+  created_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  expires_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + INTERVAL '7 days',
+  accepted_at      TIMESTAMP WITH TIME ZONE,
+  revoked_at       TIMESTAMP WITH TIME ZONE
+);
+
+-- Each address has at most one open invitation per organization.
+CREATE UNIQUE INDEX invitations_open_idx ON app_private.invitations (organization_id, lower(email))
+WHERE accepted_at IS NULL AND revoked_at IS NULL;
+
+COMMENT ON TABLE app_private.invitations IS 'Invitations sent by email to join an organization.';
+COMMENT ON COLUMN app_private.invitations.role IS 'The role the invitee gets in the organization.';
+COMMENT ON COLUMN app_private.invitations.token IS 'The token sent to the invitee to accept the invitation.';
+COMMENT ON COLUMN app_private.invitations.expires_at IS 'The date when the token can no longer be used.';
+COMMENT ON COLUMN app_private.invitations.accepted_at IS 'The time the invitation was accepted, if it was.';
+COMMENT ON COLUMN app_private.invitations.revoked_at IS 'The time the invitation was revoked, if it was.';
+
+COMMIT;
@@ -0,0 +1,7 @@
+BEGIN;
+
+DROP TABLE app.group_members;
+DROP TABLE app.group_permissions;
+DROP TABLE app.groups;
+
+COMMIT;
@@ -0,0 +1,46 @@
+BEGIN;
+
+-- Create the groups users can be put into. Groups nest: a group inherits the
+-- permissions of its parent, and so on up to the root.
+
+CREATE TABLE app.groups (
+  id           uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+  name         TEXT NOT NULL UNIQUE CHECK (char_length(name) BETWEEN 1 AND 100),
+  description  TEXT,
+  parent_id    uuid REFERENCES app.groups(id) ON DELETE SET NULL CHECK (parent_id <> id),
+  created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at   TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX groups_parent_id_idx ON app.groups (parent_id);
+
+COMMENT ON TABLE app.groups IS 'Named groups of users that permissions are granted to.';
+COMMENT ON COLUMN app.groups.parent_id IS 'The group this group inherits permissions from, if any.';
+
+CREATE TRIGGER _100_group_updated_at BEFORE UPDATE ON app.groups
+FOR EACH ROW EXECUTE PROCEDURE app_private.set_updated_at();
+
+-- Create the permissions granted to each group.
+
+CREATE TABLE app.group_permissions (
+  group_id    uuid NOT NULL REFERENCES app.groups(id) ON DELETE CASCADE,
+  permission  TEXT NOT NULL CHECK (permission ~ '^[a-z_]+:[a-z_]+$'),
+  PRIMARY KEY (group_id, permission)
+);
+
+COMMENT ON TABLE app.group_permissions IS 'Permissions such as `users:export`, granted to a group and its descendants.';
+
+-- Create the members of each group.
+
+CREATE TABLE app.group_members (
+  group_id    uuid NOT NULL REFERENCES app.groups(id) ON DELETE CASCADE,
+  user_id     uuid NOT NULL REFERENCES app.users(id) ON DELETE CASCADE,
+  created_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX group_members_user_id_idx ON app.group_members (user_id);
+
+COMMENT ON TABLE app.group_members IS 'The users in each group.';
+
+COMMIT;
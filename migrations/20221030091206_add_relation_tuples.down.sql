@@ -0,0 +1,10 @@
+BEGIN;
+
+DROP TRIGGER _500_organization_tuples ON app.organizations;
+DROP TRIGGER _500_user_tuples ON app.users;
+DROP TRIGGER _500_membership_tuples ON app.memberships;
+DROP FUNCTION app_private.delete_object_tuples();
+DROP FUNCTION app_private.sync_membership_tuples();
+DROP TABLE app_private.relation_tuples;
+
+COMMIT;
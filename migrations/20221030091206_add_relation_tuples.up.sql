@@ -0,0 +1,76 @@
+BEGIN;
+
+-- Create the relation tuples authorization checks are evaluated against. A
+-- tuple `object_type:object_id#relation@subject` states that the subject has
+-- the relation to the object. The subject is either an object such as
+-- `user:<id>`, or every subject with a relation to an object, a userset such
+-- as `org:<id>#member`. See `src/authz.rs` for how they are evaluated.
+
+CREATE TABLE app_private.relation_tuples (
+  object_type       TEXT NOT NULL CHECK (object_type ~ '^[a-z_]+$'),
+  object_id         TEXT NOT NULL CHECK (object_id ~ '^[^\s:#@]+$'),
+  relation          TEXT NOT NULL CHECK (relation ~ '^[a-z_]+$'),
+  subject_type      TEXT NOT NULL CHECK (subject_type ~ '^[a-z_]+$'),
+  subject_id        TEXT NOT NULL CHECK (subject_id ~ '^[^\s:#@]+$'),
+  subject_relation  TEXT NOT NULL DEFAULT '' CHECK (subject_relation ~ '^[a-z_]*$'),
+  created_at        TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  PRIMARY KEY (object_type, object_id, relation, subject_type, subject_id, subject_relation)
+);
+
+CREATE INDEX relation_tuples_subject_idx ON app_private.relation_tuples (subject_type, subject_id, subject_relation);
+
+COMMENT ON TABLE app_private.relation_tuples IS 'Relations between objects and subjects, e.g. `document:readme#viewer@org:<id>#member`.';
+COMMENT ON COLUMN app_private.relation_tuples.subject_relation IS 'The relation of a userset subject, or empty for a direct subject.';
+
+-- Mirror memberships as `org:<id>#<role>@user:<id>` and `user:<id>#org@org:<id>`,
+-- so checks can follow users to their organizations and back.
+
+CREATE FUNCTION app_private.sync_membership_tuples() RETURNS TRIGGER AS $$
+BEGIN
+  IF tg_op IN ('UPDATE', 'DELETE') THEN
+    DELETE FROM app_private.relation_tuples
+    WHERE (object_type, object_id, relation, subject_type, subject_id) IN (
+      ('org', old.organization_id::text, old.role, 'user', old.user_id::text),
+      ('user', old.user_id::text, 'org', 'org', old.organization_id::text)
+    );
+  END IF;
+
+  IF tg_op IN ('INSERT', 'UPDATE') THEN
+    INSERT INTO app_private.relation_tuples (object_type, object_id, relation, subject_type, subject_id)
+    VALUES
+      ('org', new.organization_id::text, new.role, 'user', new.user_id::text),
+      ('user', new.user_id::text, 'org', 'org', new.organization_id::text)
+    ON CONFLICT DO NOTHING;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+CREATE TRIGGER _500_membership_tuples AFTER INSERT OR UPDATE OR DELETE ON app.memberships
+FOR EACH ROW EXECUTE PROCEDURE app_private.sync_membership_tuples();
+
+INSERT INTO app_private.relation_tuples (object_type, object_id, relation, subject_type, subject_id)
+SELECT 'org', organization_id::text, role, 'user', user_id::text FROM app.memberships
+UNION ALL
+SELECT 'user', user_id::text, 'org', 'org', organization_id::text FROM app.memberships;
+
+-- Forget every relation of users and organizations once they are deleted.
+
+CREATE FUNCTION app_private.delete_object_tuples() RETURNS TRIGGER AS $$
+BEGIN
+  DELETE FROM app_private.relation_tuples
+  WHERE (object_type = tg_argv[0] AND object_id = old.id::text)
+  OR (subject_type = tg_argv[0] AND subject_id = old.id::text);
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+CREATE TRIGGER _500_user_tuples AFTER DELETE ON app.users
+FOR EACH ROW EXECUTE PROCEDURE app_private.delete_object_tuples('user');
+
+CREATE TRIGGER _500_organization_tuples AFTER DELETE ON app.organizations
+FOR EACH ROW EXECUTE PROCEDURE app_private.delete_object_tuples('org');
+
+COMMIT;
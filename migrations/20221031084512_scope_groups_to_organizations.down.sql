@@ -0,0 +1,12 @@
+BEGIN;
+
+DROP TRIGGER _500_leave_organization_groups ON app.memberships;
+DROP FUNCTION app_private.leave_organization_groups();
+
+DROP INDEX app.groups_organization_name_key;
+DROP INDEX app.groups_global_name_key;
+
+ALTER TABLE app.groups DROP COLUMN organization_id;
+ALTER TABLE app.groups ADD CONSTRAINT groups_name_key UNIQUE (name);
+
+COMMIT;
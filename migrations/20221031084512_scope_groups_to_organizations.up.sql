@@ -0,0 +1,31 @@
+BEGIN;
+
+-- Scope groups to an organization. The permissions of an organization's
+-- groups only apply to its members while they act in it, whereas groups
+-- without an organization are global and apply everywhere.
+
+ALTER TABLE app.groups ADD COLUMN organization_id uuid REFERENCES app.organizations(id) ON DELETE CASCADE;
+
+ALTER TABLE app.groups DROP CONSTRAINT groups_name_key;
+
+CREATE UNIQUE INDEX groups_global_name_key ON app.groups (name) WHERE organization_id IS NULL;
+CREATE UNIQUE INDEX groups_organization_name_key ON app.groups (organization_id, name) WHERE organization_id IS NOT NULL;
+
+COMMENT ON COLUMN app.groups.organization_id IS 'The organization the group belongs to, or NULL for a global group.';
+
+-- Members leaving an organization leave its groups too.
+
+CREATE FUNCTION app_private.leave_organization_groups() RETURNS TRIGGER AS $$
+BEGIN
+  DELETE FROM app.group_members AS m
+  USING app.groups AS g
+  WHERE g.id = m.group_id AND g.organization_id = old.organization_id AND m.user_id = old.user_id;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+CREATE TRIGGER _500_leave_organization_groups AFTER DELETE ON app.memberships
+FOR EACH ROW EXECUTE PROCEDURE app_private.leave_organization_groups();
+
+COMMIT;
@@ -0,0 +1,24 @@
+BEGIN;
+
+DROP POLICY relation_tuples_select ON app_private.relation_tuples;
+ALTER TABLE app_private.relation_tuples DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY invitations_select ON app_private.invitations;
+ALTER TABLE app_private.invitations DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY device_codes_select ON app_private.device_codes;
+ALTER TABLE app_private.device_codes DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY email_changes_select ON app_private.email_changes;
+ALTER TABLE app_private.email_changes DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY data_exports_select ON app_private.data_exports;
+ALTER TABLE app_private.data_exports DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY audit_log_select ON app_private.audit_log;
+ALTER TABLE app_private.audit_log DISABLE ROW LEVEL SECURITY;
+
+REVOKE ALL ON TABLE app_private.data_exports FROM app_user;
+REVOKE UPDATE (deleted_at) ON TABLE app.users FROM app_user;
+
+COMMIT;
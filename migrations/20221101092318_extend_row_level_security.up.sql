@@ -0,0 +1,52 @@
+BEGIN;
+
+-- Let users delete their own account, and read their own data exports.
+
+GRANT UPDATE (deleted_at) ON TABLE app.users TO app_user;
+GRANT SELECT ON TABLE app_private.data_exports TO app_user;
+
+-- Only show the rows about the user of the request, or those of the
+-- organization they act in. Tables that are not granted to the roles get
+-- policies too, so that granting them later can't expose every row.
+
+ALTER TABLE app_private.audit_log ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY audit_log_select ON app_private.audit_log FOR SELECT USING (
+  actor_id = app.current_user_id()
+  OR subject_id = app.current_user_id()
+  OR app.current_user_is_admin()
+);
+
+ALTER TABLE app_private.data_exports ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY data_exports_select ON app_private.data_exports FOR SELECT USING (
+  user_id = app.current_user_id()
+);
+
+ALTER TABLE app_private.email_changes ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY email_changes_select ON app_private.email_changes FOR SELECT USING (
+  user_id = app.current_user_id()
+);
+
+ALTER TABLE app_private.device_codes ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY device_codes_select ON app_private.device_codes FOR SELECT USING (
+  user_id = app.current_user_id()
+);
+
+ALTER TABLE app_private.invitations ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY invitations_select ON app_private.invitations FOR SELECT USING (
+  organization_id = app.current_org_id()
+  OR app.current_user_is_admin()
+);
+
+ALTER TABLE app_private.relation_tuples ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY relation_tuples_select ON app_private.relation_tuples FOR SELECT USING (
+  (subject_type = 'user' AND subject_id = app.current_user_id()::TEXT)
+  OR app.current_user_is_admin()
+);
+
+COMMIT;
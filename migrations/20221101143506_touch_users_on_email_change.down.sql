@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TRIGGER _500_touch_user_on_email_change ON app_private.accounts;
+DROP FUNCTION app_private.touch_user();
+
+COMMIT;
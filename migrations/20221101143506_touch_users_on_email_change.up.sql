@@ -0,0 +1,20 @@
+BEGIN;
+
+-- The email address is part of a user's representation, so changing it is a
+-- new version of the user, and must change its entity tag.
+
+CREATE FUNCTION app_private.touch_user() RETURNS TRIGGER AS $$
+BEGIN
+  UPDATE app.users SET updated_at = CURRENT_TIMESTAMP WHERE id = new.user_id;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+COMMENT ON FUNCTION app_private.touch_user() IS 'Bump the updated_at of the user an account belongs to.';
+
+CREATE TRIGGER _500_touch_user_on_email_change AFTER UPDATE OF email ON app_private.accounts
+FOR EACH ROW WHEN (old.email IS DISTINCT FROM new.email)
+EXECUTE PROCEDURE app_private.touch_user();
+
+COMMIT;
@@ -0,0 +1,111 @@
+BEGIN;
+
+-- Include invitations, group memberships and relation tuples in data exports.
+
+CREATE OR REPLACE FUNCTION app_private.export_user(input_user_id uuid) RETURNS JSONB AS $$
+  SELECT jsonb_build_object(
+    'exported_at', now(),
+    'profile', (
+      SELECT to_jsonb(u) - 'search_document' - 'avatar_key'
+      FROM app.users AS u
+      WHERE u.id = input_user_id
+    ),
+    'account', (
+      SELECT to_jsonb(a) - 'user_id' - 'hashed_password' - 'refresh_token'
+      FROM app_private.accounts AS a
+      WHERE a.user_id = input_user_id
+    ),
+    'memberships', coalesce((
+      SELECT jsonb_agg(jsonb_build_object(
+        'organization_id', o.id,
+        'organization_name', o.name,
+        'role', m.role,
+        'created_at', m.created_at
+      ) ORDER BY m.created_at)
+      FROM app.memberships AS m
+      JOIN app.organizations AS o
+      ON o.id = m.organization_id
+      WHERE m.user_id = input_user_id
+    ), '[]'),
+    'invitations', coalesce((
+      SELECT jsonb_agg(jsonb_build_object(
+        'organization_id', o.id,
+        'organization_name', o.name,
+        'email', i.email,
+        'role', i.role,
+        'created_at', i.created_at,
+        'expires_at', i.expires_at,
+        'accepted_at', i.accepted_at,
+        'revoked_at', i.revoked_at
+      ) ORDER BY i.created_at)
+      FROM app_private.invitations AS i
+      JOIN app.organizations AS o
+      ON o.id = i.organization_id
+      JOIN app_private.accounts AS a
+      ON lower(a.email) = lower(i.email)
+      WHERE a.user_id = input_user_id
+    ), '[]'),
+    'groups', coalesce((
+      SELECT jsonb_agg(jsonb_build_object(
+        'group_id', g.id,
+        'group_name', g.name,
+        'organization_id', g.organization_id,
+        'created_at', m.created_at
+      ) ORDER BY m.created_at)
+      FROM app.group_members AS m
+      JOIN app.groups AS g
+      ON g.id = m.group_id
+      WHERE m.user_id = input_user_id
+    ), '[]'),
+    'relations', coalesce((
+      SELECT jsonb_agg(to_jsonb(t) ORDER BY t.created_at)
+      FROM app_private.relation_tuples AS t
+      WHERE (t.subject_type = 'user' AND t.subject_id = input_user_id::TEXT)
+      OR (t.object_type = 'user' AND t.object_id = input_user_id::TEXT)
+    ), '[]'),
+    'sessions', jsonb_build_object(
+      'passkeys', coalesce((
+        SELECT jsonb_agg(to_jsonb(p) - 'user_id' - 'credential_id' - 'passkey' ORDER BY p.created_at)
+        FROM app_private.passkeys AS p
+        WHERE p.user_id = input_user_id
+      ), '[]'),
+      'devices', coalesce((
+        SELECT jsonb_agg(to_jsonb(d) - 'user_id' - 'device_code' - 'user_code' ORDER BY d.created_at)
+        FROM app_private.device_codes AS d
+        WHERE d.user_id = input_user_id
+      ), '[]')
+    ),
+    'pending_email_change', (
+      SELECT to_jsonb(e) - 'user_id' - 'token'
+      FROM app_private.email_changes AS e
+      WHERE e.user_id = input_user_id
+    ),
+    'audit_log', coalesce((
+      SELECT jsonb_agg(to_jsonb(l) ORDER BY l.created_at)
+      FROM app_private.audit_log AS l
+      WHERE l.actor_id = input_user_id OR l.subject_id = input_user_id
+    ), '[]')
+  );
+$$ LANGUAGE sql STABLE STRICT SECURITY DEFINER;
+
+-- Erasing a user also removes the invitations addressed to them, which hold
+-- their email address, and the relation tuples naming them, which are not
+-- tied to the user by a foreign key.
+
+CREATE OR REPLACE FUNCTION app_private.erase_user(input_user_id uuid) RETURNS VOID AS $$
+  UPDATE app_private.audit_log
+  SET metadata = '{}'
+  WHERE actor_id = input_user_id OR subject_id = input_user_id;
+
+  DELETE FROM app_private.invitations AS i
+  USING app_private.accounts AS a
+  WHERE a.user_id = input_user_id AND lower(i.email) = lower(a.email);
+
+  DELETE FROM app_private.relation_tuples
+  WHERE (subject_type = 'user' AND subject_id = input_user_id::TEXT)
+  OR (object_type = 'user' AND object_id = input_user_id::TEXT);
+
+  DELETE FROM app.users WHERE id = input_user_id;
+$$ LANGUAGE sql STRICT SECURITY DEFINER;
+
+COMMIT;
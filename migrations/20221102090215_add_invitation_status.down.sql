@@ -0,0 +1,5 @@
+BEGIN;
+
+DROP FUNCTION app_private.invitation_status(app_private.invitations);
+
+COMMIT;
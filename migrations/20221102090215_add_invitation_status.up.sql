@@ -0,0 +1,17 @@
+BEGIN;
+
+-- The status of an invitation, derived from its dates, in one place for
+-- every query that reports it.
+
+CREATE FUNCTION app_private.invitation_status(invitation app_private.invitations) RETURNS TEXT AS $$
+  SELECT CASE
+    WHEN invitation.accepted_at IS NOT NULL THEN 'accepted'
+    WHEN invitation.revoked_at IS NOT NULL THEN 'revoked'
+    WHEN invitation.expires_at <= now() THEN 'expired'
+    ELSE 'pending'
+  END;
+$$ LANGUAGE sql STABLE;
+
+COMMENT ON FUNCTION app_private.invitation_status(app_private.invitations) IS 'One of pending, accepted, revoked or expired.';
+
+COMMIT;
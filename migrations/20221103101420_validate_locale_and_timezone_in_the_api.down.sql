@@ -0,0 +1,15 @@
+BEGIN;
+
+CREATE FUNCTION app_private.is_timezone(name TEXT) RETURNS BOOLEAN AS $$
+  SELECT EXISTS (SELECT 1 FROM pg_timezone_names AS tz WHERE tz.name = is_timezone.name);
+$$ LANGUAGE sql STABLE STRICT;
+
+COMMENT ON FUNCTION app_private.is_timezone(TEXT) IS 'Whether the name is an IANA timezone name, e.g. `Europe/Paris`.';
+
+GRANT EXECUTE ON FUNCTION app_private.is_timezone(TEXT) TO app_user;
+
+ALTER TABLE app.users
+  ADD CONSTRAINT users_locale_format CHECK (locale ~ '^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,8})*$'),
+  ADD CONSTRAINT users_timezone_exists CHECK (app_private.is_timezone(timezone));
+
+COMMIT;
@@ -0,0 +1,13 @@
+BEGIN;
+
+-- Check constraints must only call immutable functions, while the timezones
+-- the database knows about may change with its timezone data. Locales and
+-- timezones are validated by the API instead.
+
+ALTER TABLE app.users
+  DROP CONSTRAINT users_timezone_exists,
+  DROP CONSTRAINT users_locale_format;
+
+DROP FUNCTION app_private.is_timezone(TEXT);
+
+COMMIT;
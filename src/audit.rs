@@ -0,0 +1,34 @@
+use serde_json::Value;
+use sqlx::{PgExecutor, Result};
+use uuid::Uuid;
+
+/// Append an entry to `app_private.audit_log`.
+/// Actor: The user performing the action
+/// Subject: The user the action is performed on or on behalf of
+/// Action: A dotted identifier such as `impersonation.start`
+pub async fn record<'e, E>(
+    executor: E,
+    actor: Option<Uuid>,
+    subject: Option<Uuid>,
+    action: &str,
+    metadata: Value,
+) -> Result<()>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.audit_log (actor_id, subject_id, action, metadata)
+            VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(actor)
+    .bind(subject)
+    .bind(action)
+    .bind(metadata)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
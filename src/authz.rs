@@ -0,0 +1,530 @@
+//! Relationship-based authorization, in the style of Google's Zanzibar.
+//!
+//! Access is derived from relation tuples such as
+//! `document:readme#viewer@org:<id>#member`, stored in
+//! `app_private.relation_tuples`. The [`AuthzSchema`] lists the relations of
+//! each object type and how they are rewritten: a user can be an editor of a
+//! document because a tuple says so, because they own it, or because they
+//! administer the organization the document belongs to. Memberships are
+//! mirrored as `org:<id>#<role>@user:<id>` and `user:<id>#org@org:<id>`
+//! tuples by a trigger.
+
+use std::{collections::HashMap, fmt, fs, path::Path, str::FromStr};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+/// How many rewrites and usersets a check follows before giving up, which
+/// bounds the work done for deeply nested or cyclic relations.
+pub const MAX_DEPTH: usize = 16;
+
+/// An object, written `type:id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectRef {
+    pub object_type: String,
+    pub object_id: String,
+}
+
+impl ObjectRef {
+    pub fn new(object_type: &str, object_id: impl ToString) -> Self {
+        ObjectRef {
+            object_type: object_type.to_string(),
+            object_id: object_id.to_string(),
+        }
+    }
+
+    pub fn user(id: Uuid) -> Self {
+        Self::new("user", id)
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.object_type, self.object_id)
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (object_type, object_id) = value.split_once(':').ok_or(Error::ValidationError)?;
+
+        let is_valid_type = !object_type.is_empty()
+            && object_type
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c == '_');
+        let is_valid_id = !object_id.is_empty()
+            && !object_id
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, ':' | '#' | '@'));
+
+        if !is_valid_type || !is_valid_id {
+            return Err(Error::ValidationError);
+        }
+
+        Ok(ObjectRef::new(object_type, object_id))
+    }
+}
+
+/// The subject of a relation: an object such as `user:<id>`, or a userset
+/// such as `org:<id>#member` standing for every subject with that relation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubjectRef {
+    pub object: ObjectRef,
+    pub relation: Option<String>,
+}
+
+impl SubjectRef {
+    pub fn user(id: Uuid) -> Self {
+        SubjectRef {
+            object: ObjectRef::user(id),
+            relation: None,
+        }
+    }
+}
+
+impl fmt::Display for SubjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.relation {
+            Some(relation) => write!(f, "{}#{}", self.object, relation),
+            None => write!(f, "{}", self.object),
+        }
+    }
+}
+
+impl FromStr for SubjectRef {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (object, relation) = match value.split_once('#') {
+            Some((object, relation)) if is_valid_relation(relation) => {
+                (object, Some(relation.to_string()))
+            }
+            Some(_) => return Err(Error::ValidationError),
+            None => (value, None),
+        };
+
+        Ok(SubjectRef {
+            object: object.parse()?,
+            relation,
+        })
+    }
+}
+
+fn is_valid_relation(relation: &str) -> bool {
+    !relation.is_empty() && relation.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+/// A stored relation: the subject has the relation to the object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationTuple {
+    pub object: ObjectRef,
+    pub relation: String,
+    pub subject: SubjectRef,
+}
+
+impl fmt::Display for RelationTuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}@{}", self.object, self.relation, self.subject)
+    }
+}
+
+#[derive(FromRow)]
+struct TupleRow {
+    object_type: String,
+    object_id: String,
+    relation: String,
+    subject_type: String,
+    subject_id: String,
+    subject_relation: String,
+}
+
+impl From<TupleRow> for RelationTuple {
+    fn from(row: TupleRow) -> Self {
+        RelationTuple {
+            object: ObjectRef {
+                object_type: row.object_type,
+                object_id: row.object_id,
+            },
+            relation: row.relation,
+            subject: SubjectRef {
+                object: ObjectRef {
+                    object_type: row.subject_type,
+                    object_id: row.subject_id,
+                },
+                relation: Some(row.subject_relation).filter(|relation| !relation.is_empty()),
+            },
+        }
+    }
+}
+
+/// One way a subject can come to have a relation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rewrite {
+    /// The subjects of tuples with the relation itself.
+    This,
+    /// The subjects with another relation to the same object, e.g. owners
+    /// are also editors.
+    ComputedUserset { relation: String },
+    /// The subjects with a relation to the objects found through another
+    /// relation, e.g. the admins of the organization a document belongs to.
+    TupleToUserset {
+        tupleset: String,
+        computed_userset: String,
+    },
+}
+
+/// The relations of each object type and their rewrites, keyed by type and
+/// then by relation. A relation is held if any of its rewrites grants it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AuthzSchema {
+    types: HashMap<String, HashMap<String, Vec<Rewrite>>>,
+}
+
+impl AuthzSchema {
+    pub fn new(types: HashMap<String, HashMap<String, Vec<Rewrite>>>) -> Result<Self, Error> {
+        let schema = AuthzSchema { types };
+
+        // Tuple-to-userset rewrites point at other types, so only their
+        // tupleset can be checked here.
+        for (object_type, relations) in &schema.types {
+            for rewrite in relations.values().flatten() {
+                let relation = match rewrite {
+                    Rewrite::This => continue,
+                    Rewrite::ComputedUserset { relation } => relation,
+                    Rewrite::TupleToUserset { tupleset, .. } => tupleset,
+                };
+
+                if !relations.contains_key(relation) {
+                    tracing::error!(
+                        "Invalid authorization schema: `{}` has no relation `{}`",
+                        object_type,
+                        relation
+                    );
+                    return Err(Error::InternalError);
+                }
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Load the schema from a JSON file.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let types = fs::read(path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| {
+                tracing::error!(
+                    "Unable to read authorization schema from {}",
+                    path.display()
+                );
+                Error::InternalError
+            })?;
+
+        Self::new(types)
+    }
+
+    /// The rewrites of a relation, if the type has it.
+    pub fn relation(&self, object_type: &str, relation: &str) -> Option<&[Rewrite]> {
+        self.types
+            .get(object_type)
+            .and_then(|relations| relations.get(relation))
+            .map(Vec::as_slice)
+    }
+
+    /// Reject tuples whose object or userset relation is not in the schema.
+    pub fn validate(&self, tuple: &RelationTuple) -> Result<(), Error> {
+        self.relation(&tuple.object.object_type, &tuple.relation)
+            .ok_or_else(|| Error::invalid_field("relation", "invalid"))?;
+
+        let is_known_subject = match &tuple.subject.relation {
+            Some(relation) => self
+                .relation(&tuple.subject.object.object_type, relation)
+                .is_some(),
+            None => self.types.contains_key(&tuple.subject.object.object_type),
+        };
+
+        if is_known_subject {
+            Ok(())
+        } else {
+            Err(Error::invalid_field("subject", "invalid"))
+        }
+    }
+
+    /// Whether the subject has the relation to the object. Nobody has a
+    /// relation the schema does not define.
+    pub async fn check(
+        &self,
+        pool: &PgPool,
+        subject: &SubjectRef,
+        relation: &str,
+        object: &ObjectRef,
+    ) -> Result<bool, Error> {
+        self.check_at(pool, subject, relation, object, 0).await
+    }
+
+    fn check_at<'a>(
+        &'a self,
+        pool: &'a PgPool,
+        subject: &'a SubjectRef,
+        relation: &'a str,
+        object: &'a ObjectRef,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            if depth > MAX_DEPTH {
+                tracing::warn!(
+                    "Gave up checking {}#{}@{} after {} steps",
+                    object,
+                    relation,
+                    subject,
+                    MAX_DEPTH
+                );
+                return Ok(false);
+            }
+
+            // A userset always contains itself.
+            if subject.relation.as_deref() == Some(relation) && subject.object == *object {
+                return Ok(true);
+            }
+
+            let rewrites = match self.relation(&object.object_type, relation) {
+                Some(rewrites) => rewrites,
+                None => return Ok(false),
+            };
+
+            for rewrite in rewrites {
+                let found = match rewrite {
+                    Rewrite::This => {
+                        let mut found = false;
+
+                        for related in subjects(pool, object, relation).await? {
+                            found = related == *subject
+                                || match &related.relation {
+                                    Some(userset) => {
+                                        self.check_at(
+                                            pool,
+                                            subject,
+                                            userset,
+                                            &related.object,
+                                            depth + 1,
+                                        )
+                                        .await?
+                                    }
+                                    None => false,
+                                };
+
+                            if found {
+                                break;
+                            }
+                        }
+
+                        found
+                    }
+                    Rewrite::ComputedUserset { relation } => {
+                        self.check_at(pool, subject, relation, object, depth + 1)
+                            .await?
+                    }
+                    Rewrite::TupleToUserset {
+                        tupleset,
+                        computed_userset,
+                    } => {
+                        let mut found = false;
+
+                        for related in subjects(pool, object, tupleset).await? {
+                            found = self
+                                .check_at(
+                                    pool,
+                                    subject,
+                                    computed_userset,
+                                    &related.object,
+                                    depth + 1,
+                                )
+                                .await?;
+
+                            if found {
+                                break;
+                            }
+                        }
+
+                        found
+                    }
+                };
+
+                if found {
+                    return Ok(true);
+                }
+            }
+
+            Ok(false)
+        })
+    }
+}
+
+/// The schema of the objects this API knows about:
+///
+/// - `org`: `owner`s are `admin`s, who are `member`s.
+/// - `user`: belongs to `org`s, whose members are its `viewer`s. Its
+///   `editor`s are only ever granted directly, as anyone can create an
+///   organization and administer its members.
+/// - `document`: a generic resource other services can share through the
+///   API. It belongs to an `org` whose members can view it and whose admins
+///   can edit it.
+impl Default for AuthzSchema {
+    fn default() -> Self {
+        let types = serde_json::from_value(json!({
+            "org": {
+                "owner": ["this"],
+                "admin": ["this", { "computed_userset": { "relation": "owner" } }],
+                "member": ["this", { "computed_userset": { "relation": "admin" } }]
+            },
+            "user": {
+                "org": ["this"],
+                "editor": ["this"],
+                "viewer": [
+                    "this",
+                    { "computed_userset": { "relation": "editor" } },
+                    { "tuple_to_userset": { "tupleset": "org", "computed_userset": "member" } }
+                ]
+            },
+            "document": {
+                "org": ["this"],
+                "owner": ["this"],
+                "editor": [
+                    "this",
+                    { "computed_userset": { "relation": "owner" } },
+                    { "tuple_to_userset": { "tupleset": "org", "computed_userset": "admin" } }
+                ],
+                "viewer": [
+                    "this",
+                    { "computed_userset": { "relation": "editor" } },
+                    { "tuple_to_userset": { "tupleset": "org", "computed_userset": "member" } }
+                ]
+            }
+        }))
+        .unwrap_or_else(|_| unreachable!());
+
+        Self::new(types).unwrap_or_else(|_| unreachable!())
+    }
+}
+
+/// The subjects directly related to an object.
+async fn subjects<'e, E>(
+    executor: E,
+    object: &ObjectRef,
+    relation: &str,
+) -> Result<Vec<SubjectRef>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let tuples = sqlx::query_as::<_, TupleRow>(
+        // language=PostgreSQL
+        r#"
+          SELECT *
+          FROM app_private.relation_tuples
+          WHERE object_type = $1 AND object_id = $2 AND relation = $3
+      "#,
+    )
+    .bind(&object.object_type)
+    .bind(&object.object_id)
+    .bind(relation)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(tuples
+        .into_iter()
+        .map(|row| RelationTuple::from(row).subject)
+        .collect())
+}
+
+/// The stored tuples matching every given part, ordered by object.
+pub async fn read_tuples<'e, E>(
+    executor: E,
+    object: Option<&ObjectRef>,
+    relation: Option<&str>,
+    subject: Option<&SubjectRef>,
+) -> Result<Vec<RelationTuple>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let tuples = sqlx::query_as::<_, TupleRow>(
+        // language=PostgreSQL
+        r#"
+          SELECT *
+          FROM app_private.relation_tuples
+          WHERE ($1::text IS NULL OR (object_type = $1 AND object_id = $2))
+          AND ($3::text IS NULL OR relation = $3)
+          AND ($4::text IS NULL OR (subject_type = $4 AND subject_id = $5 AND subject_relation = $6))
+          ORDER BY object_type, object_id, relation, subject_type, subject_id, subject_relation
+      "#,
+    )
+    .bind(object.map(|object| &object.object_type))
+    .bind(object.map(|object| &object.object_id))
+    .bind(relation)
+    .bind(subject.map(|subject| &subject.object.object_type))
+    .bind(subject.map(|subject| &subject.object.object_id))
+    .bind(subject.map(|subject| subject.relation.as_deref().unwrap_or_default()))
+    .fetch_all(executor)
+    .await?;
+
+    Ok(tuples.into_iter().map(RelationTuple::from).collect())
+}
+
+/// Store a tuple, doing nothing if it already exists.
+pub async fn write_tuple<'e, E>(executor: E, tuple: &RelationTuple) -> Result<(), Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app_private.relation_tuples (object_type, object_id, relation, subject_type, subject_id, subject_relation)
+          VALUES ($1, $2, $3, $4, $5, $6)
+          ON CONFLICT DO NOTHING
+      "#,
+    )
+    .bind(&tuple.object.object_type)
+    .bind(&tuple.object.object_id)
+    .bind(&tuple.relation)
+    .bind(&tuple.subject.object.object_type)
+    .bind(&tuple.subject.object.object_id)
+    .bind(tuple.subject.relation.as_deref().unwrap_or_default())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a tuple, doing nothing if it does not exist.
+pub async fn delete_tuple<'e, E>(executor: E, tuple: &RelationTuple) -> Result<(), Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          DELETE FROM app_private.relation_tuples
+          WHERE object_type = $1 AND object_id = $2 AND relation = $3
+          AND subject_type = $4 AND subject_id = $5 AND subject_relation = $6
+      "#,
+    )
+    .bind(&tuple.object.object_type)
+    .bind(&tuple.object.object_id)
+    .bind(&tuple.relation)
+    .bind(&tuple.subject.object.object_type)
+    .bind(&tuple.subject.object.object_id)
+    .bind(tuple.subject.relation.as_deref().unwrap_or_default())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
@@ -1,8 +1,46 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::import::FileFormat;
 
 #[derive(Parser, Debug, Default)]
 #[clap(author, version, about, long_about = None)]
 pub struct Config {
     #[clap(short, long, value_parser, default_value = "3000")]
     pub port: u16,
+    /// Days to keep deleted users before they are permanently purged
+    #[clap(long, value_parser, default_value = "30")]
+    pub purge_retention_days: u32,
+    /// A JSON Schema file user metadata must satisfy. Any object is accepted without one
+    #[clap(long, value_parser)]
+    pub metadata_schema: Option<PathBuf>,
+    /// A JSON file of the relations authorization checks evaluate. The built-in schema is used without one
+    #[clap(long, value_parser)]
+    pub authz_schema: Option<PathBuf>,
+    /// The directory uploaded files such as avatars are stored in
+    #[clap(long, value_parser, default_value = "uploads")]
+    pub upload_dir: PathBuf,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Tasks to run instead of serving the API.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Register users from a CSV or NDJSON file and print a report of every row as JSON
+    Import {
+        /// The file to import
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// The format of the file. Taken from the file extension when left out
+        #[clap(long, value_enum)]
+        format: Option<FileFormat>,
+        /// Validate every row and report what would happen, without registering anyone
+        #[clap(long)]
+        dry_run: bool,
+        /// The number of rows registered per transaction
+        #[clap(long, value_parser, default_value = "100")]
+        batch_size: usize,
+    },
 }
@@ -0,0 +1,45 @@
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// The header a request's correlation id is read from and returned in.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+tokio::task_local! {
+    static CORRELATION_ID: Uuid;
+}
+
+/// The correlation id of the request being handled, or a fresh one outside
+/// of a request, e.g. in background jobs.
+pub fn correlation_id() -> Uuid {
+    CORRELATION_ID
+        .try_with(|id| *id)
+        .unwrap_or_else(|_| Uuid::new_v4())
+}
+
+/// Tag every request with a correlation id, so that errors reported by
+/// clients can be matched with the logs, which carry it too. A valid id sent by the client, e.g.
+/// by a gateway, is kept.
+pub async fn correlate<B>(req: Request<B>, next: Next<B>) -> Response {
+    let id = req
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    let mut res = CORRELATION_ID
+        .scope(id, next.run(req))
+        .instrument(tracing::info_span!("request", correlation_id = %id))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&id.to_string()) {
+        res.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+
+    res
+}
@@ -1,58 +1,508 @@
+use std::collections::BTreeMap;
+
 use axum::{
-    http::StatusCode,
+    http::{
+        header::{ALLOW, CONTENT_LANGUAGE, CONTENT_TYPE},
+        HeaderValue, Request, StatusCode,
+    },
+    middleware::Next,
     response::{IntoResponse, Response},
-    Json,
 };
-use serde_json::json;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::{openapi::schema::Schema, ToSchema};
+use uuid::Uuid;
+
+use crate::http::{correlation::correlation_id, i18n};
+
+/// The media type errors are rendered as, see RFC 7807.
+pub const PROBLEM_JSON: &str = "application/problem+json";
 
-#[derive(thiserror::Error, Debug, utoipa::ToSchema)]
+#[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Not found")]
     NotFound,
-    #[error("Unable to start server")]
+    #[error("Internal error")]
     InternalError,
     #[error("Invalid JWT")]
     InvalidToken,
     #[error("Validation error")]
     ValidationError,
+    #[error("Validation error")]
+    InvalidFields(Vec<FieldError>),
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Email address is already in use")]
+    EmailTaken,
+    #[error("The resource already exists")]
+    Conflict,
+    #[error("Invalid reference")]
+    InvalidReference,
+    #[error("Invalid value")]
+    InvalidValue,
+    #[error("The resource has changed since it was read")]
+    PreconditionFailed,
+    #[error("The If-Match header is required")]
+    PreconditionRequired,
+    #[error("Payload too large")]
+    PayloadTooLarge,
+    #[error("Unsupported media type")]
+    UnsupportedMediaType,
+    #[error("Method not allowed")]
+    MethodNotAllowed,
+    #[error("Bad request")]
+    BadRequest(String),
+    #[error("Invalid body")]
+    InvalidBody(String),
+    #[error("Internal error")]
+    Database(sqlx::Error),
+    #[error("Internal error")]
+    Io(#[from] std::io::Error),
+    #[error("Internal error")]
+    Server(#[from] hyper::Error),
+    #[error("Internal error")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("Internal error")]
+    Json(#[from] serde_json::Error),
+    #[error("Internal error")]
+    Csv(#[from] csv::Error),
+    #[error("Internal error")]
+    Webauthn(#[from] webauthn_rs::prelude::WebauthnError),
 }
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
+/// Declare the stable codes of the errors once, as the constants
+/// [`Error::code`] returns and as [`Error::CODES`] listing them all. A code
+/// that is declared but never returned fails the build as dead code.
+macro_rules! error_codes {
+    ($($name:ident = $code:literal,)*) => {
+        mod codes {
+            $(pub const $name: &str = $code;)*
+        }
+
+        impl Error {
+            /// Every code [`Error::code`] returns, each of which has a
+            /// message in the catalogues.
+            pub const CODES: &'static [&'static str] = &[$($code),*];
+        }
+    };
+}
+
+error_codes! {
+    NOT_FOUND = "not_found",
+    INTERNAL_ERROR = "internal_error",
+    INVALID_TOKEN = "invalid_token",
+    VALIDATION_FAILED = "validation_failed",
+    INVALID_CREDENTIALS = "invalid_credentials",
+    FORBIDDEN = "forbidden",
+    EMAIL_TAKEN = "email_taken",
+    CONFLICT = "conflict",
+    INVALID_REFERENCE = "invalid_reference",
+    INVALID_VALUE = "invalid_value",
+    PRECONDITION_FAILED = "precondition_failed",
+    PRECONDITION_REQUIRED = "precondition_required",
+    PAYLOAD_TOO_LARGE = "payload_too_large",
+    UNSUPPORTED_MEDIA_TYPE = "unsupported_media_type",
+    METHOD_NOT_ALLOWED = "method_not_allowed",
+    BAD_REQUEST = "bad_request",
+    INVALID_BODY = "invalid_body",
+}
+
+impl Error {
+    /// A stable, machine readable code clients can branch on. Unlike the
+    /// title and detail, it never changes once published.
+    pub fn code(&self) -> &'static str {
         use Error::*;
 
-        let status_code = match self {
-            InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        match self {
+            NotFound => codes::NOT_FOUND,
+            InternalError | Database(_) | Io(_) | Server(_) | Jwt(_) | Json(_) | Csv(_)
+            | Webauthn(_) => codes::INTERNAL_ERROR,
+            InvalidToken => codes::INVALID_TOKEN,
+            ValidationError | InvalidFields(_) => codes::VALIDATION_FAILED,
+            InvalidCredentials => codes::INVALID_CREDENTIALS,
+            Forbidden => codes::FORBIDDEN,
+            EmailTaken => codes::EMAIL_TAKEN,
+            Conflict => codes::CONFLICT,
+            InvalidReference => codes::INVALID_REFERENCE,
+            InvalidValue => codes::INVALID_VALUE,
+            PreconditionFailed => codes::PRECONDITION_FAILED,
+            PreconditionRequired => codes::PRECONDITION_REQUIRED,
+            PayloadTooLarge => codes::PAYLOAD_TOO_LARGE,
+            UnsupportedMediaType => codes::UNSUPPORTED_MEDIA_TYPE,
+            MethodNotAllowed => codes::METHOD_NOT_ALLOWED,
+            BadRequest(_) => codes::BAD_REQUEST,
+            InvalidBody(_) => codes::INVALID_BODY,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        use Error::*;
+
+        match self {
+            InternalError | Database(_) | Io(_) | Server(_) | Jwt(_) | Json(_) | Csv(_)
+            | Webauthn(_) => StatusCode::INTERNAL_SERVER_ERROR,
             NotFound => StatusCode::NOT_FOUND,
-            InvalidToken | ValidationError => StatusCode::BAD_REQUEST,
+            InvalidToken | ValidationError | BadRequest(_) => StatusCode::BAD_REQUEST,
+            InvalidFields(_) | InvalidReference | InvalidValue | InvalidBody(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            InvalidCredentials => StatusCode::UNAUTHORIZED,
+            Forbidden => StatusCode::FORBIDDEN,
+            EmailTaken | Conflict => StatusCode::CONFLICT,
+            PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        }
+    }
+
+    /// A short summary of the error, in the language of the request.
+    pub fn title(&self) -> String {
+        i18n::message(self.code(), None).unwrap_or_else(|| self.to_string())
+    }
+
+    /// An explanation of the error meant for people, in the language of the
+    /// request. Internal errors never say more than that something went wrong.
+    /// Bad requests and bodies are explained by the rejection of the
+    /// extractor instead, which is always in English.
+    pub fn detail(&self) -> String {
+        let attribute = match self {
+            Error::BadRequest(detail) | Error::InvalidBody(detail) => return detail.clone(),
+            Error::InvalidFields(_) => "fields",
+            _ => "detail",
         };
 
-        let body = Json(json!({ "error": self.to_string() }));
+        i18n::message(self.code(), Some(attribute)).unwrap_or_else(|| self.to_string())
+    }
+
+    /// Turn the rejection of an axum extractor into an error, keeping the
+    /// status it would have been sent with and its explanation, such as the
+    /// field a JSON body is missing.
+    pub fn from_rejection<R>(rejection: R) -> Self
+    where
+        R: IntoResponse + std::error::Error,
+    {
+        let mut detail = rejection.to_string();
+        let mut source = rejection.source();
 
-        (status_code, body).into_response()
+        // Composite rejections repeat the message of the rejection they wrap.
+        while let Some(err) = source {
+            let message = err.to_string();
+
+            if !detail.ends_with(&message) {
+                detail = format!("{detail}: {message}");
+            }
+
+            source = err.source();
+        }
+
+        match rejection.into_response().status() {
+            StatusCode::PAYLOAD_TOO_LARGE => Error::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => Error::UnsupportedMediaType,
+            StatusCode::UNPROCESSABLE_ENTITY => Error::InvalidBody(detail),
+            status if status.is_client_error() => Error::BadRequest(detail),
+            _ => {
+                tracing::error!("Unexpected extractor rejection: {}", detail);
+                Error::InternalError
+            }
+        }
+    }
+
+    /// Reject the value of a single field, for the checks a validator can't
+    /// express, such as query parameters or values checked against the data.
+    /// `field` is named the way clients send it.
+    pub fn invalid_field(field: &str, code: &str) -> Self {
+        Error::InvalidFields(vec![FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            params: BTreeMap::new(),
+            message: default_message(code),
+        }])
+    }
+
+    /// Name the invalid fields by their path from `parent`, for errors of an
+    /// item nested in a body, e.g. `writes[2]`. Other errors are unchanged.
+    pub fn nested(self, parent: &str) -> Self {
+        match self {
+            Error::InvalidFields(errors) => Error::InvalidFields(
+                errors
+                    .into_iter()
+                    .map(|error| FieldError {
+                        field: format!("{parent}.{}", error.field),
+                        ..error
+                    })
+                    .collect(),
+            ),
+            err => err,
+        }
+    }
+
+    pub fn problem(&self, correlation_id: Uuid) -> Problem {
+        let code = self.code();
+
+        Problem {
+            problem_type: format!("urn:cdb:error:{code}"),
+            title: self.title(),
+            status: self.status().as_u16(),
+            detail: self.detail(),
+            code: code.to_string(),
+            correlation_id,
+            errors: match self {
+                Error::InvalidFields(errors) => errors.clone(),
+                _ => Vec::new(),
+            },
+        }
     }
 }
 
-impl From<validator::ValidationErrors> for Error {
-    fn from(_validation: validator::ValidationErrors) -> Self {
-        Error::ValidationError
+/// An error as rendered to clients, following RFC 7807.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    /// A URI identifying the kind of error
+    #[schema(example = "urn:cdb:error:not_found")]
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// A short summary, in the language negotiated from `Accept-Language`
+    #[schema(example = "Not found")]
+    pub title: String,
+    #[schema(example = 404)]
+    pub status: u16,
+    #[schema(example = "The requested resource does not exist.")]
+    pub detail: String,
+    /// A stable code identifying the kind of error
+    #[schema(example = "not_found")]
+    pub code: String,
+    /// Identifies the request in the logs, also sent in the `X-Correlation-Id` header
+    #[schema(example = "0b6e4ab4-6d4e-4bd6-9d9d-31c5d3e0aa2b")]
+    pub correlation_id: Uuid,
+    /// The invalid fields, for validation errors
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// Why the value of a field was rejected. Nested fields are named by their
+/// path, e.g. `members[2].email`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    #[schema(example = "password")]
+    pub field: String,
+    /// The validator that rejected the value
+    #[schema(example = "length")]
+    pub code: String,
+    /// The arguments of the validator, such as the bounds of a length
+    #[schema(value_type = Object, example = json!({ "min": 8 }))]
+    pub params: BTreeMap<String, Value>,
+    /// In the language negotiated from `Accept-Language`, unless the
+    /// validator sets a message of its own, which is always in English
+    #[schema(example = "Too short or too long")]
+    pub message: String,
+}
+
+/// Handlers document their errors as `body = Error`, which is rendered as a
+/// [`Problem`].
+impl ToSchema for Error {
+    fn schema() -> Schema {
+        Problem::schema()
     }
 }
 
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let correlation_id = correlation_id();
+        let problem = self.problem(correlation_id);
+
+        if self.status().is_server_error() {
+            tracing::error!(%correlation_id, code = problem.code, "{:?}", self);
+        } else {
+            tracing::debug!(%correlation_id, code = problem.code, "{}", self);
+        }
+
+        let body = match serde_json::to_vec(&problem) {
+            Ok(body) => body,
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        };
+
+        let mut res = (self.status(), body).into_response();
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+
+        if let Ok(locale) = HeaderValue::from_str(&i18n::locale().to_string()) {
+            res.headers_mut().insert(CONTENT_LANGUAGE, locale);
+        }
+
+        res
+    }
+}
+
+/// Render the `405` of a route without a handler for the method as a problem,
+/// keeping the `Allow` header listing the methods it does have.
+pub async fn method_not_allowed<B>(req: Request<B>, next: Next<B>) -> Response {
+    let res = next.run(req).await;
+
+    if res.status() != StatusCode::METHOD_NOT_ALLOWED || res.headers().contains_key(CONTENT_TYPE) {
+        return res;
+    }
+
+    let allow = res.headers().get(ALLOW).cloned();
+    let mut res = Error::MethodNotAllowed.into_response();
+
+    if let Some(allow) = allow {
+        res.headers_mut().insert(ALLOW, allow);
+    }
+
+    res
+}
+
+/// Render the `413` of a body limit as a problem, whether the limit layer
+/// refused the request up front or the body overflowed while being read.
+pub async fn payload_too_large<B>(req: Request<B>, next: Next<B>) -> Response {
+    let res = next.run(req).await;
+
+    let is_problem = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|content_type| content_type == PROBLEM_JSON);
+
+    if res.status() != StatusCode::PAYLOAD_TOO_LARGE || is_problem {
+        return res;
+    }
+
+    Error::PayloadTooLarge.into_response()
+}
+
+/// The SQLSTATE codes of the errors Postgres reports, see
+/// <https://www.postgresql.org/docs/current/errcodes-appendix.html>.
+pub mod sqlstate {
+    pub const FOREIGN_KEY_VIOLATION: &str = "23503";
+    pub const UNIQUE_VIOLATION: &str = "23505";
+    pub const CHECK_VIOLATION: &str = "23514";
+}
+
+/// Translate constraint violations into the errors clients can act on. A
+/// violation of a known constraint maps to its domain error, and otherwise
+/// to the error for its SQLSTATE. Anything else is an internal error.
 impl From<sqlx::Error> for Error {
-    fn from(_err: sqlx::Error) -> Self {
-        Error::InternalError
+    fn from(err: sqlx::Error) -> Self {
+        use sqlstate::*;
+
+        let translated = match &err {
+            sqlx::Error::Database(db) => match (db.code().as_deref(), db.constraint()) {
+                (Some(UNIQUE_VIOLATION), Some("accounts_email_key")) => Some(Error::EmailTaken),
+                (Some(UNIQUE_VIOLATION), _) => Some(Error::Conflict),
+                (Some(FOREIGN_KEY_VIOLATION), _) => Some(Error::InvalidReference),
+                (Some(CHECK_VIOLATION), _) => Some(Error::InvalidValue),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match translated {
+            Some(translated) => {
+                tracing::debug!("Translated database error: {}", err);
+                translated
+            }
+            None => Error::Database(err),
+        }
+    }
+}
+
+impl From<validator::ValidationErrors> for Error {
+    fn from(validation: validator::ValidationErrors) -> Self {
+        let mut errors = Vec::new();
+        collect_field_errors(None, &validation, &mut errors);
+
+        errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+        Error::InvalidFields(errors)
     }
 }
 
-impl From<hyper::Error> for Error {
-    fn from(_err: hyper::Error) -> Self {
-        Error::InternalError
+/// Flatten the errors of nested structs and lists, naming each field by its
+/// path from the body, e.g. `members[2].email`.
+fn collect_field_errors(
+    parent: Option<&str>,
+    validation: &validator::ValidationErrors,
+    errors: &mut Vec<FieldError>,
+) {
+    use validator::ValidationErrorsKind;
+
+    for (field, kind) in validation.errors() {
+        let path = match parent {
+            Some(parent) => format!("{parent}.{}", camel_case(field)),
+            None => camel_case(field),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                errors.extend(field_errors.iter().map(|error| {
+                    FieldError {
+                        field: path.clone(),
+                        code: error.code.to_string(),
+                        // The rejected value is left out, as it may be a password.
+                        params: error
+                            .params
+                            .iter()
+                            .filter(|(name, _)| *name != "value")
+                            .map(|(name, value)| (name.to_string(), value.clone()))
+                            .collect(),
+                        message: error
+                            .message
+                            .as_ref()
+                            .map_or_else(|| default_message(&error.code), ToString::to_string),
+                    }
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => {
+                collect_field_errors(Some(&path), nested, errors);
+            }
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(Some(&format!("{path}[{index}]")), nested, errors);
+                }
+            }
+        }
     }
 }
 
-impl From<jsonwebtoken::errors::Error> for Error {
-    fn from(_err: jsonwebtoken::errors::Error) -> Self {
-        Error::InternalError
+/// Request bodies are camel cased, so name fields the way clients sent them.
+fn camel_case(field: &str) -> String {
+    let mut parts = field.split('_');
+    let mut name = parts.next().unwrap_or_default().to_string();
+
+    for part in parts {
+        let mut chars = part.chars();
+
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.push_str(chars.as_str());
+        }
     }
+
+    name
+}
+
+/// The codes of the validators and field checks with a message in the
+/// catalogues, as `validation-<code>`. Other codes read as `invalid`.
+pub const VALIDATION_CODES: &[&str] = &[
+    "email",
+    "length",
+    "range",
+    "url",
+    "must_match",
+    "regex",
+    "required",
+    "invalid",
+];
+
+/// The message of a validator without one of its own, in the language of
+/// the request.
+fn default_message(code: &str) -> String {
+    i18n::message(&format!("validation-{code}"), None)
+        .or_else(|| i18n::message("validation-invalid", None))
+        .unwrap_or_else(|| "Invalid value".to_string())
 }
@@ -0,0 +1,117 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, RequestParts},
+    http::{
+        header::{ETAG, IF_MATCH, IF_NONE_MATCH},
+        HeaderValue, Method, Request, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
+};
+use chrono::{DateTime, Utc};
+
+use crate::Error;
+
+/// A strong entity tag identifying one version of a resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(String);
+
+impl ETag {
+    /// Derive the tag from the last time the resource changed.
+    pub fn from_version(version: DateTime<Utc>) -> Self {
+        ETag(format!("\"{:x}\"", version.timestamp_micros()))
+    }
+
+    /// Whether the tag is listed in an `If-Match` or `If-None-Match` header.
+    /// Weak tags in the header only match when `weak` comparison is allowed.
+    fn is_listed_in(&self, header: &HeaderValue, weak: bool) -> bool {
+        let Ok(header) = header.to_str() else {
+            return false;
+        };
+
+        header.split(',').map(str::trim).any(|candidate| {
+            if candidate == "*" {
+                return true;
+            }
+
+            match candidate.strip_prefix("W/") {
+                Some(candidate) => weak && candidate == self.0,
+                None => candidate == self.0,
+            }
+        })
+    }
+}
+
+impl IntoResponseParts for ETag {
+    type Error = Error;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let value = HeaderValue::from_str(&self.0).map_err(|err| {
+            tracing::error!(%err, "Invalid entity tag {}", self.0);
+            Error::InternalError
+        })?;
+        res.headers_mut().insert(ETAG, value);
+
+        Ok(res)
+    }
+}
+
+/// Middleware to extract the `If-Match` header of a request that modifies a
+/// resource. Handlers check it against the current version with
+/// [`IfMatch::check`] once they know it.
+#[derive(Debug)]
+pub struct IfMatch(Option<HeaderValue>);
+
+impl IfMatch {
+    /// Fail unless the client sent the tag of the current version, so that
+    /// changes made since the client read the resource are not overwritten.
+    pub fn check(&self, current: &ETag) -> Result<(), Error> {
+        let header = self.0.as_ref().ok_or(Error::PreconditionRequired)?;
+
+        if current.is_listed_in(header, false) {
+            Ok(())
+        } else {
+            Err(Error::PreconditionFailed)
+        }
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for IfMatch
+where
+    B: Send,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        Ok(IfMatch(req.headers().get(IF_MATCH).cloned()))
+    }
+}
+
+/// Answer conditional `GET` requests with `304 Not Modified` when the
+/// response carries an `ETag` listed in the `If-None-Match` header.
+pub async fn conditional_get<B>(req: Request<B>, next: Next<B>) -> Response {
+    let if_none_match = match *req.method() {
+        Method::GET | Method::HEAD => req.headers().get(IF_NONE_MATCH).cloned(),
+        _ => None,
+    };
+
+    let res = next.run(req).await;
+
+    let Some(if_none_match) = if_none_match else {
+        return res;
+    };
+
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| ETag(etag.to_string()));
+
+    match etag {
+        Some(etag) if res.status().is_success() && etag.is_listed_in(&if_none_match, true) => {
+            (StatusCode::NOT_MODIFIED, etag, ()).into_response()
+        }
+        _ => res,
+    }
+}
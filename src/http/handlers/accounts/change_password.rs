@@ -0,0 +1,55 @@
+use axum::{http::StatusCode, Extension};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+    http::{extract::ValidatedJson, jwt::NotImpersonated},
+    Error,
+};
+
+#[derive(Debug, Deserialize, Validate, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordBody {
+    #[schema(example = "Sm4rT.HuLk")]
+    pub current_password: String,
+    #[schema(example = "Sm4sH.HuLk")]
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/accounts/password",
+    request_body = ChangePasswordBody,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Malformed JSON body", body = Error),
+        (status = 401, description = "Invalid credentials", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn change_password(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+    ValidatedJson(payload): ValidatedJson<ChangePasswordBody>,
+) -> Result<StatusCode, Error> {
+    let changed = sqlx::query_scalar::<_, bool>(
+        // language=PostgreSQL
+        r#"SELECT app.change_password($1, $2, $3)"#,
+    )
+    .bind(claims.sub)
+    .bind(payload.current_password)
+    .bind(payload.new_password)
+    .fetch_one(&pool)
+    .await?;
+
+    if !changed {
+        return Err(Error::InvalidCredentials);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
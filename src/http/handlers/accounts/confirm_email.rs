@@ -0,0 +1,81 @@
+use axum::{http::StatusCode, Extension};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{audit, http::extract::Json, Error};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmEmailBody {
+    /// The token from the link sent to the new address
+    #[schema(example = "0b9c3f5e-7a1d-4c8e-9f2b-6d4e8a1c3b5f")]
+    pub token: Uuid,
+}
+
+#[derive(Debug, FromRow)]
+struct EmailChange {
+    user_id: Uuid,
+    new_email: String,
+}
+
+/// Confirm a pending email address change. The new address counts as
+/// verified since the token could only have been read from its inbox.
+#[utoipa::path(
+    post,
+    path = "/accounts/email/confirm",
+    request_body = ConfirmEmailBody,
+    responses(
+        (status = 204, description = "Email address changed"),
+        (status = 404, description = "Unknown or expired token", body = Error),
+        (status = 409, description = "Email address is already in use", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn confirm_email_change(
+    Extension(pool): Extension<PgPool>,
+    Json(payload): Json<ConfirmEmailBody>,
+) -> Result<StatusCode, Error> {
+    let mut tx = pool.begin().await?;
+
+    let change = sqlx::query_as::<_, EmailChange>(
+        // language=PostgreSQL
+        r#"
+            DELETE FROM app_private.email_changes
+            WHERE token = $1 AND expires_at > now()
+            RETURNING user_id, new_email
+        "#,
+    )
+    .bind(payload.token)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    // The address may have been taken since the change was requested.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+            UPDATE app_private.accounts
+            SET email = $2, email_verified_at = now()
+            WHERE user_id = $1
+        "#,
+    )
+    .bind(change.user_id)
+    .bind(&change.new_email)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(change.user_id),
+        Some(change.user_id),
+        "email.change_confirmed",
+        serde_json::json!({ "newEmail": change.new_email }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
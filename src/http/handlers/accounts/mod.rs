@@ -1,3 +1,7 @@
+mod change_password;
+mod confirm_email;
 mod register;
 
+pub use change_password::*;
+pub use confirm_email::*;
 pub use register::*;
@@ -1,4 +1,4 @@
-use axum::{Extension, Json};
+use axum::Extension;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
@@ -6,7 +6,13 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::Error;
+use crate::{
+    http::{
+        extract::{Json, ValidatedJson},
+        rls::ScopedTx,
+    },
+    Error,
+};
 
 #[derive(FromRow, Serialize, Debug, Default, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -39,15 +45,17 @@ pub struct RegisterBody {
     request_body = RegisterBody,
     responses(
         (status = 200, description = "Registration successful", body = RegisterResponse),
-        (status = 401, description = "Invalid refresh token", body = Error),
+        (status = 409, description = "Email address is already in use", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
         (status = 500, description = "Internal error", body = Error)
     )
 )]
 pub async fn register(
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<RegisterBody>,
+    ValidatedJson(payload): ValidatedJson<RegisterBody>,
 ) -> Result<Json<RegisterResponse>, Error> {
-    payload.validate()?;
+    // Registering never acts as anyone, whatever token comes with the request.
+    let mut tx = ScopedTx::begin(&pool, None).await?;
 
     let register_response = sqlx::query_as::<_, RegisterResponse>(
         // language=PostgresQL
@@ -60,8 +68,10 @@ pub async fn register(
     .bind(payload.last_name)
     .bind(payload.email)
     .bind(payload.password)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(Json(register_response))
 }
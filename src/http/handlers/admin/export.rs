@@ -0,0 +1,214 @@
+use axum::{
+    body::{Bytes, StreamBody},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+    Extension,
+};
+use futures::{stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use tokio::sync::mpsc;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    http::{
+        extract::Query,
+        handlers::users::{UserFilter, UsersResponse},
+        jwt::Permissions,
+        tenant::TenantScope,
+    },
+    import::FileFormat,
+    Error,
+};
+
+/// The size the body is sent in, in bytes. Rows are buffered until a chunk is full.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of chunks that can wait for a slow client before reading rows pauses.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/// The query parameters of a bulk export, on top of the users list filters.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    /// `csv` or `ndjson`
+    #[param(inline)]
+    pub format: FileFormat,
+}
+
+/// A user as a CSV record, with the metadata flattened to a JSON string.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CsvRecord<'a> {
+    id: Uuid,
+    first_name: Option<&'a str>,
+    last_name: Option<&'a str>,
+    email: &'a str,
+    display_name: Option<&'a str>,
+    locale: Option<&'a str>,
+    timezone: Option<&'a str>,
+    metadata: String,
+    avatar_url: Option<&'a str>,
+    created_at: i64,
+    updated_at: Option<i64>,
+    deactivated_at: Option<i64>,
+    deleted_at: Option<i64>,
+}
+
+impl<'a> From<&'a UsersResponse> for CsvRecord<'a> {
+    fn from(user: &'a UsersResponse) -> Self {
+        CsvRecord {
+            id: user.id,
+            first_name: user.first_name.as_deref(),
+            last_name: user.last_name.as_deref(),
+            email: &user.email,
+            display_name: user.display_name.as_deref(),
+            locale: user.locale.as_deref(),
+            timezone: user.timezone.as_deref(),
+            metadata: user.metadata.to_string(),
+            avatar_url: user.avatar_url.as_deref(),
+            created_at: user.created_at.timestamp_millis(),
+            updated_at: user.updated_at.map(|date| date.timestamp_millis()),
+            deactivated_at: user.deactivated_at.map(|date| date.timestamp_millis()),
+            deleted_at: user.deleted_at.map(|date| date.timestamp_millis()),
+        }
+    }
+}
+
+/// Stream every user of the tenant matching the filters as CSV or NDJSON.
+/// Rows are read from a database cursor as the client downloads them, so the
+/// export takes the same memory however many users there are.
+#[utoipa::path(
+    get,
+    path = "/admin/users/export",
+    responses(
+        (status = 200, description = "The matching users, one per row", content_type = ["text/csv", "application/x-ndjson"]),
+        (status = 400, description = "Unknown format or malformed filter", body = Error),
+        (status = 403, description = "Missing the users:export permission, or only admins can export inactive users", body = Error),
+        (status = 422, description = "Invalid role or sort", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(ExportParams, UserFilter)
+)]
+pub async fn export_users(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    scope: TenantScope,
+    Query(params): Query<ExportParams>,
+    Query(filter): Query<UserFilter>,
+) -> Result<impl IntoResponse, Error> {
+    permissions.require("users:export")?;
+
+    if filter.include_inactive == Some(true) && !scope.claims.is_admin() {
+        return Err(Error::Forbidden);
+    }
+
+    let sort = filter.sort()?;
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        // language=PostgreSQL
+        r#"
+          SELECT u.*, a.email
+          FROM app.users AS u
+          LEFT JOIN app_private.accounts AS a
+          ON a.user_id = u.id
+          WHERE TRUE
+      "#,
+    );
+
+    scope.push_conditions(&mut query);
+    filter.push_conditions(&mut query)?;
+    sort.push_unpaginated_order_by(&mut query);
+
+    audit::record(
+        &pool,
+        Some(scope.claims.sub),
+        None,
+        "user.export",
+        json!({ "format": params.format.extension(), "organization_id": scope.org_id }),
+    )
+    .await?;
+
+    let (sender, receiver) = mpsc::channel::<Result<Bytes, Error>>(CHUNKS_IN_FLIGHT);
+    let format = params.format;
+
+    tokio::spawn(async move {
+        let mut rows = query.build_query_as::<UsersResponse>().fetch(&pool);
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        let mut is_first = true;
+
+        loop {
+            let item = match rows.try_next().await {
+                Ok(Some(user)) => match encode(format, &user, is_first, &mut chunk) {
+                    Ok(()) => {
+                        is_first = false;
+
+                        if chunk.len() < CHUNK_SIZE {
+                            continue;
+                        }
+
+                        Ok(Bytes::from(std::mem::take(&mut chunk)))
+                    }
+                    Err(err) => Err(err),
+                },
+                Ok(None) if chunk.is_empty() => break,
+                Ok(None) => Ok(Bytes::from(std::mem::take(&mut chunk))),
+                Err(err) => Err(err.into()),
+            };
+
+            let failed = item.is_err();
+
+            // Stop reading rows once the client has gone away.
+            if sender.send(item).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    // Sending an error aborts the response, so clients can tell that the
+    // export is incomplete.
+    let body = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|item| (item, receiver))
+    });
+
+    Ok((
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"users.{}\"", format.extension()),
+            ),
+        ],
+        StreamBody::new(body),
+    ))
+}
+
+/// Append a user to the chunk. The CSV header is written before the first user.
+fn encode(
+    format: FileFormat,
+    user: &UsersResponse,
+    is_first: bool,
+    chunk: &mut Vec<u8>,
+) -> Result<(), Error> {
+    match format {
+        FileFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(is_first)
+                .from_writer(chunk);
+
+            writer.serialize(CsvRecord::from(user))?;
+            writer.flush()?;
+
+            Ok(())
+        }
+        FileFormat::Ndjson => {
+            serde_json::to_writer(&mut *chunk, user)?;
+            chunk.push(b'\n');
+
+            Ok(())
+        }
+    }
+}
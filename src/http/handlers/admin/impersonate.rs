@@ -0,0 +1,97 @@
+use axum::Extension;
+use jsonwebtoken::{encode, Algorithm, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    audit,
+    http::{
+        extract::{Json, Path, ValidatedJson},
+        jwt::{Admin, Claims, Role},
+        tenant::active_org,
+    },
+    Error, KEYS,
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonateResponse {
+    pub token_type: &'static str,
+    pub access_token: String,
+    pub expires_in: i64,
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub impersonator: Uuid,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonateBody {
+    #[schema(example = "Reproducing ticket #1024")]
+    #[validate(length(min = 1))]
+    pub reason: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/impersonate",
+    request_body = ImpersonateBody,
+    responses(
+        (status = 200, description = "Impersonation token issued", body = ImpersonateResponse),
+        (status = 400, description = "Malformed JSON body", body = Error),
+        (status = 403, description = "Not an admin, or the target cannot be impersonated", body = Error),
+        (status = 404, description = "User not found", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the user to act as")
+    )
+)]
+pub async fn impersonate(
+    Extension(pool): Extension<PgPool>,
+    Admin(admin): Admin,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<ImpersonateBody>,
+) -> Result<Json<ImpersonateResponse>, Error> {
+    let role: Role = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT role FROM app_private.accounts WHERE user_id = $1"#,
+    )
+    .bind(id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound)?
+    .into();
+
+    if role == Role::Admin {
+        return Err(Error::Forbidden);
+    }
+
+    audit::record(
+        &pool,
+        Some(admin.sub),
+        Some(id),
+        "impersonation.start",
+        json!({ "reason": payload.reason }),
+    )
+    .await?;
+
+    let org_id = active_org(&pool, id).await?;
+    let claims = Claims::impersonate(id, role, admin.sub).with_org(org_id);
+
+    let header = Header::new(Algorithm::HS512);
+    let access_token = encode(&header, &claims, &KEYS.encoding)?;
+
+    tracing::info!("User `{}` is impersonating user `{}`", admin.sub, id);
+
+    Ok(Json(ImpersonateResponse {
+        token_type: "Bearer",
+        access_token,
+        expires_in: claims.expires_in(),
+        impersonator: admin.sub,
+    }))
+}
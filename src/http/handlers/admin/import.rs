@@ -0,0 +1,86 @@
+use axum::{
+    body::Bytes,
+    http::{header::CONTENT_TYPE, HeaderMap},
+    Extension,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use utoipa::IntoParams;
+
+use crate::{
+    http::{
+        extract::{Json, Query},
+        jwt::Permissions,
+    },
+    import::{self, FileFormat, ImportOptions, ImportReport, DEFAULT_BATCH_SIZE},
+    Error,
+};
+
+/// The largest file that can be imported, in bytes.
+pub const MAX_IMPORT_SIZE: usize = 10 * 1024 * 1024;
+
+/// The query parameters of a bulk import.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// `csv` or `ndjson`. Taken from the `Content-Type` header when left out
+    #[param(inline)]
+    pub format: Option<FileFormat>,
+    /// Validate every row and report what would happen, without registering anyone
+    pub dry_run: Option<bool>,
+    /// The number of rows registered per transaction, 100 by default
+    #[param(example = 100)]
+    pub batch_size: Option<usize>,
+}
+
+/// Register users from a CSV or NDJSON file, with the same fields and rules
+/// as `POST /accounts/register`. Rows are reported on one by one, and a row
+/// that fails does not stop the others from being registered.
+#[utoipa::path(
+    post,
+    path = "/admin/users/import",
+    request_body(content = String, content_type = "text/csv", description = "A CSV file with a header row, or an NDJSON file"),
+    responses(
+        (status = 200, description = "The outcome of every row", body = ImportReport),
+        (status = 400, description = "Unknown format or malformed batch size", body = Error),
+        (status = 403, description = "Missing the users:import permission", body = Error),
+        (status = 413, description = "The file is larger than 10 MiB", body = Error),
+        (status = 422, description = "No format given or known from `Content-Type`, or a batch size of 0", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(ImportParams)
+)]
+pub async fn import_users(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ImportReport>, Error> {
+    permissions.require("users:import")?;
+
+    let format = params
+        .format
+        .or_else(|| {
+            headers
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .and_then(FileFormat::from_content_type)
+        })
+        .ok_or_else(|| Error::invalid_field("format", "required"))?;
+
+    let options = ImportOptions {
+        dry_run: params.dry_run.unwrap_or(false),
+        batch_size: params.batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+    };
+
+    if options.batch_size == 0 {
+        return Err(Error::invalid_field("batch_size", "range"));
+    }
+
+    let report = import::import_users(&pool, import::parse(format, &body), options).await?;
+
+    import::record(&pool, Some(permissions.claims.sub), &report).await?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,9 @@
+mod export;
+mod impersonate;
+mod import;
+mod user_status;
+
+pub use export::*;
+pub use impersonate::*;
+pub use import::*;
+pub use user_status::*;
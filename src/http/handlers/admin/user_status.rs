@@ -0,0 +1,199 @@
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    http::{
+        etag::{ETag, IfMatch},
+        extract::{Json, Path},
+        handlers::users::UsersResponse,
+        jwt::Permissions,
+        tenant::can_see_user,
+    },
+    Error,
+};
+
+/// Refuse to change the status of a user out of reach of the session. The
+/// permission may be granted by the groups of an organization, so only
+/// administrators may change anyone; others may only change the members of
+/// their organization, and never an administrator.
+async fn require_reachable(
+    pool: &PgPool,
+    permissions: &Permissions,
+    id: Uuid,
+) -> Result<(), Error> {
+    let claims = &permissions.claims;
+
+    if claims.is_admin() {
+        return Ok(());
+    }
+
+    if !can_see_user(pool, claims, id).await? {
+        return Err(Error::NotFound);
+    }
+
+    let is_admin = sqlx::query_scalar::<_, bool>(
+        // language=PostgreSQL
+        r#"SELECT role = 'admin' FROM app_private.accounts WHERE user_id = $1"#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(false);
+
+    if is_admin {
+        Err(Error::Forbidden)
+    } else {
+        Ok(())
+    }
+}
+
+/// Lock a user until the end of the transaction and return the entity tag of
+/// its current version. Unlike [`lock_user`], deleted users are found too, so
+/// that they can be restored.
+///
+/// [`lock_user`]: crate::http::handlers::users::lock_user
+async fn lock_any_user<'e, E>(executor: E, id: Uuid) -> Result<ETag, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let version = sqlx::query_scalar::<_, DateTime<Utc>>(
+        // language=PostgreSQL
+        r#"SELECT coalesce(updated_at, created_at) FROM app.users WHERE id = $1 FOR UPDATE"#,
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(ETag::from_version(version))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/deactivate",
+    responses(
+        (status = 200, description = "User deactivated, tagged with its new version in the `ETag` header", body = UsersResponse),
+        (status = 403, description = "Missing the users:deactivate permission, or the user is an administrator", body = Error),
+        (status = 404, description = "User not found, out of reach or already inactive", body = Error),
+        (status = 412, description = "The user has changed since the version in `If-Match`", body = Error),
+        (status = 428, description = "The `If-Match` header is missing", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the user to deactivate"),
+        ("If-Match" = String, Header, description = "The `ETag` of the version being deactivated")
+    )
+)]
+pub async fn deactivate_user(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+    if_match: IfMatch,
+) -> Result<(ETag, Json<UsersResponse>), Error> {
+    permissions.require("users:deactivate")?;
+    require_reachable(&pool, &permissions, id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    if_match.check(&lock_any_user(&mut tx, id).await?)?;
+
+    let user = sqlx::query_as::<_, UsersResponse>(
+        // language=PostgreSQL
+        r#"
+          WITH u AS (
+            UPDATE app.users
+            SET deactivated_at = now()
+            WHERE id = $1 AND deactivated_at IS NULL AND deleted_at IS NULL
+            RETURNING *
+          )
+          SELECT u.*, a.email
+          FROM u
+          LEFT JOIN app_private.accounts AS a
+          ON a.user_id = u.id
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        Some(id),
+        "user.deactivate",
+        json!({}),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((user.etag(), Json(user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/restore",
+    responses(
+        (status = 200, description = "User restored, tagged with its new version in the `ETag` header", body = UsersResponse),
+        (status = 403, description = "Missing the users:deactivate permission, or the user is an administrator", body = Error),
+        (status = 404, description = "User not found, out of reach or already active", body = Error),
+        (status = 412, description = "The user has changed since the version in `If-Match`", body = Error),
+        (status = 428, description = "The `If-Match` header is missing", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the user to restore"),
+        ("If-Match" = String, Header, description = "The `ETag` of the version being restored, or `*` for any")
+    )
+)]
+pub async fn restore_user(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+    if_match: IfMatch,
+) -> Result<(ETag, Json<UsersResponse>), Error> {
+    permissions.require("users:deactivate")?;
+    require_reachable(&pool, &permissions, id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    if_match.check(&lock_any_user(&mut tx, id).await?)?;
+
+    let user = sqlx::query_as::<_, UsersResponse>(
+        // language=PostgreSQL
+        r#"
+          WITH u AS (
+            UPDATE app.users
+            SET deactivated_at = NULL, deleted_at = NULL
+            WHERE id = $1 AND (deactivated_at IS NOT NULL OR deleted_at IS NOT NULL)
+            RETURNING *
+          )
+          SELECT u.*, a.email
+          FROM u
+          LEFT JOIN app_private.accounts AS a
+          ON a.user_id = u.id
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        Some(id),
+        "user.restore",
+        json!({}),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((user.etag(), Json(user)))
+}
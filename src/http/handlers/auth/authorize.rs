@@ -1,11 +1,18 @@
-use crate::{http::jwt::Claims, Error, KEYS};
-use axum::{Extension, Json};
+use crate::{
+    http::{
+        extract::{Json, ValidatedJson},
+        jwt::Claims,
+        tenant::active_org,
+    },
+    Error, KEYS,
+};
+use axum::Extension;
+use chrono::{DateTime, Utc};
 use jsonwebtoken::{encode, Algorithm, Header};
-use serde::Deserialize;
-use serde::Serialize;
-use sqlx::FromRow;
-use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
 use utoipa::ToSchema;
+use uuid::Uuid;
 use validator::Validate;
 
 #[derive(Debug, Serialize, ToSchema, FromRow)]
@@ -18,6 +25,38 @@ pub struct AuthResponse {
     pub refresh_token_expires: i64,
 }
 
+/// A row of `app.jwt_token`, as returned by the functions that start a session.
+#[derive(Debug, FromRow)]
+pub struct Session {
+    pub role: String,
+    pub user_id: Uuid,
+    pub refresh_token: Uuid,
+    pub refresh_token_expires: DateTime<Utc>,
+}
+
+impl AuthResponse {
+    /// Sign an access token for the session, acting in the user's active
+    /// organization, and pair it with its refresh token.
+    pub async fn issue<'e, E>(executor: E, session: Session) -> Result<Self, Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let org_id = active_org(executor, session.user_id).await?;
+        let claims = Claims::new(session.user_id, session.role.into()).with_org(org_id);
+
+        let header = Header::new(Algorithm::HS512);
+        let access_token = encode(&header, &claims, &KEYS.encoding)?;
+
+        Ok(AuthResponse {
+            token_type: "Bearer",
+            access_token,
+            expires_in: claims.expires_in(),
+            refresh_token: session.refresh_token.to_string(),
+            refresh_token_expires: session.refresh_token_expires.timestamp_millis(),
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthBody {
@@ -35,42 +74,35 @@ pub struct AuthBody {
     request_body = AuthBody,
     responses(
         (status = 200, description = "Authorization successful", body = AuthResponse),
-        (status = 400, description = "Validation error", body = Error),
+        (status = 400, description = "Malformed JSON body", body = Error),
         (status = 401, description = "Invalid credentials", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
         (status = 500, description = "Internal server error", body = Error),
     ),
 
 )]
 pub async fn authorize(
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<AuthBody>,
+    ValidatedJson(payload): ValidatedJson<AuthBody>,
 ) -> Result<Json<AuthResponse>, Error> {
-    payload.validate()?;
-
-    let row = sqlx::query!(
+    // `app.authenticate` returns a row of nulls for wrong credentials and
+    // inactive users alike, so the two can't be told apart.
+    let session = sqlx::query_as!(
+        Session,
         // language=PostgreSQL
         r#"SELECT
                 role "role!",
                 user_id "user_id!",
                 refresh_token "refresh_token!",
                 refresh_token_expires "refresh_token_expires!"
-            FROM app.authenticate($1, $2)"#,
+            FROM app.authenticate($1, $2)
+            WHERE user_id IS NOT NULL"#,
         &payload.client_id,
         &payload.client_secret
     )
-    .fetch_one(&pool)
-    .await?;
-
-    let claims = Claims::new(row.user_id, row.role.into());
-
-    let header = Header::new(Algorithm::HS512);
-    let access_token = encode(&header, &claims, &KEYS.encoding)?;
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
 
-    Ok(Json(AuthResponse {
-        token_type: "Bearer",
-        access_token,
-        expires_in: claims.exp,
-        refresh_token: row.refresh_token.to_string(),
-        refresh_token_expires: row.refresh_token_expires.timestamp_millis(),
-    }))
+    Ok(Json(AuthResponse::issue(&pool, session).await?))
 }
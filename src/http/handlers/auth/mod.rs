@@ -1,5 +1,7 @@
 mod authorize;
 mod revalidate;
+mod switch_org;
 
 pub use authorize::*;
 pub use revalidate::*;
+pub use switch_org::*;
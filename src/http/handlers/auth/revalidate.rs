@@ -1,10 +1,13 @@
-use axum::{Extension, Json};
+use axum::Extension;
 use jsonwebtoken::{encode, Algorithm, Header};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 use utoipa::ToSchema;
 
-use crate::{http::jwt::Claims, Error, KEYS};
+use crate::{
+    http::{extract::Json, handlers::auth::Session, jwt::Claims, tenant::active_org},
+    Error, KEYS,
+};
 
 #[derive(Debug, Serialize, ToSchema, FromRow)]
 #[serde(rename_all = "camelCase")]
@@ -36,20 +39,23 @@ pub async fn revalidate(
     Extension(pool): Extension<PgPool>,
     Json(payload): Json<RevalidateBody>,
 ) -> Result<Json<RevalidateResponse>, Error> {
-    let row = sqlx::query!(
-        // language=PostgresQL
-        r#"SELECT
-            role "role!",
-            user_id "user_id!",
-            refresh_token "refresh_token!",
-            refresh_token_expires "refresh_token_expires!"
-        FROM app.validate_refresh_token($1)"#,
-        payload.refresh_token
+    // `app.validate_refresh_token` returns a row of nulls for unknown,
+    // expired or inactive sessions.
+    let row = sqlx::query_as::<_, Session>(
+        // language=PostgreSQL
+        r#"
+          SELECT role, user_id, refresh_token, refresh_token_expires
+          FROM app.validate_refresh_token($1)
+          WHERE user_id IS NOT NULL
+      "#,
     )
-    .fetch_one(&pool)
-    .await?;
+    .bind(payload.refresh_token)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
 
-    let claims = Claims::new(row.user_id, row.role.into());
+    let org_id = active_org(&pool, row.user_id).await?;
+    let claims = Claims::new(row.user_id, row.role.into()).with_org(org_id);
 
     let header = Header::new(Algorithm::HS512);
     let access_token = encode(&header, &claims, &KEYS.encoding)?;
@@ -59,7 +65,7 @@ pub async fn revalidate(
     Ok(Json(RevalidateResponse {
         token_type: "Bearer",
         access_token,
-        expires_in: claims.exp,
+        expires_in: claims.expires_in(),
         refresh_token: row.refresh_token.to_string(),
         refresh_token_expires: row.refresh_token_expires.timestamp_millis(),
     }))
@@ -0,0 +1,89 @@
+use axum::Extension;
+use jsonwebtoken::{encode, Algorithm, Header};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    http::{
+        extract::Json,
+        jwt::{Claims, NotImpersonated},
+        tenant::org_role,
+    },
+    Error, KEYS,
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchOrgBody {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub org_id: Uuid,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchOrgResponse {
+    pub token_type: &'static str,
+    pub access_token: String,
+    pub expires_in: i64,
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub org_id: Uuid,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/switch-org",
+    request_body = SwitchOrgBody,
+    responses(
+        (status = 200, description = "Access token issued for the organization", body = SwitchOrgResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not a member of the organization, or impersonated", body = Error),
+        (status = 404, description = "Organization not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn switch_org(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+    Json(payload): Json<SwitchOrgBody>,
+) -> Result<Json<SwitchOrgResponse>, Error> {
+    // Admins may act in any organization, everyone else only in their own.
+    if org_role(&pool, payload.org_id, claims.sub).await?.is_none() {
+        if !claims.is_admin() {
+            return Err(Error::Forbidden);
+        }
+
+        sqlx::query_scalar::<_, Uuid>(
+            // language=PostgreSQL
+            r#"SELECT id FROM app.organizations WHERE id = $1"#,
+        )
+        .bind(payload.org_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(Error::NotFound)?;
+    }
+
+    // Remember the organization so that refreshed tokens of members keep
+    // acting in it.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app_private.accounts SET active_org_id = $2 WHERE user_id = $1"#,
+    )
+    .bind(claims.sub)
+    .bind(payload.org_id)
+    .execute(&pool)
+    .await?;
+
+    let claims = Claims::new(claims.sub, claims.role).with_org(Some(payload.org_id));
+
+    let header = Header::new(Algorithm::HS512);
+    let access_token = encode(&header, &claims, &KEYS.encoding)?;
+
+    Ok(Json(SwitchOrgResponse {
+        token_type: "Bearer",
+        access_token,
+        expires_in: claims.expires_in(),
+        org_id: payload.org_id,
+    }))
+}
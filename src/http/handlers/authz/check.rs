@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use super::MANAGE_AUTHZ;
+use crate::{
+    authz::{AuthzSchema, ObjectRef, SubjectRef},
+    http::{extract::Json, jwt::Permissions},
+    Error,
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckBody {
+    /// A subject such as `user:<id>` or `org:<id>#member`. Defaults to the
+    /// authenticated user
+    #[schema(example = "user:a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub subject: Option<String>,
+    #[schema(example = "editor")]
+    pub relation: String,
+    #[schema(example = "document:readme")]
+    pub object: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResponse {
+    #[schema(example = true)]
+    pub allowed: bool,
+}
+
+/// Check whether a subject has a relation to an object, following the
+/// rewrites of the authorization schema.
+#[utoipa::path(
+    post,
+    path = "/authz/check",
+    request_body = CheckBody,
+    responses(
+        (status = 200, description = "Whether the subject has the relation", body = CheckResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Missing the authz:manage permission to check another subject", body = Error),
+        (status = 422, description = "A malformed or unknown subject, relation or object", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn check(
+    Extension(pool): Extension<PgPool>,
+    Extension(schema): Extension<Arc<AuthzSchema>>,
+    permissions: Permissions,
+    Json(payload): Json<CheckBody>,
+) -> Result<Json<CheckResponse>, Error> {
+    let me = SubjectRef::user(permissions.claims.sub);
+    let subject = match payload.subject {
+        Some(subject) => subject
+            .parse::<SubjectRef>()
+            .map_err(|_| Error::invalid_field("subject", "invalid"))?,
+        None => me.clone(),
+    };
+
+    // Checking others would reveal who has access to what.
+    if subject != me {
+        permissions.require(MANAGE_AUTHZ)?;
+    }
+
+    let object = payload
+        .object
+        .parse::<ObjectRef>()
+        .map_err(|_| Error::invalid_field("object", "invalid"))?;
+    schema
+        .relation(&object.object_type, &payload.relation)
+        .ok_or_else(|| Error::invalid_field("relation", "invalid"))?;
+
+    let allowed = schema
+        .check(&pool, &subject, &payload.relation, &object)
+        .await?;
+
+    Ok(Json(CheckResponse { allowed }))
+}
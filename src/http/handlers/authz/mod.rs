@@ -0,0 +1,9 @@
+mod check;
+mod tuples;
+
+pub use check::*;
+pub use tuples::*;
+
+/// The permission needed to read and write relation tuples, and to check the
+/// relations of subjects other than oneself.
+pub const MANAGE_AUTHZ: &str = "authz:manage";
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Extension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+
+use super::MANAGE_AUTHZ;
+use crate::{
+    audit,
+    authz::{self, AuthzSchema, ObjectRef, RelationTuple, SubjectRef},
+    http::{
+        extract::{Json, Query},
+        jwt::Permissions,
+    },
+    Error,
+};
+
+/// A relation tuple: the subject has the relation to the object.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TupleBody {
+    #[schema(example = "document:readme")]
+    pub object: String,
+    #[schema(example = "viewer")]
+    pub relation: String,
+    /// A subject such as `user:<id>`, or a userset such as `org:<id>#member`
+    #[schema(example = "org:a00c9bc7-92ca-413a-97ec-66204314bbca#member")]
+    pub subject: String,
+}
+
+impl TupleBody {
+    fn parse(self, schema: &AuthzSchema) -> Result<RelationTuple, Error> {
+        let tuple = RelationTuple {
+            object: self
+                .object
+                .parse()
+                .map_err(|_| Error::invalid_field("object", "invalid"))?,
+            relation: self.relation,
+            subject: self
+                .subject
+                .parse()
+                .map_err(|_| Error::invalid_field("subject", "invalid"))?,
+        };
+
+        schema.validate(&tuple)?;
+
+        Ok(tuple)
+    }
+}
+
+impl From<RelationTuple> for TupleBody {
+    fn from(tuple: RelationTuple) -> Self {
+        TupleBody {
+            object: tuple.object.to_string(),
+            relation: tuple.relation,
+            subject: tuple.subject.to_string(),
+        }
+    }
+}
+
+/// The query parameters to filter relation tuples by.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TupleFilter {
+    /// Only tuples of this object
+    #[param(example = "document:readme")]
+    pub object: Option<String>,
+    /// Only tuples with this relation
+    #[param(example = "viewer")]
+    pub relation: Option<String>,
+    /// Only tuples of this subject or userset
+    #[param(example = "org:a00c9bc7-92ca-413a-97ec-66204314bbca#member")]
+    pub subject: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteTuplesBody {
+    /// Tuples to store. Storing an existing tuple does nothing
+    #[serde(default)]
+    pub writes: Vec<TupleBody>,
+    /// Tuples to remove. Removing a missing tuple does nothing
+    #[serde(default)]
+    pub deletes: Vec<TupleBody>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/authz/tuples",
+    responses(
+        (status = 200, description = "The stored tuples matching the filters", body = [TupleBody]),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Missing the authz:manage permission", body = Error),
+        (status = 422, description = "Malformed object or subject", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(TupleFilter)
+)]
+pub async fn find_tuples(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Query(filter): Query<TupleFilter>,
+) -> Result<Json<Vec<TupleBody>>, Error> {
+    permissions.require(MANAGE_AUTHZ)?;
+
+    let object = filter
+        .object
+        .as_deref()
+        .map(str::parse::<ObjectRef>)
+        .transpose()
+        .map_err(|_| Error::invalid_field("object", "invalid"))?;
+    let subject = filter
+        .subject
+        .as_deref()
+        .map(str::parse::<SubjectRef>)
+        .transpose()
+        .map_err(|_| Error::invalid_field("subject", "invalid"))?;
+
+    let tuples = authz::read_tuples(
+        &pool,
+        object.as_ref(),
+        filter.relation.as_deref(),
+        subject.as_ref(),
+    )
+    .await?;
+
+    Ok(Json(tuples.into_iter().map(TupleBody::from).collect()))
+}
+
+/// Store and remove relation tuples in one transaction. Deletes are applied
+/// after writes, so a tuple in both ends up removed.
+#[utoipa::path(
+    post,
+    path = "/authz/tuples",
+    request_body = WriteTuplesBody,
+    responses(
+        (status = 204, description = "The tuples were written"),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Missing the authz:manage permission", body = Error),
+        (status = 422, description = "A malformed tuple or one not in the schema", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn write_tuples(
+    Extension(pool): Extension<PgPool>,
+    Extension(schema): Extension<Arc<AuthzSchema>>,
+    permissions: Permissions,
+    Json(payload): Json<WriteTuplesBody>,
+) -> Result<StatusCode, Error> {
+    permissions.require(MANAGE_AUTHZ)?;
+
+    let writes = payload
+        .writes
+        .into_iter()
+        .enumerate()
+        .map(|(i, tuple)| {
+            tuple
+                .parse(&schema)
+                .map_err(|err| err.nested(&format!("writes[{i}]")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let deletes = payload
+        .deletes
+        .into_iter()
+        .enumerate()
+        .map(|(i, tuple)| {
+            tuple
+                .parse(&schema)
+                .map_err(|err| err.nested(&format!("deletes[{i}]")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tx = pool.begin().await?;
+
+    for tuple in &writes {
+        authz::write_tuple(&mut tx, tuple).await?;
+    }
+
+    for tuple in &deletes {
+        authz::delete_tuple(&mut tx, tuple).await?;
+    }
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        None,
+        "authz.write",
+        json!({
+            "writes": writes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "deletes": deletes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,514 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Extension};
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool, Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    audit,
+    http::{
+        extract::{Json, Path, ValidatedJson},
+        jwt::Permissions,
+        tenant::org_role,
+    },
+    permissions::{group_permissions, PermissionCache, ORGANIZATION_PERMISSIONS},
+    Error,
+};
+
+/// The permission needed to manage groups, their permissions and members.
+pub const MANAGE_GROUPS: &str = "groups:manage";
+
+/// The organization whose groups the session manages: the one it acts in, or
+/// none for the global groups. Like a [`TenantScope`], the session must still
+/// belong to the organization.
+///
+/// [`TenantScope`]: crate::http::tenant::TenantScope
+pub(super) async fn managed_org(
+    pool: &PgPool,
+    permissions: &Permissions,
+) -> Result<Option<Uuid>, Error> {
+    permissions.require(MANAGE_GROUPS)?;
+
+    let claims = &permissions.claims;
+
+    if let Some(org_id) = claims.org_id {
+        if !claims.is_admin() && org_role(pool, org_id, claims.sub).await?.is_none() {
+            return Err(Error::Forbidden);
+        }
+    }
+
+    Ok(claims.org_id)
+}
+
+/// Refuse to manage a group that grants, directly or through its ancestors,
+/// a permission the session does not hold itself, so that managing groups
+/// never escalates privileges.
+pub(super) async fn require_grantable<'e, E>(
+    executor: E,
+    permissions: &Permissions,
+    id: Uuid,
+) -> Result<(), Error>
+where
+    E: PgExecutor<'e>,
+{
+    let granted = group_permissions(executor, id).await?;
+
+    if granted.iter().all(|permission| permissions.has(permission)) {
+        Ok(())
+    } else {
+        Err(Error::Forbidden)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    #[schema(example = "Support")]
+    pub name: String,
+    #[schema(example = "First line support staff")]
+    pub description: Option<String>,
+    /// The group permissions are inherited from
+    #[schema(example = json!(null))]
+    pub parent_id: Option<Uuid>,
+    /// The permissions granted to the group itself, not including inherited ones
+    #[schema(example = json!(["users:deactivate"]))]
+    pub permissions: Vec<String>,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1664905980000")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGroupBody {
+    #[schema(example = "Support")]
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[schema(example = "First line support staff")]
+    pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
+    /// Permissions of the form `resource:action`
+    #[schema(example = json!(["users:deactivate"]))]
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateGroupBody {
+    #[schema(example = "Support")]
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[schema(example = "First line support staff")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub description: Option<Option<String>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub parent_id: Option<Option<Uuid>>,
+    /// Replaces every permission granted to the group
+    #[schema(example = json!(["users:deactivate", "users:export"]))]
+    pub permissions: Option<Vec<String>>,
+}
+
+pub(super) async fn find_group<'e, E>(
+    executor: E,
+    id: Uuid,
+    org_id: Option<Uuid>,
+) -> Result<GroupResponse, Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as::<_, GroupResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT
+            g.id,
+            g.name,
+            g.description,
+            g.parent_id,
+            coalesce(array_agg(p.permission ORDER BY p.permission) FILTER (WHERE p.permission IS NOT NULL), '{}') AS permissions,
+            g.created_at,
+            g.updated_at
+          FROM app.groups AS g
+          LEFT JOIN app.group_permissions AS p
+          ON p.group_id = g.id
+          WHERE g.id = $1 AND g.organization_id IS NOT DISTINCT FROM $2
+          GROUP BY g.id
+      "#,
+    )
+    .bind(id)
+    .bind(org_id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+/// Replace the permissions granted to a group. The groups of an organization
+/// may only grant the [`ORGANIZATION_PERMISSIONS`].
+async fn replace_permissions(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    org_id: Option<Uuid>,
+    permissions: &[String],
+) -> Result<(), Error> {
+    if org_id.is_some()
+        && permissions
+            .iter()
+            .any(|permission| !ORGANIZATION_PERMISSIONS.contains(&permission.as_str()))
+    {
+        return Err(Error::invalid_field("permissions", "invalid"));
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app.group_permissions WHERE group_id = $1"#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.group_permissions (group_id, permission)
+          SELECT $1, permission FROM unnest($2::text[]) AS permission
+          ON CONFLICT DO NOTHING
+      "#,
+    )
+    .bind(id)
+    .bind(permissions)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Point a group at a new parent, refusing parents of other organizations and
+/// parents that descend from the group itself. The table is locked so that
+/// two concurrent moves can't create a cycle between them.
+async fn set_parent(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    org_id: Option<Uuid>,
+    parent_id: Option<Uuid>,
+) -> Result<(), Error> {
+    sqlx::query(
+        // language=PostgreSQL
+        r#"LOCK TABLE app.groups IN SHARE ROW EXCLUSIVE MODE"#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(parent_id) = parent_id {
+        sqlx::query_scalar::<_, Uuid>(
+            // language=PostgreSQL
+            r#"SELECT id FROM app.groups WHERE id = $1 AND organization_id IS NOT DISTINCT FROM $2"#,
+        )
+        .bind(parent_id)
+        .bind(org_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::InvalidReference)?;
+
+        let creates_cycle = sqlx::query_scalar::<_, bool>(
+            // language=PostgreSQL
+            r#"
+              WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id FROM app.groups WHERE id = $2
+                UNION
+                SELECT g.id, g.parent_id
+                FROM app.groups AS g
+                JOIN ancestors AS a
+                ON g.id = a.parent_id
+              )
+              SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $1)
+          "#,
+        )
+        .bind(id)
+        .bind(parent_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if creates_cycle {
+            return Err(Error::invalid_field("parentId", "invalid"));
+        }
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.groups SET parent_id = $2 WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(parent_id)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups",
+    responses(
+        (status = 200, description = "Every group of the active organization, or every global group outside of one, by name", body = [GroupResponse]),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn find_groups(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+) -> Result<Json<Vec<GroupResponse>>, Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let groups = sqlx::query_as::<_, GroupResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT
+            g.id,
+            g.name,
+            g.description,
+            g.parent_id,
+            coalesce(array_agg(p.permission ORDER BY p.permission) FILTER (WHERE p.permission IS NOT NULL), '{}') AS permissions,
+            g.created_at,
+            g.updated_at
+          FROM app.groups AS g
+          LEFT JOIN app.group_permissions AS p
+          ON p.group_id = g.id
+          WHERE g.organization_id IS NOT DISTINCT FROM $1
+          GROUP BY g.id
+          ORDER BY g.name
+      "#,
+    )
+    .bind(org_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(groups))
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups/{id}",
+    responses(
+        (status = 200, description = "The group", body = GroupResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission", body = Error),
+        (status = 404, description = "Group not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group")
+    )
+)]
+pub async fn find_group_by_id(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+) -> Result<Json<GroupResponse>, Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    Ok(Json(find_group(&pool, id, org_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/groups",
+    request_body = CreateGroupBody,
+    responses(
+        (status = 201, description = "Group created", body = GroupResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission, or a permission the group would grant", body = Error),
+        (status = 409, description = "A group with this name exists", body = Error),
+        (status = 422, description = "Invalid name or permission, one organizations can't grant, or an unknown parent or one nested below the group", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn create_group(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    permissions: Permissions,
+    ValidatedJson(payload): ValidatedJson<CreateGroupBody>,
+) -> Result<(StatusCode, Json<GroupResponse>), Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.groups (name, description, organization_id)
+          VALUES ($1, $2, $3)
+          RETURNING id
+      "#,
+    )
+    .bind(payload.name.trim())
+    .bind(&payload.description)
+    .bind(org_id)
+    .fetch_one(&mut tx)
+    .await?;
+
+    if payload.parent_id.is_some() {
+        set_parent(&mut tx, id, org_id, payload.parent_id).await?;
+    }
+
+    replace_permissions(&mut tx, id, org_id, &payload.permissions).await?;
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        None,
+        "group.create",
+        json!({ "group_id": id, "permissions": payload.permissions }),
+    )
+    .await?;
+
+    let group = find_group(&mut tx, id, org_id).await?;
+
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/groups/{id}",
+    request_body = UpdateGroupBody,
+    responses(
+        (status = 200, description = "Group updated", body = GroupResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission, or a permission the group grants", body = Error),
+        (status = 404, description = "Group not found", body = Error),
+        (status = 409, description = "A group with this name exists", body = Error),
+        (status = 422, description = "Invalid name or permission, one organizations can't grant, or an unknown parent or one nested below the group", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group")
+    )
+)]
+pub async fn update_group(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateGroupBody>,
+) -> Result<Json<GroupResponse>, Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let mut tx = pool.begin().await?;
+
+    find_group(&mut tx, id, org_id).await?;
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+          UPDATE app.groups
+          SET
+            name = coalesce($2, name),
+            description = CASE WHEN $3 THEN $4 ELSE description END
+          WHERE id = $1
+          RETURNING id
+      "#,
+    )
+    .bind(id)
+    .bind(payload.name.as_deref().map(str::trim))
+    .bind(payload.description.is_some())
+    .bind(payload.description.flatten())
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    if let Some(parent_id) = payload.parent_id {
+        set_parent(&mut tx, id, org_id, parent_id).await?;
+    }
+
+    if let Some(granted) = &payload.permissions {
+        replace_permissions(&mut tx, id, org_id, granted).await?;
+    }
+
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        None,
+        "group.update",
+        json!({ "group_id": id, "permissions": payload.permissions }),
+    )
+    .await?;
+
+    let group = find_group(&mut tx, id, org_id).await?;
+
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(Json(group))
+}
+
+/// Delete a group. Groups nested in it become top level groups.
+#[utoipa::path(
+    delete,
+    path = "/groups/{id}",
+    responses(
+        (status = 204, description = "Group deleted"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission, or a permission the group grants", body = Error),
+        (status = 404, description = "Group not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group")
+    )
+)]
+pub async fn delete_group(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let mut tx = pool.begin().await?;
+
+    find_group(&mut tx, id, org_id).await?;
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app.groups WHERE id = $1"#,
+    )
+    .bind(id)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        None,
+        "group.delete",
+        json!({ "group_id": id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Extension};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{find_group, managed_org, require_grantable};
+use crate::{
+    audit,
+    http::{
+        error::sqlstate::FOREIGN_KEY_VIOLATION,
+        extract::{Json, Path},
+        jwt::Permissions,
+        tenant::org_role,
+    },
+    permissions::PermissionCache,
+    Error,
+};
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMemberResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub user_id: Uuid,
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups/{id}/members",
+    responses(
+        (status = 200, description = "The direct members of the group", body = [GroupMemberResponse]),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission", body = Error),
+        (status = 404, description = "Group not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group")
+    )
+)]
+pub async fn find_group_members(
+    Extension(pool): Extension<PgPool>,
+    permissions: Permissions,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<GroupMemberResponse>>, Error> {
+    let org_id = managed_org(&pool, &permissions).await?;
+    find_group(&pool, id, org_id).await?;
+
+    let members = sqlx::query_as::<_, GroupMemberResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT m.user_id, u.first_name, u.last_name, a.email, m.created_at
+          FROM app.group_members AS m
+          JOIN app.users AS u
+          ON u.id = m.user_id
+          JOIN app_private.accounts AS a
+          ON a.user_id = m.user_id
+          WHERE m.group_id = $1
+          ORDER BY m.created_at, m.user_id
+      "#,
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(members))
+}
+
+#[utoipa::path(
+    put,
+    path = "/groups/{id}/members/{user_id}",
+    responses(
+        (status = 204, description = "The user is a member of the group"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission, or a permission the group grants", body = Error),
+        (status = 404, description = "Group not found, or user not found in the organization of the group", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group"),
+        ("user_id" = Uuid, Path, description = "The id of the user to add")
+    )
+)]
+pub async fn add_group_member(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    permissions: Permissions,
+    path: Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    let Path((id, user_id)) = path;
+
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let mut tx = pool.begin().await?;
+
+    find_group(&mut tx, id, org_id).await?;
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    // The groups of an organization only take in its members.
+    if let Some(org_id) = org_id {
+        org_role(&mut tx, org_id, user_id)
+            .await?
+            .ok_or(Error::NotFound)?;
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.group_members (group_id, user_id)
+          VALUES ($1, $2)
+          ON CONFLICT DO NOTHING
+      "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(err) if err.code().as_deref() == Some(FOREIGN_KEY_VIOLATION) => {
+            Error::NotFound
+        }
+        err => err.into(),
+    })?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        Some(user_id),
+        "group.member.add",
+        json!({ "group_id": id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/groups/{id}/members/{user_id}",
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Missing the groups:manage permission, or a permission the group grants", body = Error),
+        (status = 404, description = "Group not found, or not a member of it", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the group"),
+        ("user_id" = Uuid, Path, description = "The id of the member to remove")
+    )
+)]
+pub async fn remove_group_member(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    permissions: Permissions,
+    path: Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    let Path((id, user_id)) = path;
+
+    let org_id = managed_org(&pool, &permissions).await?;
+
+    let mut tx = pool.begin().await?;
+
+    find_group(&mut tx, id, org_id).await?;
+    require_grantable(&mut tx, &permissions, id).await?;
+
+    sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"DELETE FROM app.group_members WHERE group_id = $1 AND user_id = $2 RETURNING user_id"#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    audit::record(
+        &mut tx,
+        Some(permissions.claims.sub),
+        Some(user_id),
+        "group.member.remove",
+        json!({ "group_id": id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,5 @@
+mod group;
+mod members;
+
+pub use group::*;
+pub use members::*;
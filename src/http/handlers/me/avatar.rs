@@ -0,0 +1,153 @@
+use axum::{
+    extract::{
+        multipart::{Field, MultipartRejection},
+        Multipart,
+    },
+    Extension,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{find_profile, ProfileResponse};
+use crate::{
+    blob::SharedBlobStore,
+    http::{extract::Json, handlers::users::ImageFormat, jwt::Claims},
+    Error,
+};
+
+/// The largest avatar image accepted, in bytes.
+const MAX_AVATAR_SIZE: usize = 2 * 1024 * 1024;
+
+/// The multipart form an avatar is uploaded with.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarUpload {
+    /// A PNG, JPEG, GIF or WebP image of at most 2 MiB
+    #[schema(value_type = String, format = Binary)]
+    pub avatar: Vec<u8>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/avatar",
+    request_body(content = AvatarUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Avatar replaced", body = ProfileResponse),
+        (status = 400, description = "Malformed multipart body", body = Error),
+        (status = 404, description = "User not found", body = Error),
+        (status = 413, description = "Avatar larger than 2 MiB", body = Error),
+        (status = 415, description = "Avatar is not a PNG, JPEG, GIF or WebP image", body = Error),
+        (status = 422, description = "Missing avatar", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn upload_avatar(
+    Extension(pool): Extension<PgPool>,
+    Extension(blob_store): Extension<SharedBlobStore>,
+    claims: Claims,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> Result<Json<ProfileResponse>, Error> {
+    let mut multipart = multipart.map_err(Error::from_rejection)?;
+
+    let field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("avatar") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => return Err(Error::invalid_field("avatar", "required")),
+            Err(err) => return Err(Error::BadRequest(err.to_string())),
+        }
+    };
+
+    let declared = field
+        .content_type()
+        .and_then(ImageFormat::from_content_type)
+        .ok_or(Error::UnsupportedMediaType)?;
+    let bytes = read_limited(field, MAX_AVATAR_SIZE).await?;
+
+    if ImageFormat::sniff(&bytes) != Some(declared) {
+        return Err(Error::UnsupportedMediaType);
+    }
+
+    let key = format!(
+        "avatars/{}/{}.{}",
+        claims.sub,
+        Uuid::new_v4(),
+        declared.extension()
+    );
+    blob_store.put(&key, bytes.into()).await?;
+
+    // The image is stored first so that a committed key always points at one,
+    // and removed again when the user can't be pointed at it.
+    let (previous, profile) = match set_avatar_key(&pool, claims.sub, &key).await {
+        Ok(updated) => updated,
+        Err(err) => {
+            if let Err(err) = blob_store.delete(&key).await {
+                tracing::warn!("Unable to delete avatar `{}`: {}", key, err);
+            }
+
+            return Err(err);
+        }
+    };
+
+    // The old image is no longer referenced, so failing to remove it only
+    // wastes space.
+    if let Some(previous) = previous {
+        if let Err(err) = blob_store.delete(&previous).await {
+            tracing::warn!("Unable to delete avatar `{}`: {}", previous, err);
+        }
+    }
+
+    Ok(Json(profile))
+}
+
+/// Point a user at a new avatar, returning the key of the one it replaces
+/// and the updated profile.
+async fn set_avatar_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    key: &str,
+) -> Result<(Option<String>, ProfileResponse), Error> {
+    let mut tx = pool.begin().await?;
+
+    let previous = sqlx::query_scalar::<_, Option<String>>(
+        // language=PostgreSQL
+        r#"
+            UPDATE app.users AS u
+            SET avatar_key = $2
+            FROM (SELECT avatar_key FROM app.users WHERE id = $1 FOR UPDATE) AS previous
+            WHERE u.id = $1 AND u.deleted_at IS NULL
+            RETURNING previous.avatar_key
+        "#,
+    )
+    .bind(user_id)
+    .bind(key)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let profile = find_profile(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    Ok((previous, profile))
+}
+
+/// Read a multipart field, failing as soon as it grows past `limit` bytes.
+async fn read_limited(mut field: Field<'_>, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|err| Error::BadRequest(err.to_string()))?
+    {
+        if bytes.len() + chunk.len() > limit {
+            return Err(Error::PayloadTooLarge);
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(bytes)
+}
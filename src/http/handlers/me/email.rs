@@ -0,0 +1,125 @@
+use axum::{http::StatusCode, Extension};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    audit,
+    http::{extract::ValidatedJson, jwt::NotImpersonated},
+    mail::{Email, SharedMailer},
+    Error, PUBLIC_URL,
+};
+
+#[derive(Debug, Deserialize, Validate, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEmailBody {
+    #[schema(example = "ziggy.stardust@gmail.com")]
+    #[validate(email)]
+    pub email: String,
+}
+
+/// Start changing the email address of the authenticated user. The address
+/// is only changed once the token sent to the new address is confirmed, and the
+/// current address is told about the request in case it was not theirs.
+#[utoipa::path(
+    post,
+    path = "/me/email",
+    request_body = ChangeEmailBody,
+    responses(
+        (status = 202, description = "Confirmation sent to the new address"),
+        (status = 400, description = "Malformed JSON body", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 409, description = "Email address is already in use", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn request_email_change(
+    Extension(pool): Extension<PgPool>,
+    Extension(mailer): Extension<SharedMailer>,
+    NotImpersonated(claims): NotImpersonated,
+    ValidatedJson(payload): ValidatedJson<ChangeEmailBody>,
+) -> Result<StatusCode, Error> {
+    let mut tx = pool.begin().await?;
+
+    let taken = sqlx::query_scalar::<_, bool>(
+        // language=PostgreSQL
+        r#"SELECT EXISTS (SELECT 1 FROM app_private.accounts WHERE email = $1)"#,
+    )
+    .bind(&payload.email)
+    .fetch_one(&mut tx)
+    .await?;
+
+    if taken {
+        return Err(Error::EmailTaken);
+    }
+
+    let current_email = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT email FROM app_private.accounts WHERE user_id = $1"#,
+    )
+    .bind(claims.sub)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    // A new request replaces any earlier one, so only the latest link works.
+    let token = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.email_changes (user_id, new_email)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET
+                new_email = excluded.new_email,
+                token = excluded.token,
+                expires_at = excluded.expires_at,
+                created_at = excluded.created_at
+            RETURNING token
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(&payload.email)
+    .fetch_one(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(claims.sub),
+        "email.change_requested",
+        serde_json::json!({ "newEmail": payload.email }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    mailer
+        .send(Email {
+            to: payload.email.clone(),
+            subject: "Confirm your new email address".into(),
+            body: format!(
+                "Confirm that you want to use this address for your account at \
+                 {}/accounts/email/confirm with the token {token}.\n\n\
+                 The token expires in 24 hours.",
+                *PUBLIC_URL
+            ),
+        })
+        .await?;
+
+    mailer
+        .send(Email {
+            to: current_email,
+            subject: "Your email address is being changed".into(),
+            body: format!(
+                "Someone asked to change the email address of your account to {}. \
+                 If this was not you, change your password.",
+                payload.email
+            ),
+        })
+        .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,148 @@
+use axum::{http::StatusCode, Extension};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    audit,
+    http::{extract::Json, jwt::NotImpersonated},
+    mail::{Email, SharedMailer},
+    privacy, Error,
+};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EraseBody {
+    /// The user's password, to confirm the request
+    #[schema(example = "Sm4rT.HuLk")]
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErasureResponse {
+    /// When the user will be erased, unless the request is withdrawn before then
+    #[schema(example = "1667065994804")]
+    #[serde(with = "ts_milliseconds")]
+    pub erase_after: DateTime<Utc>,
+}
+
+/// Ask for the authenticated user to be erased once the cooling-off period is
+/// over. See `src/privacy.rs` for what is erased.
+#[utoipa::path(
+    post,
+    path = "/me/erase",
+    request_body = EraseBody,
+    responses(
+        (status = 202, description = "Erasure scheduled", body = ErasureResponse),
+        (status = 401, description = "Invalid credentials", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn request_erasure(
+    Extension(pool): Extension<PgPool>,
+    Extension(mailer): Extension<SharedMailer>,
+    NotImpersonated(claims): NotImpersonated,
+    Json(payload): Json<EraseBody>,
+) -> Result<(StatusCode, Json<ErasureResponse>), Error> {
+    let mut tx = pool.begin().await?;
+
+    let email = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+            SELECT email
+            FROM app_private.accounts
+            WHERE user_id = $1 AND hashed_password = crypt($2, hashed_password)
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(payload.password)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
+
+    // Asking again keeps the original date rather than restarting the period.
+    let erase_after = sqlx::query_scalar::<_, DateTime<Utc>>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.erasure_requests (user_id, erase_after)
+            VALUES ($1, now() + $2)
+            ON CONFLICT (user_id) DO UPDATE SET user_id = excluded.user_id
+            RETURNING erase_after
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(privacy::cooling_off())
+    .fetch_one(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(claims.sub),
+        "erasure.requested",
+        serde_json::json!({}),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    mailer
+        .send(Email {
+            to: email,
+            subject: "Your account will be erased".into(),
+            body: format!(
+                "Your account and the data we hold about you will be erased on {}. \
+                 Until then you can change your mind by signing in and withdrawing the request.",
+                erase_after.to_rfc2822()
+            ),
+        })
+        .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(ErasureResponse { erase_after })))
+}
+
+/// Withdraw a pending erasure request during the cooling-off period.
+#[utoipa::path(
+    delete,
+    path = "/me/erase",
+    responses(
+        (status = 204, description = "Erasure request withdrawn"),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "No pending erasure request", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn cancel_erasure(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+) -> Result<StatusCode, Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app_private.erasure_requests WHERE user_id = $1"#,
+    )
+    .bind(claims.sub)
+    .execute(&mut tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(claims.sub),
+        "erasure.cancelled",
+        serde_json::json!({}),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,207 @@
+use axum::{
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::IntoResponse,
+    Extension,
+};
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::Serialize;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    blob::SharedBlobStore,
+    http::{
+        extract::{Json, Path},
+        jwt::NotImpersonated,
+        rls::ScopedTx,
+    },
+    privacy, Error,
+};
+
+#[derive(Debug, FromRow)]
+struct DataExport {
+    id: Uuid,
+    status: String,
+    blob_key: Option<String>,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    /// One of `pending`, `ready` or `failed`
+    #[schema(example = "ready")]
+    pub status: String,
+    /// Where to download the archive from, once it is ready
+    #[schema(example = "/me/exports/a00c9bc7-92ca-413a-97ec-66204314bbca/download")]
+    pub download_url: Option<String>,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1665856395804")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[schema(example = "1666461194804")]
+    #[serde(with = "ts_milliseconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<DataExport> for ExportResponse {
+    fn from(export: DataExport) -> Self {
+        ExportResponse {
+            download_url: export
+                .blob_key
+                .map(|_| format!("/me/exports/{}/download", export.id)),
+            id: export.id,
+            status: export.status,
+            created_at: export.created_at,
+            completed_at: export.completed_at,
+            expires_at: export.expires_at,
+        }
+    }
+}
+
+/// Start producing an archive of everything held about the authenticated
+/// user. The archive is produced in the background; poll the export until it
+/// is ready.
+#[utoipa::path(
+    post,
+    path = "/me/export",
+    responses(
+        (status = 202, description = "Export started", body = ExportResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn request_export(
+    Extension(pool): Extension<PgPool>,
+    Extension(blob_store): Extension<SharedBlobStore>,
+    NotImpersonated(claims): NotImpersonated,
+) -> Result<(StatusCode, Json<ExportResponse>), Error> {
+    let mut tx = pool.begin().await?;
+
+    let export = sqlx::query_as::<_, DataExport>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.data_exports (user_id)
+            VALUES ($1)
+            RETURNING id, status, blob_key, created_at, completed_at, expires_at
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_one(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(claims.sub),
+        "export.requested",
+        serde_json::json!({ "exportId": export.id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    tokio::spawn(privacy::run_export(pool, blob_store, export.id, claims.sub));
+
+    Ok((StatusCode::ACCEPTED, Json(export.into())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/exports/{id}",
+    responses(
+        (status = 200, description = "The state of the export", body = ExportResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "Export not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The export's id")
+    )
+)]
+pub async fn find_export(
+    NotImpersonated(claims): NotImpersonated,
+    mut tx: ScopedTx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ExportResponse>, Error> {
+    let export = find_data_export(&mut *tx, claims.sub, id).await?;
+
+    Ok(Json(export.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/exports/{id}/download",
+    responses(
+        (status = 200, description = "The export archive", content_type = "application/json"),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "Export not found or not ready", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The export's id")
+    )
+)]
+pub async fn download_export(
+    Extension(blob_store): Extension<SharedBlobStore>,
+    NotImpersonated(claims): NotImpersonated,
+    mut tx: ScopedTx,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let key = find_data_export(&mut *tx, claims.sub, id)
+        .await?
+        .blob_key
+        .ok_or(Error::NotFound)?;
+
+    let bytes = blob_store.get(&key).await?.ok_or(Error::NotFound)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/json".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export-{id}.json\""),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+/// Find an export of the given user that has not expired yet.
+async fn find_data_export<'e, E>(executor: E, user_id: Uuid, id: Uuid) -> Result<DataExport, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let export = sqlx::query_as::<_, DataExport>(
+        // language=PostgreSQL
+        r#"
+            SELECT id, status, blob_key, created_at, completed_at, expires_at
+            FROM app_private.data_exports
+            WHERE id = $1 AND user_id = $2 AND expires_at > now()
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(export)
+}
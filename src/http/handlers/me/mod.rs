@@ -0,0 +1,13 @@
+mod avatar;
+mod email;
+mod erase;
+mod export;
+mod permissions;
+mod profile;
+
+pub use avatar::*;
+pub use email::*;
+pub use erase::*;
+pub use export::*;
+pub use permissions::*;
+pub use profile::*;
@@ -0,0 +1,31 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::http::{extract::Json, jwt::Permissions};
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionsResponse {
+    /// Whether the user is an admin, who holds every permission
+    #[schema(example = false)]
+    pub admin: bool,
+    /// The permissions granted through the user's groups and their ancestors
+    #[schema(example = json!(["users:deactivate", "users:export"]))]
+    pub permissions: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/permissions",
+    responses(
+        (status = 200, description = "The effective permissions of the authenticated user", body = PermissionsResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn find_my_permissions(permissions: Permissions) -> Json<PermissionsResponse> {
+    Json(PermissionsResponse {
+        admin: permissions.claims.is_admin(),
+        permissions: permissions.granted().iter().cloned().collect(),
+    })
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use axum::Extension;
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgExecutor};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    http::{
+        etag::{ETag, IfMatch},
+        extract::{Json, ValidatedJson},
+        handlers::users::{apply_update, lock_user, UpdateUserBody},
+        jwt::Claims,
+        rls::ScopedTx,
+    },
+    metadata::MetadataSchema,
+    Error,
+};
+
+/// The profile of the authenticated user, including details of their account
+/// that are not exposed through `/users`.
+#[derive(Debug, Clone, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    #[schema(example = "Ziggy")]
+    pub display_name: Option<String>,
+    #[schema(example = "en-GB")]
+    pub locale: Option<String>,
+    #[schema(example = "Europe/London")]
+    pub timezone: Option<String>,
+    /// Free-form details, validated against the configured JSON Schema
+    #[schema(value_type = Object, example = json!({ "band": "The Spiders from Mars" }))]
+    pub metadata: Value,
+    #[schema(example = "/users/a00c9bc7-92ca-413a-97ec-66204314bbca/avatar")]
+    pub avatar_url: Option<String>,
+    #[schema(example = true)]
+    pub email_verified: bool,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub last_login: Option<DateTime<Utc>>,
+    #[schema(example = json!(["user"]))]
+    pub roles: Vec<String>,
+    /// Whether a passkey is registered to the account
+    #[schema(example = false)]
+    pub mfa_enabled: bool,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1664905980000")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    responses(
+        (status = 200, description = "The authenticated user's profile", body = ProfileResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 404, description = "User not found", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn find_me(claims: Claims, mut tx: ScopedTx) -> Result<Json<ProfileResponse>, Error> {
+    let profile = find_profile(&mut *tx, claims.sub).await?;
+
+    Ok(Json(profile))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me",
+    request_body = UpdateUserBody,
+    responses(
+        (status = 200, description = "Profile updated, tagged with the new version of the user in the `ETag` header", body = ProfileResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 404, description = "User not found", body = Error),
+        (status = 412, description = "The user has changed since the version in `If-Match`", body = Error),
+        (status = 428, description = "The `If-Match` header is missing", body = Error),
+        (status = 422, description = "Invalid display name, locale, timezone or metadata", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("If-Match" = String, Header, description = "The `ETag` of the version of the user being updated, as given by `/users/{id}`")
+    )
+)]
+pub async fn update_me(
+    Extension(metadata_schema): Extension<Arc<MetadataSchema>>,
+    claims: Claims,
+    mut tx: ScopedTx,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<UpdateUserBody>,
+) -> Result<(ETag, Json<ProfileResponse>), Error> {
+    if_match.check(&lock_user(&mut *tx, claims.sub).await?)?;
+
+    let user = apply_update(&mut *tx, claims.sub, payload, &metadata_schema)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let profile = find_profile(&mut *tx, claims.sub).await?;
+
+    tx.commit().await?;
+
+    // The profile also shows details of the account, so only the user is
+    // tagged, and `/me` is never answered conditionally.
+    Ok((user.etag(), Json(profile)))
+}
+
+pub(crate) async fn find_profile<'e, E>(executor: E, id: Uuid) -> Result<ProfileResponse, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let profile = sqlx::query_as::<_, ProfileResponse>(
+        // language=PostgreSQL
+        r#"
+            SELECT
+                u.id,
+                u.first_name,
+                u.last_name,
+                a.email,
+                u.display_name,
+                u.locale,
+                u.timezone,
+                u.metadata,
+                u.avatar_url,
+                a.email_verified_at IS NOT NULL AS email_verified,
+                a.last_login,
+                ARRAY[a.role] AS roles,
+                EXISTS (SELECT 1 FROM app_private.passkeys AS p WHERE p.user_id = u.id) AS mfa_enabled,
+                u.created_at,
+                u.updated_at
+            FROM app.users AS u
+            JOIN app_private.accounts AS a
+            ON a.user_id = u.id
+            WHERE u.id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(profile)
+}
@@ -1,7 +1,14 @@
 pub mod accounts;
+pub mod admin;
 pub mod auth;
+pub mod authz;
+pub mod groups;
+pub mod me;
 mod not_found;
+pub mod oauth;
 mod openapi;
+pub mod orgs;
+pub mod passkeys;
 pub mod users;
 
 pub use not_found::not_found;
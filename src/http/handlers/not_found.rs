@@ -1,6 +1,5 @@
-use axum::response::IntoResponse;
-use hyper::StatusCode;
+use crate::Error;
 
-pub async fn not_found() -> impl IntoResponse {
-    (StatusCode::NOT_FOUND, "Not found")
+pub async fn not_found() -> Error {
+    Error::NotFound
 }
@@ -0,0 +1,63 @@
+use axum::{http::StatusCode, Extension};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    http::{extract::Json, jwt::NotImpersonated},
+    Error,
+};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApproveDeviceBody {
+    #[schema(example = "WDJB-MJHT")]
+    pub user_code: String,
+    #[schema(example = true)]
+    pub approve: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/device",
+    request_body = ApproveDeviceBody,
+    responses(
+        (status = 204, description = "The device was approved or denied"),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "No pending request for the code", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn approve_device(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+    Json(payload): Json<ApproveDeviceBody>,
+) -> Result<StatusCode, Error> {
+    let user_code = payload.user_code.trim().to_uppercase();
+
+    let result = sqlx::query(
+        // language=PostgreSQL
+        r#"
+            UPDATE app_private.device_codes
+            SET
+                user_id = CASE WHEN $3 THEN $2 END,
+                denied = NOT $3
+            WHERE user_code = $1
+            AND user_id IS NULL
+            AND NOT denied
+            AND expires_at > now()
+        "#,
+    )
+    .bind(user_code)
+    .bind(claims.sub)
+    .bind(payload.approve)
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
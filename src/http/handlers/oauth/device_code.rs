@@ -0,0 +1,77 @@
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+
+use crate::{
+    http::extract::{Form, Json},
+    Error, PUBLIC_URL,
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeviceCodeBody {
+    #[schema(example = "cdb-cli")]
+    pub client_id: String,
+}
+
+/// The device authorization response of RFC 8628 section 3.2.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    #[schema(example = "WDJB-MJHT")]
+    pub user_code: String,
+    #[schema(example = "http://localhost:3000/device")]
+    pub verification_uri: String,
+    #[schema(example = "http://localhost:3000/device?user_code=WDJB-MJHT")]
+    pub verification_uri_complete: String,
+    #[schema(example = 600)]
+    pub expires_in: i64,
+    #[schema(example = 5)]
+    pub interval: i32,
+}
+
+#[derive(Debug, FromRow)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    interval: i32,
+    expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/device/code",
+    request_body(content = DeviceCodeBody, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Device authorization started", body = DeviceCodeResponse),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn device_code(
+    Extension(pool): Extension<PgPool>,
+    Form(payload): Form<DeviceCodeBody>,
+) -> Result<Json<DeviceCodeResponse>, Error> {
+    let code = sqlx::query_as::<_, DeviceCode>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.device_codes (client_id)
+            VALUES ($1)
+            RETURNING device_code, user_code, interval, expires_at
+        "#,
+    )
+    .bind(&payload.client_id)
+    .fetch_one(&pool)
+    .await?;
+
+    let verification_uri = format!("{}/device", *PUBLIC_URL);
+
+    Ok(Json(DeviceCodeResponse {
+        verification_uri_complete: format!("{}?user_code={}", verification_uri, code.user_code),
+        verification_uri,
+        expires_in: (code.expires_at - Utc::now()).num_seconds(),
+        interval: code.interval,
+        device_code: code.device_code,
+        user_code: code.user_code,
+    }))
+}
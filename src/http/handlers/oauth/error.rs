@@ -0,0 +1,61 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{http::extract::Json, Error};
+
+/// Errors returned from the token endpoint, following RFC 6749 section 5.2
+/// and the device flow additions of RFC 8628 section 3.5.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthErrorCode {
+    AuthorizationPending,
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+    InvalidGrant,
+    UnsupportedGrantType,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthErrorResponse {
+    pub error: OAuthErrorCode,
+}
+
+#[derive(Debug)]
+pub enum OAuthError {
+    Grant(OAuthErrorCode),
+    Server(Error),
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        match self {
+            OAuthError::Grant(error) => {
+                (StatusCode::BAD_REQUEST, Json(OAuthErrorResponse { error })).into_response()
+            }
+            OAuthError::Server(err) => err.into_response(),
+        }
+    }
+}
+
+impl From<OAuthErrorCode> for OAuthError {
+    fn from(code: OAuthErrorCode) -> Self {
+        OAuthError::Grant(code)
+    }
+}
+
+impl From<Error> for OAuthError {
+    fn from(err: Error) -> Self {
+        OAuthError::Server(err)
+    }
+}
+
+impl From<sqlx::Error> for OAuthError {
+    fn from(err: sqlx::Error) -> Self {
+        OAuthError::Server(err.into())
+    }
+}
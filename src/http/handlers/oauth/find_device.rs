@@ -0,0 +1,73 @@
+use axum::Extension;
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    http::{
+        extract::{Json, Query},
+        jwt::NotImpersonated,
+    },
+    Error,
+};
+
+/// The query of the verification URI given to devices.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FindDeviceParams {
+    #[param(example = "WDJB-MJHT")]
+    pub user_code: String,
+}
+
+/// A pending device authorization request, shown to the user before they
+/// approve or deny it.
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceRequestResponse {
+    #[schema(example = "WDJB-MJHT")]
+    pub user_code: String,
+    #[schema(example = "cdb-cli")]
+    pub client_id: String,
+    #[schema(example = "1666461194804")]
+    #[serde(with = "ts_milliseconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/device",
+    responses(
+        (status = 200, description = "The pending request for the code", body = DeviceRequestResponse),
+        (status = 400, description = "Invalid JWT or missing code", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "No pending request for the code", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(FindDeviceParams)
+)]
+pub async fn find_device(
+    Extension(pool): Extension<PgPool>,
+    _session: NotImpersonated,
+    Query(params): Query<FindDeviceParams>,
+) -> Result<Json<DeviceRequestResponse>, Error> {
+    let user_code = params.user_code.trim().to_uppercase();
+
+    let request = sqlx::query_as::<_, DeviceRequestResponse>(
+        // language=PostgreSQL
+        r#"
+            SELECT user_code, client_id, expires_at
+            FROM app_private.device_codes
+            WHERE user_code = $1
+            AND user_id IS NULL
+            AND NOT denied
+            AND expires_at > now()
+        "#,
+    )
+    .bind(user_code)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(request))
+}
@@ -0,0 +1,11 @@
+mod approve_device;
+mod device_code;
+mod error;
+mod find_device;
+mod token;
+
+pub use approve_device::*;
+pub use device_code::*;
+pub use error::*;
+pub use find_device::*;
+pub use token::*;
@@ -0,0 +1,147 @@
+use axum::Extension;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{OAuthError, OAuthErrorCode};
+use crate::http::{
+    extract::{Form, Json},
+    handlers::auth::{AuthResponse, Session},
+};
+
+pub const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenBody {
+    #[schema(example = "urn:ietf:params:oauth:grant-type:device_code")]
+    pub grant_type: String,
+    pub device_code: String,
+    #[schema(example = "cdb-cli")]
+    pub client_id: String,
+}
+
+#[derive(Debug, FromRow)]
+struct PendingDevice {
+    user_id: Option<Uuid>,
+    denied: bool,
+    interval: i32,
+    last_polled_at: Option<DateTime<Utc>>,
+    expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/token",
+    request_body(content = TokenBody, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The device was approved", body = AuthResponse),
+        (status = 400, description = "The device is still pending, or the grant failed", body = OAuthErrorResponse),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn token(
+    Extension(pool): Extension<PgPool>,
+    Form(payload): Form<TokenBody>,
+) -> Result<Json<AuthResponse>, OAuthError> {
+    if payload.grant_type != DEVICE_CODE_GRANT {
+        return Err(OAuthErrorCode::UnsupportedGrantType.into());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let device = sqlx::query_as::<_, PendingDevice>(
+        // language=PostgreSQL
+        r#"
+            SELECT user_id, denied, interval, last_polled_at, expires_at
+            FROM app_private.device_codes
+            WHERE device_code = $1 AND client_id = $2
+            FOR UPDATE
+        "#,
+    )
+    .bind(&payload.device_code)
+    .bind(&payload.client_id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(OAuthErrorCode::InvalidGrant)?;
+
+    let now = Utc::now();
+
+    if device.expires_at <= now || device.denied {
+        sqlx::query(r#"DELETE FROM app_private.device_codes WHERE device_code = $1"#)
+            .bind(&payload.device_code)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        return Err(if device.denied {
+            OAuthErrorCode::AccessDenied
+        } else {
+            OAuthErrorCode::ExpiredToken
+        }
+        .into());
+    }
+
+    let too_soon = device
+        .last_polled_at
+        .is_some_and(|last| now - last < Duration::seconds(device.interval.into()));
+
+    if too_soon {
+        // Every poll that arrives too soon pushes the interval back by five seconds.
+        sqlx::query(
+            // language=PostgreSQL
+            r#"
+                UPDATE app_private.device_codes
+                SET interval = interval + 5, last_polled_at = now()
+                WHERE device_code = $1
+            "#,
+        )
+        .bind(&payload.device_code)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        return Err(OAuthErrorCode::SlowDown.into());
+    }
+
+    let user_id = match device.user_id {
+        Some(user_id) => user_id,
+        None => {
+            sqlx::query(
+                r#"UPDATE app_private.device_codes SET last_polled_at = now() WHERE device_code = $1"#,
+            )
+            .bind(&payload.device_code)
+            .execute(&mut tx)
+            .await?;
+            tx.commit().await?;
+
+            return Err(OAuthErrorCode::AuthorizationPending.into());
+        }
+    };
+
+    sqlx::query(r#"DELETE FROM app_private.device_codes WHERE device_code = $1"#)
+        .bind(&payload.device_code)
+        .execute(&mut tx)
+        .await?;
+
+    // `app_private.create_session` returns a row of nulls for inactive users,
+    // who may have been deactivated since approving the device.
+    let session = sqlx::query_as::<_, Session>(
+        // language=PostgreSQL
+        r#"SELECT role, user_id, refresh_token, refresh_token_expires
+            FROM app_private.create_session($1)
+            WHERE user_id IS NOT NULL"#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let session = session.ok_or(OAuthErrorCode::InvalidGrant)?;
+
+    tracing::info!("Device authorized for user with id `{}`", user_id);
+
+    Ok(Json(AuthResponse::issue(&pool, session).await?))
+}
@@ -1,26 +1,127 @@
-use super::{accounts, auth, users};
-use axum::Json;
+use super::{accounts, admin, auth, authz, groups, me, oauth, orgs, passkeys, users};
+use crate::http::extract::Json;
 use utoipa::{openapi, OpenApi};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         users::find_users,
+        users::search_users,
         users::find_user_by_id,
+        users::update_user,
+        users::delete_user,
+        users::find_avatar,
+        me::find_me,
+        me::update_me,
+        me::request_email_change,
+        me::upload_avatar,
+        me::find_my_permissions,
+        me::request_export,
+        me::find_export,
+        me::download_export,
+        me::request_erasure,
+        me::cancel_erasure,
+        groups::find_groups,
+        groups::find_group_by_id,
+        groups::create_group,
+        groups::update_group,
+        groups::delete_group,
+        groups::find_group_members,
+        groups::add_group_member,
+        groups::remove_group_member,
+        authz::check,
+        authz::find_tuples,
+        authz::write_tuples,
         accounts::register,
+        accounts::change_password,
+        accounts::confirm_email_change,
         auth::authorize,
-        auth::revalidate
+        auth::revalidate,
+        auth::switch_org,
+        orgs::create_org,
+        orgs::find_my_orgs,
+        orgs::find_members,
+        orgs::add_member,
+        orgs::remove_member,
+        orgs::create_invitation,
+        orgs::find_invitations,
+        orgs::revoke_invitation,
+        orgs::accept_invitation,
+        passkeys::start_registration,
+        passkeys::finish_registration,
+        passkeys::start_authentication,
+        passkeys::finish_authentication,
+        oauth::device_code,
+        oauth::token,
+        oauth::find_device,
+        oauth::approve_device,
+        admin::impersonate,
+        admin::import_users,
+        admin::export_users,
+        admin::deactivate_user,
+        admin::restore_user
     ),
     components(schemas(
         users::UserResponse,
         users::UsersResponse,
+        users::UserSearchResult,
+        users::UsersPage,
+        users::UserSearchPage,
+        users::UpdateUserBody,
+        me::ProfileResponse,
+        me::ChangeEmailBody,
+        me::AvatarUpload,
+        me::PermissionsResponse,
+        me::ExportResponse,
+        me::EraseBody,
+        me::ErasureResponse,
+        groups::GroupResponse,
+        groups::CreateGroupBody,
+        groups::UpdateGroupBody,
+        groups::GroupMemberResponse,
+        authz::CheckBody,
+        authz::CheckResponse,
+        authz::TupleBody,
+        authz::WriteTuplesBody,
         auth::AuthBody,
         auth::AuthResponse,
         auth::RevalidateBody,
         auth::RevalidateResponse,
+        auth::SwitchOrgBody,
+        auth::SwitchOrgResponse,
+        orgs::OrganizationResponse,
+        orgs::CreateOrgBody,
+        orgs::MemberResponse,
+        orgs::AddMemberBody,
+        orgs::InvitationResponse,
+        orgs::InviteBody,
+        orgs::AcceptInvitationBody,
+        orgs::AcceptInvitationResponse,
         accounts::RegisterBody,
         accounts::RegisterResponse,
-        crate::Error
+        accounts::ChangePasswordBody,
+        accounts::ConfirmEmailBody,
+        passkeys::StartRegistrationResponse,
+        passkeys::FinishRegistrationBody,
+        passkeys::PasskeyResponse,
+        passkeys::StartAuthenticationBody,
+        passkeys::StartAuthenticationResponse,
+        passkeys::FinishAuthenticationBody,
+        oauth::DeviceCodeBody,
+        oauth::DeviceCodeResponse,
+        oauth::TokenBody,
+        oauth::DeviceRequestResponse,
+        oauth::ApproveDeviceBody,
+        oauth::OAuthErrorCode,
+        oauth::OAuthErrorResponse,
+        admin::ImpersonateBody,
+        admin::ImpersonateResponse,
+        crate::import::FileFormat,
+        crate::import::ImportReport,
+        crate::import::RowReport,
+        crate::import::RowStatus,
+        crate::Error,
+        crate::http::error::FieldError
     ))
 )]
 pub(super) struct ApiDoc;
@@ -0,0 +1,507 @@
+use axum::{http::StatusCode, Extension};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use super::members::{caller_role, can_manage};
+use crate::{
+    audit,
+    http::{
+        extract::{Json, Path, ValidatedJson},
+        handlers::accounts::RegisterBody,
+        jwt::Claims,
+    },
+    mail::{Email, SharedMailer},
+    Error, PUBLIC_URL,
+};
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    /// One of `owner`, `admin` or `member`
+    #[schema(example = "member")]
+    pub role: String,
+    /// One of `pending`, `accepted`, `revoked` or `expired`
+    #[schema(example = "pending")]
+    pub status: String,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1666461194804")]
+    #[serde(with = "ts_milliseconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteBody {
+    #[schema(example = "major.tom@gmail.com")]
+    #[validate(email)]
+    pub email: String,
+    /// One of `owner`, `admin` or `member`, defaults to `member`
+    #[schema(example = "member")]
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptInvitationBody {
+    pub token: Uuid,
+    /// Only needed when no account exists for the invited address yet
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    /// Only needed when no account exists for the invited address yet
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    /// Only needed when no account exists for the invited address yet
+    #[schema(example = "Z1gGy.Pl4y3d!GuI74R")]
+    #[validate(length(min = 8))]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptInvitationResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub organization_id: Uuid,
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub user_id: Uuid,
+    #[schema(example = "member")]
+    pub role: String,
+    /// Whether a new account was registered for the invitee
+    #[schema(example = false)]
+    pub registered: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct OpenInvitation {
+    id: Uuid,
+    organization_id: Uuid,
+    email: String,
+    role: String,
+}
+
+async fn find_invitation<'e, E>(executor: E, id: Uuid) -> Result<InvitationResponse, Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as::<_, InvitationResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT
+            id,
+            email,
+            role,
+            app_private.invitation_status(i) AS status,
+            created_at,
+            expires_at
+          FROM app_private.invitations AS i
+          WHERE id = $1
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+/// Invite someone to the organization by email. Inviting the same address
+/// again replaces the open invitation, so only the latest link works.
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/invitations",
+    request_body = InviteBody,
+    responses(
+        (status = 201, description = "Invitation sent", body = InvitationResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not allowed to invite members with this role", body = Error),
+        (status = 409, description = "The address already belongs to a member", body = Error),
+        (status = 422, description = "Invalid email or role", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization")
+    )
+)]
+pub async fn create_invitation(
+    Extension(pool): Extension<PgPool>,
+    Extension(mailer): Extension<SharedMailer>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<InviteBody>,
+) -> Result<(StatusCode, Json<InvitationResponse>), Error> {
+    let role = payload.role.unwrap_or_else(|| "member".to_string());
+
+    if !matches!(role.as_str(), "owner" | "admin" | "member") {
+        return Err(Error::invalid_field("role", "invalid"));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if !can_manage(&caller_role(&mut tx, id, &claims).await?, &role) {
+        return Err(Error::Forbidden);
+    }
+
+    let is_member = sqlx::query_scalar::<_, bool>(
+        // language=PostgreSQL
+        r#"
+          SELECT EXISTS (
+            SELECT 1
+            FROM app.memberships AS m
+            JOIN app_private.accounts AS a
+            ON a.user_id = m.user_id
+            WHERE m.organization_id = $1 AND lower(a.email) = lower($2)
+          )
+      "#,
+    )
+    .bind(id)
+    .bind(&payload.email)
+    .fetch_one(&mut tx)
+    .await?;
+
+    if is_member {
+        return Err(Error::Conflict);
+    }
+
+    let (invitation_id, token) = sqlx::query_as::<_, (Uuid, Uuid)>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app_private.invitations (organization_id, email, role, invited_by)
+          VALUES ($1, $2, $3, $4)
+          ON CONFLICT (organization_id, lower(email)) WHERE accepted_at IS NULL AND revoked_at IS NULL
+          DO UPDATE SET
+            email = excluded.email,
+            role = excluded.role,
+            token = excluded.token,
+            invited_by = excluded.invited_by,
+            created_at = excluded.created_at,
+            expires_at = excluded.expires_at
+          RETURNING id, token
+      "#,
+    )
+    .bind(id)
+    .bind(&payload.email)
+    .bind(&role)
+    .bind(claims.sub)
+    .fetch_one(&mut tx)
+    .await?;
+
+    let org_name = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT name FROM app.organizations WHERE id = $1"#,
+    )
+    .bind(id)
+    .fetch_one(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        None,
+        "organization.invitation.create",
+        json!({ "organization_id": id, "invitation_id": invitation_id, "role": role }),
+    )
+    .await?;
+
+    let invitation = find_invitation(&mut tx, invitation_id).await?;
+
+    tx.commit().await?;
+
+    mailer
+        .send(Email {
+            to: payload.email,
+            subject: format!("You have been invited to join {org_name}"),
+            body: format!(
+                "You have been invited to join {org_name}. Accept the invitation at \
+                 {}/invitations/accept with the token {token}.\n\n\
+                 The invitation expires on {}.",
+                *PUBLIC_URL,
+                invitation.expires_at.format("%B %-d, %Y at %H:%M UTC")
+            ),
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/invitations",
+    responses(
+        (status = 200, description = "Every invitation of the organization, newest first", body = [InvitationResponse]),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not allowed to manage members", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization")
+    )
+)]
+pub async fn find_invitations(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<InvitationResponse>>, Error> {
+    if !can_manage(&caller_role(&pool, id, &claims).await?, "member") {
+        return Err(Error::Forbidden);
+    }
+
+    let invitations = sqlx::query_as::<_, InvitationResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT
+            id,
+            email,
+            role,
+            app_private.invitation_status(i) AS status,
+            created_at,
+            expires_at
+          FROM app_private.invitations AS i
+          WHERE organization_id = $1
+          ORDER BY created_at DESC, id
+      "#,
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(invitations))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/invitations/{invitation_id}",
+    responses(
+        (status = 204, description = "Invitation revoked"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not allowed to manage members", body = Error),
+        (status = 404, description = "No open invitation with this id", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization"),
+        ("invitation_id" = Uuid, Path, description = "The id of the invitation to revoke")
+    )
+)]
+pub async fn revoke_invitation(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    path: Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    let Path((id, invitation_id)) = path;
+
+    let mut tx = pool.begin().await?;
+
+    // Only those who manage members learn whether an invitation exists.
+    let caller = caller_role(&mut tx, id, &claims).await?;
+
+    if !can_manage(&caller, "member") {
+        return Err(Error::Forbidden);
+    }
+
+    let role = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+          SELECT role
+          FROM app_private.invitations
+          WHERE id = $1 AND organization_id = $2 AND accepted_at IS NULL AND revoked_at IS NULL
+          FOR UPDATE
+      "#,
+    )
+    .bind(invitation_id)
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    if !can_manage(&caller, &role) {
+        return Err(Error::Forbidden);
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app_private.invitations SET revoked_at = now() WHERE id = $1"#,
+    )
+    .bind(invitation_id)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        None,
+        "organization.invitation.revoke",
+        json!({ "organization_id": id, "invitation_id": invitation_id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Accept an invitation with the token that was emailed to the invitee. The
+/// account with the invited address joins the organization, or, when there is
+/// none, one is registered and joins in the same transaction. Having the
+/// token proves the invitee owns the address, so it is marked as verified.
+#[utoipa::path(
+    post,
+    path = "/invitations/accept",
+    request_body = AcceptInvitationBody,
+    responses(
+        (status = 200, description = "Invitation accepted", body = AcceptInvitationResponse),
+        (status = 404, description = "Unknown, expired, revoked or used token", body = Error),
+        (status = 409, description = "The account with the invited address is deactivated or deleted", body = Error),
+        (status = 422, description = "Registration details missing or invalid", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn accept_invitation(
+    Extension(pool): Extension<PgPool>,
+    ValidatedJson(payload): ValidatedJson<AcceptInvitationBody>,
+) -> Result<Json<AcceptInvitationResponse>, Error> {
+    let mut tx = pool.begin().await?;
+
+    let invitation = sqlx::query_as::<_, OpenInvitation>(
+        // language=PostgreSQL
+        r#"
+          SELECT id, organization_id, email, role
+          FROM app_private.invitations
+          WHERE token = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > now()
+          FOR UPDATE
+      "#,
+    )
+    .bind(payload.token)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let existing = sqlx::query_as::<_, (Uuid, bool)>(
+        // language=PostgreSQL
+        r#"
+          SELECT user_id, app_private.is_active_user(user_id)
+          FROM app_private.accounts
+          WHERE lower(email) = lower($1)
+      "#,
+    )
+    .bind(&invitation.email)
+    .fetch_optional(&mut tx)
+    .await?;
+
+    let (user_id, registered) = match existing {
+        // Deactivated and deleted accounts can't join, and the invitation
+        // stays open in case they are restored.
+        Some((_, false)) => return Err(Error::Conflict),
+        Some((user_id, true)) => (user_id, false),
+        None => {
+            let mut missing = ValidationErrors::new();
+
+            for (field, value) in [
+                ("first_name", &payload.first_name),
+                ("last_name", &payload.last_name),
+                ("password", &payload.password),
+            ] {
+                if value.is_none() {
+                    missing.add(field, ValidationError::new("required"));
+                }
+            }
+
+            if !missing.is_empty() {
+                return Err(missing.into());
+            }
+
+            // Registering through an invitation is held to the same rules as
+            // registering directly.
+            let registration = RegisterBody {
+                first_name: payload.first_name,
+                last_name: payload.last_name,
+                email: invitation.email.clone(),
+                password: payload.password.unwrap_or_default(),
+            };
+            registration.validate()?;
+
+            let user_id = sqlx::query_scalar::<_, Uuid>(
+                // language=PostgreSQL
+                r#"SELECT id FROM app.register_user($1, $2, $3, $4)"#,
+            )
+            .bind(registration.first_name)
+            .bind(registration.last_name)
+            .bind(registration.email)
+            .bind(registration.password)
+            .fetch_one(&mut tx)
+            .await?;
+
+            (user_id, true)
+        }
+    };
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          UPDATE app_private.accounts
+          SET email_verified_at = coalesce(email_verified_at, now())
+          WHERE user_id = $1
+      "#,
+    )
+    .bind(user_id)
+    .execute(&mut tx)
+    .await?;
+
+    // Someone who already joined keeps the role they have.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.memberships (organization_id, user_id, role)
+          VALUES ($1, $2, $3)
+          ON CONFLICT DO NOTHING
+      "#,
+    )
+    .bind(invitation.organization_id)
+    .bind(user_id)
+    .bind(&invitation.role)
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app_private.invitations SET accepted_at = now() WHERE id = $1"#,
+    )
+    .bind(invitation.id)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(user_id),
+        Some(user_id),
+        "organization.invitation.accept",
+        json!({ "organization_id": invitation.organization_id, "invitation_id": invitation.id, "registered": registered }),
+    )
+    .await?;
+
+    let role = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT role FROM app.memberships WHERE organization_id = $1 AND user_id = $2"#,
+    )
+    .bind(invitation.organization_id)
+    .bind(user_id)
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(AcceptInvitationResponse {
+        organization_id: invitation.organization_id,
+        user_id,
+        role,
+        registered,
+    }))
+}
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Extension};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    http::{
+        extract::{Json, Path},
+        jwt::Claims,
+        tenant::org_role,
+    },
+    permissions::PermissionCache,
+    Error,
+};
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub user_id: Uuid,
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    /// One of `owner`, `admin` or `member`
+    #[schema(example = "member")]
+    pub role: String,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddMemberBody {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub user_id: Uuid,
+    /// One of `owner`, `admin` or `member`, defaults to `member`
+    #[schema(example = "member")]
+    pub role: Option<String>,
+}
+
+/// The role the session holds in the organization. Admins acting as
+/// themselves are treated as owners of every organization.
+pub(super) async fn caller_role<'e, E>(
+    executor: E,
+    org_id: Uuid,
+    claims: &Claims,
+) -> Result<String, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let role = org_role(executor, org_id, claims.sub).await?;
+
+    match role {
+        _ if claims.is_admin() => Ok("owner".to_string()),
+        Some(role) => Ok(role),
+        None => Err(Error::Forbidden),
+    }
+}
+
+/// Owners and admins manage members, but only owners manage other owners.
+pub(super) fn can_manage(caller: &str, target: &str) -> bool {
+    match caller {
+        "owner" => true,
+        "admin" => target != "owner",
+        _ => false,
+    }
+}
+
+async fn find_member<'e, E>(
+    executor: E,
+    org_id: Uuid,
+    user_id: Uuid,
+) -> Result<MemberResponse, Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as::<_, MemberResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT m.user_id, u.first_name, u.last_name, a.email, m.role, m.created_at
+          FROM app.memberships AS m
+          JOIN app.users AS u
+          ON u.id = m.user_id
+          JOIN app_private.accounts AS a
+          ON a.user_id = m.user_id
+          WHERE m.organization_id = $1 AND m.user_id = $2
+      "#,
+    )
+    .bind(org_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/members",
+    responses(
+        (status = 200, description = "The members of the organization", body = [MemberResponse]),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not a member of the organization", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization")
+    )
+)]
+pub async fn find_members(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<MemberResponse>>, Error> {
+    caller_role(&pool, id, &claims).await?;
+
+    let members = sqlx::query_as::<_, MemberResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT m.user_id, u.first_name, u.last_name, a.email, m.role, m.created_at
+          FROM app.memberships AS m
+          JOIN app.users AS u
+          ON u.id = m.user_id
+          JOIN app_private.accounts AS a
+          ON a.user_id = m.user_id
+          WHERE m.organization_id = $1
+          ORDER BY m.created_at, m.user_id
+      "#,
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(members))
+}
+
+/// Owners and admins add members directly, other users are asked to join
+/// through an invitation.
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/members",
+    request_body = AddMemberBody,
+    responses(
+        (status = 201, description = "Member added", body = MemberResponse),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not allowed to manage members with this role", body = Error),
+        (status = 404, description = "User not found", body = Error),
+        (status = 409, description = "The user is already a member", body = Error),
+        (status = 422, description = "Invalid role", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization")
+    )
+)]
+pub async fn add_member(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<AddMemberBody>,
+) -> Result<(StatusCode, Json<MemberResponse>), Error> {
+    let role = payload.role.unwrap_or_else(|| "member".to_string());
+
+    if !matches!(role.as_str(), "owner" | "admin" | "member") {
+        return Err(Error::invalid_field("role", "invalid"));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if !can_manage(&caller_role(&mut tx, id, &claims).await?, &role) {
+        return Err(Error::Forbidden);
+    }
+
+    sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.users WHERE id = $1 AND deactivated_at IS NULL AND deleted_at IS NULL"#,
+    )
+    .bind(payload.user_id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.memberships (organization_id, user_id, role)
+          VALUES ($1, $2, $3)
+          ON CONFLICT DO NOTHING
+          RETURNING user_id
+      "#,
+    )
+    .bind(id)
+    .bind(payload.user_id)
+    .bind(&role)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::Conflict)?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(payload.user_id),
+        "organization.member.add",
+        json!({ "organization_id": id, "role": role }),
+    )
+    .await?;
+
+    let member = find_member(&mut tx, id, payload.user_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(member)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/members/{user_id}",
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 403, description = "Not allowed to remove this member", body = Error),
+        (status = 404, description = "Not a member", body = Error),
+        (status = 409, description = "The last owner cannot be removed", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The id of the organization"),
+        ("user_id" = Uuid, Path, description = "The id of the member to remove")
+    )
+)]
+pub async fn remove_member(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<Arc<PermissionCache>>,
+    claims: Claims,
+    path: Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    let Path((id, user_id)) = path;
+    let mut tx = pool.begin().await?;
+
+    let caller = caller_role(&mut tx, id, &claims).await?;
+
+    // Lock the memberships of the organization so that two owners can't
+    // remove each other at the same time.
+    let roles = sqlx::query_as::<_, (Uuid, String)>(
+        // language=PostgreSQL
+        r#"SELECT user_id, role FROM app.memberships WHERE organization_id = $1 FOR UPDATE"#,
+    )
+    .bind(id)
+    .fetch_all(&mut tx)
+    .await?;
+
+    let role = roles
+        .iter()
+        .find(|(member, _)| *member == user_id)
+        .map(|(_, role)| role.as_str())
+        .ok_or(Error::NotFound)?;
+
+    // Members may always leave, but an organization must keep an owner.
+    if user_id != claims.sub && !can_manage(&caller, role) {
+        return Err(Error::Forbidden);
+    }
+
+    if role == "owner" && roles.iter().filter(|(_, role)| role == "owner").count() == 1 {
+        return Err(Error::Conflict);
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app.memberships WHERE organization_id = $1 AND user_id = $2"#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(user_id),
+        "organization.member.remove",
+        json!({ "organization_id": id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    // Leaving an organization leaves its groups too.
+    cache.invalidate();
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,7 @@
+mod invitations;
+mod members;
+mod organizations;
+
+pub use invitations::*;
+pub use members::*;
+pub use organizations::*;
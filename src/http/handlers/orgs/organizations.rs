@@ -0,0 +1,126 @@
+use axum::{http::StatusCode, Extension};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    audit,
+    http::{
+        extract::{Json, ValidatedJson},
+        jwt::Claims,
+        rls::ScopedTx,
+    },
+    Error,
+};
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationResponse {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    #[schema(example = "Spiders from Mars")]
+    pub name: String,
+    /// The role of the current user in the organization, one of `owner`,
+    /// `admin` or `member`
+    #[schema(example = "owner")]
+    pub role: String,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOrgBody {
+    #[schema(example = "Spiders from Mars")]
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs",
+    request_body = CreateOrgBody,
+    responses(
+        (status = 201, description = "Organization created, owned by the current user", body = OrganizationResponse),
+        (status = 400, description = "Malformed JSON body or invalid token", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn create_org(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateOrgBody>,
+) -> Result<(StatusCode, Json<OrganizationResponse>), Error> {
+    let mut tx = pool.begin().await?;
+
+    let org = sqlx::query_as::<_, OrganizationResponse>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app.organizations (name)
+          VALUES ($1)
+          RETURNING id, name, 'owner' AS role, created_at
+      "#,
+    )
+    .bind(payload.name.trim())
+    .fetch_one(&mut tx)
+    .await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"INSERT INTO app.memberships (organization_id, user_id, role) VALUES ($1, $2, 'owner')"#,
+    )
+    .bind(org.id)
+    .bind(claims.sub)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(
+        &mut tx,
+        Some(claims.sub),
+        Some(claims.sub),
+        "organization.create",
+        json!({ "organization_id": org.id }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(org)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs",
+    responses(
+        (status = 200, description = "The organizations the current user belongs to", body = [OrganizationResponse]),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn find_my_orgs(
+    claims: Claims,
+    mut tx: ScopedTx,
+) -> Result<Json<Vec<OrganizationResponse>>, Error> {
+    let orgs = sqlx::query_as::<_, OrganizationResponse>(
+        // language=PostgreSQL
+        r#"
+          SELECT o.id, o.name, m.role, o.created_at
+          FROM app.memberships AS m
+          JOIN app.organizations AS o
+          ON o.id = m.organization_id
+          WHERE m.user_id = $1
+          ORDER BY o.name, o.id
+      "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    Ok(Json(orgs))
+}
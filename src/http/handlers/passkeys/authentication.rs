@@ -0,0 +1,159 @@
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+use webauthn_rs::prelude::{Passkey, PublicKeyCredential, RequestChallengeResponse};
+
+use super::{challenge, registration::registered_passkeys};
+use crate::{
+    http::{
+        extract::{Json, ValidatedJson},
+        handlers::auth::{AuthResponse, Session},
+    },
+    Error, WEBAUTHN,
+};
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StartAuthenticationBody {
+    #[schema(example = "david.bowie@gmail.com")]
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StartAuthenticationResponse {
+    pub challenge_id: Uuid,
+    /// The options to pass to `navigator.credentials.get()`
+    #[schema(value_type = Object)]
+    pub options: RequestChallengeResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishAuthenticationBody {
+    pub challenge_id: Uuid,
+    /// The assertion returned by `navigator.credentials.get()`
+    #[schema(value_type = Object)]
+    pub credential: PublicKeyCredential,
+}
+
+/// Start logging in with a passkey. Unknown addresses and accounts without
+/// passkeys are refused alike, so that the answer doesn't tell which
+/// addresses have an account.
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/start",
+    request_body = StartAuthenticationBody,
+    responses(
+        (status = 200, description = "Authentication ceremony started", body = StartAuthenticationResponse),
+        (status = 400, description = "Malformed JSON body", body = Error),
+        (status = 401, description = "No passkeys are registered for the address", body = Error),
+        (status = 422, description = "Invalid fields", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn start_authentication(
+    Extension(pool): Extension<PgPool>,
+    ValidatedJson(payload): ValidatedJson<StartAuthenticationBody>,
+) -> Result<Json<StartAuthenticationResponse>, Error> {
+    let user_id = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"SELECT user_id FROM app_private.accounts WHERE email = $1"#,
+    )
+    .bind(&payload.email)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
+
+    let passkeys = registered_passkeys(&pool, user_id).await?;
+
+    if passkeys.is_empty() {
+        return Err(Error::InvalidCredentials);
+    }
+
+    let (options, state) = WEBAUTHN.start_passkey_authentication(&passkeys)?;
+
+    let challenge_id = challenge::store(&pool, user_id, challenge::AUTHENTICATION, &state).await?;
+
+    Ok(Json(StartAuthenticationResponse {
+        challenge_id,
+        options,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/finish",
+    request_body = FinishAuthenticationBody,
+    responses(
+        (status = 200, description = "Authentication successful", body = AuthResponse),
+        (status = 401, description = "The assertion could not be verified", body = Error),
+        (status = 404, description = "The challenge does not exist or has expired", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn finish_authentication(
+    Extension(pool): Extension<PgPool>,
+    Json(payload): Json<FinishAuthenticationBody>,
+) -> Result<Json<AuthResponse>, Error> {
+    let mut tx = pool.begin().await?;
+
+    let (user_id, state) =
+        challenge::take(&mut tx, payload.challenge_id, challenge::AUTHENTICATION).await?;
+
+    let result = WEBAUTHN
+        .finish_passkey_authentication(&payload.credential, &state)
+        .map_err(|_| Error::InvalidCredentials)?;
+
+    let stored = sqlx::query_scalar::<_, serde_json::Value>(
+        // language=PostgreSQL
+        r#"
+            SELECT passkey FROM app_private.passkeys
+            WHERE user_id = $1 AND credential_id = $2
+            FOR UPDATE
+        "#,
+    )
+    .bind(user_id)
+    .bind(result.cred_id().as_ref())
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
+
+    let mut passkey: Passkey = serde_json::from_value(stored)?;
+    passkey.update_credential(&result);
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+            UPDATE app_private.passkeys
+            SET passkey = $3, last_used_at = now()
+            WHERE user_id = $1 AND credential_id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(result.cred_id().as_ref())
+    .bind(serde_json::to_value(&passkey)?)
+    .execute(&mut tx)
+    .await?;
+
+    // `app_private.create_session` returns a row of nulls for inactive users,
+    // who may have been deactivated since the challenge was issued.
+    let session = sqlx::query_as::<_, Session>(
+        // language=PostgreSQL
+        r#"SELECT role, user_id, refresh_token, refresh_token_expires
+            FROM app_private.create_session($1)
+            WHERE user_id IS NOT NULL"#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(Error::InvalidCredentials)?;
+
+    tx.commit().await?;
+
+    Ok(Json(AuthResponse::issue(&pool, session).await?))
+}
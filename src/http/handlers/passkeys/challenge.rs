@@ -0,0 +1,64 @@
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{PgExecutor, Row};
+use uuid::Uuid;
+
+use crate::Error;
+
+pub const REGISTRATION: &str = "registration";
+pub const AUTHENTICATION: &str = "authentication";
+
+/// Persist the state of a ceremony so it can be finished by a later request.
+pub async fn store<'e, E, S>(
+    executor: E,
+    user_id: Uuid,
+    ceremony: &str,
+    state: &S,
+) -> Result<Uuid, Error>
+where
+    E: PgExecutor<'e>,
+    S: Serialize,
+{
+    let state = serde_json::to_value(state)?;
+
+    let id = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.webauthn_challenges (user_id, ceremony, state)
+            VALUES ($1, $2, $3)
+            RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(ceremony)
+    .bind(state)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Remove a pending ceremony, returning the user it belongs to and its state.
+/// A challenge can only be taken once, so replaying a response fails.
+pub async fn take<'e, E, S>(executor: E, id: Uuid, ceremony: &str) -> Result<(Uuid, S), Error>
+where
+    E: PgExecutor<'e>,
+    S: DeserializeOwned,
+{
+    let row = sqlx::query(
+        // language=PostgreSQL
+        r#"
+            DELETE FROM app_private.webauthn_challenges
+            WHERE id = $1 AND ceremony = $2 AND expires_at > now()
+            RETURNING user_id, state
+        "#,
+    )
+    .bind(id)
+    .bind(ceremony)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let state = serde_json::from_value(row.try_get("state")?)?;
+
+    Ok((row.try_get("user_id")?, state))
+}
@@ -0,0 +1,6 @@
+mod authentication;
+mod challenge;
+mod registration;
+
+pub use authentication::*;
+pub use registration::*;
@@ -0,0 +1,165 @@
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool, Row};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use webauthn_rs::prelude::{CreationChallengeResponse, Passkey, RegisterPublicKeyCredential};
+
+use super::challenge;
+use crate::{
+    http::{extract::Json, jwt::NotImpersonated},
+    Error, WEBAUTHN,
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StartRegistrationResponse {
+    pub challenge_id: Uuid,
+    /// The options to pass to `navigator.credentials.create()`
+    #[schema(value_type = Object)]
+    pub options: CreationChallengeResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishRegistrationBody {
+    pub challenge_id: Uuid,
+    #[schema(example = "Laptop")]
+    pub name: Option<String>,
+    /// The credential returned by `navigator.credentials.create()`
+    #[schema(value_type = Object)]
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Debug, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyResponse {
+    pub id: Uuid,
+    #[schema(example = "Laptop")]
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/start",
+    responses(
+        (status = 200, description = "Registration ceremony started", body = StartRegistrationResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn start_registration(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+) -> Result<Json<StartRegistrationResponse>, Error> {
+    let user = sqlx::query(
+        // language=PostgreSQL
+        r#"
+            SELECT a.email, concat_ws(' ', u.first_name, u.last_name) AS display_name
+            FROM app.users AS u
+            JOIN app_private.accounts AS a
+            ON a.user_id = u.id
+            WHERE u.id = $1
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let email: String = user.try_get("email")?;
+    let display_name: String = user.try_get("display_name")?;
+
+    let exclude_credentials = registered_passkeys(&pool, claims.sub)
+        .await?
+        .iter()
+        .map(|passkey| passkey.cred_id().clone())
+        .collect();
+
+    let (options, state) = WEBAUTHN.start_passkey_registration(
+        claims.sub,
+        &email,
+        if display_name.is_empty() {
+            &email
+        } else {
+            &display_name
+        },
+        Some(exclude_credentials),
+    )?;
+
+    let challenge_id = challenge::store(&pool, claims.sub, challenge::REGISTRATION, &state).await?;
+
+    Ok(Json(StartRegistrationResponse {
+        challenge_id,
+        options,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/finish",
+    request_body = FinishRegistrationBody,
+    responses(
+        (status = 200, description = "Passkey registered", body = PasskeyResponse),
+        (status = 400, description = "Invalid JWT", body = Error),
+        (status = 403, description = "Not allowed while impersonating", body = Error),
+        (status = 404, description = "The challenge does not exist or has expired", body = Error),
+        (status = 422, description = "The credential could not be verified", body = Error),
+        (status = 500, description = "Internal error", body = Error)
+    )
+)]
+pub async fn finish_registration(
+    Extension(pool): Extension<PgPool>,
+    NotImpersonated(claims): NotImpersonated,
+    Json(payload): Json<FinishRegistrationBody>,
+) -> Result<Json<PasskeyResponse>, Error> {
+    let (user_id, state) =
+        challenge::take(&pool, payload.challenge_id, challenge::REGISTRATION).await?;
+
+    if user_id != claims.sub {
+        return Err(Error::Forbidden);
+    }
+
+    let passkey = WEBAUTHN
+        .finish_passkey_registration(&payload.credential, &state)
+        .map_err(|_| Error::invalid_field("credential", "invalid"))?;
+
+    let response = sqlx::query_as::<_, PasskeyResponse>(
+        // language=PostgreSQL
+        r#"
+            INSERT INTO app_private.passkeys (user_id, credential_id, name, passkey)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, name, created_at
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(passkey.cred_id().as_ref())
+    .bind(payload.name)
+    .bind(serde_json::to_value(&passkey)?)
+    .fetch_one(&pool)
+    .await?;
+
+    tracing::info!("Registered passkey for user with id `{}`", claims.sub);
+
+    Ok(Json(response))
+}
+
+/// Load every passkey registered to a user.
+pub(super) async fn registered_passkeys(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<Passkey>, Error> {
+    sqlx::query_scalar::<_, serde_json::Value>(
+        // language=PostgreSQL
+        r#"SELECT passkey FROM app_private.passkeys WHERE user_id = $1"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|passkey| serde_json::from_value(passkey).map_err(Error::from))
+    .collect()
+}
@@ -0,0 +1,123 @@
+use axum::{body::Bytes, http::header::CONTENT_TYPE, response::IntoResponse, Extension};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    blob::SharedBlobStore,
+    http::{extract::Path, jwt::Claims, rls::ScopedTx, tenant::can_see_user},
+    Error,
+};
+
+/// The image formats accepted as avatars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageFormat {
+    /// Detect the format from the leading bytes of the file, so that the
+    /// declared content type does not have to be trusted.
+    pub(crate) fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(ImageFormat::Png),
+            [0xFF, 0xD8, 0xFF, ..] => Some(ImageFormat::Jpeg),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(ImageFormat::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => {
+                Some(ImageFormat::Webp)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "image/png" => Some(ImageFormat::Png),
+            "image/jpeg" => Some(ImageFormat::Jpeg),
+            "image/gif" => Some(ImageFormat::Gif),
+            "image/webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "png" => Some(ImageFormat::Png),
+            "jpg" => Some(ImageFormat::Jpeg),
+            "gif" => Some(ImageFormat::Gif),
+            "webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/avatar",
+    responses(
+        (status = 200, description = "The user's avatar image", content_type = "image/*"),
+        (status = 400, description = "Invalid token", body = Error),
+        (status = 404, description = "User or avatar not found, or not in the active organization", body = Error),
+        (status = 500, description = "Internal Error", body = Error)
+    ),
+    params(
+        ("id" = Uuid, Path, description = "The user's id")
+    )
+)]
+pub async fn find_avatar(
+    Extension(pool): Extension<PgPool>,
+    Extension(blob_store): Extension<SharedBlobStore>,
+    claims: Claims,
+    mut tx: ScopedTx,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    // Avatars are as private as the rest of the user.
+    if !can_see_user(&pool, &claims, id).await? {
+        return Err(Error::NotFound);
+    }
+
+    let key = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+          SELECT avatar_key
+          FROM app.users
+          WHERE id = $1
+          AND avatar_key IS NOT NULL
+          AND deactivated_at IS NULL
+          AND deleted_at IS NULL
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let format = key
+        .rsplit_once('.')
+        .and_then(|(_, extension)| ImageFormat::from_extension(extension))
+        .ok_or(Error::InternalError)?;
+
+    let bytes: Bytes = blob_store.get(&key).await?.ok_or(Error::NotFound)?;
+
+    Ok(([(CONTENT_TYPE, format.content_type())], bytes))
+}
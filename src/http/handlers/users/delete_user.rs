@@ -0,0 +1,54 @@
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use super::lock_user;
+use crate::{
+    http::{etag::IfMatch, extract::Path, jwt::NotImpersonated, rls::ScopedTx},
+    Error,
+};
+
+#[utoipa::path(
+  delete,
+  path = "/users/{id}",
+  responses(
+      (status = 204, description = "User deleted"),
+      (status = 400, description = "Invalid JWT", body = Error),
+      (status = 403, description = "Not allowed to delete the user", body = Error),
+      (status = 404, description = "User not found", body = Error),
+      (status = 412, description = "The user has changed since the version in `If-Match`", body = Error),
+      (status = 428, description = "The `If-Match` header is missing", body = Error),
+      (status = 500, description = "Internal Error", body = Error)
+  ),
+  params(
+      ("id" = Uuid, Path, description = "The user's id"),
+      ("If-Match" = String, Header, description = "The `ETag` of the version being deleted")
+  )
+)]
+pub async fn delete_user(
+    NotImpersonated(claims): NotImpersonated,
+    mut tx: ScopedTx,
+    Path(id): Path<Uuid>,
+    if_match: IfMatch,
+) -> Result<StatusCode, Error> {
+    if !claims.can_manage(id) {
+        return Err(Error::Forbidden);
+    }
+
+    if_match.check(&lock_user(&mut *tx, id).await?)?;
+
+    // Users are only marked as deleted, and purged once the retention window
+    // has passed. Admins can restore them until then.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deleted_at = now() WHERE id = $1"#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!("Deleted user with id `{}`", id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
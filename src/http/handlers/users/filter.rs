@@ -0,0 +1,281 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::UsersResponse;
+use crate::{
+    http::pagination::{invalid_cursor, Pagination},
+    Error,
+};
+
+/// The query parameters to filter and sort the users list by.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserFilter {
+    /// Only users with an email address at this domain
+    #[param(example = "gmail.com")]
+    pub email_domain: Option<String>,
+    /// Only users created at or after this date
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only users created before this date
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only users whose email address is, or is not, verified
+    pub verified: Option<bool>,
+    /// Only users with this role, either `admin` or `user`
+    #[param(example = "user")]
+    pub role: Option<String>,
+    /// A comma separated list of columns to sort by, each optionally prefixed
+    /// with `-` to sort in descending order. One of `created_at`, `updated_at`,
+    /// `first_name`, `last_name` and `email`
+    #[param(example = "created_at,-last_name")]
+    pub sort: Option<String>,
+    /// Include deactivated and deleted users, admins only
+    pub include_inactive: Option<bool>,
+}
+
+impl UserFilter {
+    /// Append the filters as conditions to a query with a `WHERE` clause over
+    /// `app.users AS u` joined with `app_private.accounts AS a`.
+    pub fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) -> Result<(), Error> {
+        if self.include_inactive != Some(true) {
+            query.push(" AND u.deactivated_at IS NULL AND u.deleted_at IS NULL");
+        }
+
+        if let Some(domain) = &self.email_domain {
+            query
+                .push(" AND lower(split_part(a.email, '@', 2)) = lower(")
+                .push_bind(domain.clone())
+                .push(")");
+        }
+
+        if let Some(created_after) = self.created_after {
+            query.push(" AND u.created_at >= ").push_bind(created_after);
+        }
+
+        if let Some(created_before) = self.created_before {
+            query.push(" AND u.created_at < ").push_bind(created_before);
+        }
+
+        if let Some(verified) = self.verified {
+            query.push(if verified {
+                " AND a.email_verified_at IS NOT NULL"
+            } else {
+                " AND a.email_verified_at IS NULL"
+            });
+        }
+
+        if let Some(role) = &self.role {
+            if !matches!(role.as_str(), "admin" | "user") {
+                return Err(Error::invalid_field("role", "invalid"));
+            }
+
+            query.push(" AND a.role = ").push_bind(role.clone());
+        }
+
+        Ok(())
+    }
+
+    pub fn sort(&self) -> Result<Sort, Error> {
+        Sort::parse(self.sort.as_deref())
+    }
+}
+
+/// The columns the users list can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    CreatedAt,
+    UpdatedAt,
+    FirstName,
+    LastName,
+    Email,
+}
+
+impl SortColumn {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "created_at" => Some(Self::CreatedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            "first_name" => Some(Self::FirstName),
+            "last_name" => Some(Self::LastName),
+            "email" => Some(Self::Email),
+            _ => None,
+        }
+    }
+
+    /// The expression to sort by. Nullable columns are coalesced so that they
+    /// can be compared with a cursor key.
+    fn expression(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "u.created_at",
+            Self::UpdatedAt => "COALESCE(u.updated_at, u.created_at)",
+            Self::FirstName => "COALESCE(u.first_name, '')",
+            Self::LastName => "COALESCE(u.last_name, '')",
+            Self::Email => "a.email",
+        }
+    }
+
+    fn key(self, user: &UsersResponse) -> Value {
+        match self {
+            Self::CreatedAt => Value::from(user.created_at.to_rfc3339()),
+            Self::UpdatedAt => Value::from(user.updated_at.unwrap_or(user.created_at).to_rfc3339()),
+            Self::FirstName => Value::from(user.first_name.clone().unwrap_or_default()),
+            Self::LastName => Value::from(user.last_name.clone().unwrap_or_default()),
+            Self::Email => Value::from(user.email.clone()),
+        }
+    }
+
+    fn push_key(self, query: &mut QueryBuilder<'_, Postgres>, key: &Value) -> Result<(), Error> {
+        let key = key.as_str().ok_or_else(invalid_cursor)?;
+
+        match self {
+            Self::CreatedAt | Self::UpdatedAt => {
+                let key = DateTime::parse_from_rfc3339(key)
+                    .map_err(|_| invalid_cursor())?
+                    .with_timezone(&Utc);
+                query.push_bind(key);
+            }
+            Self::FirstName | Self::LastName | Self::Email => {
+                query.push_bind(key.to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An ordering of the users list. The user id is always appended as the last
+/// column so that every row has a unique position.
+#[derive(Debug)]
+pub struct Sort(Vec<(SortColumn, bool)>);
+
+impl Sort {
+    fn parse(sort: Option<&str>) -> Result<Self, Error> {
+        let sort = match sort {
+            Some(sort) if !sort.is_empty() => sort,
+            _ => return Ok(Sort(vec![(SortColumn::CreatedAt, false)])),
+        };
+
+        let mut columns: Vec<(SortColumn, bool)> = Vec::new();
+
+        for name in sort.split(',') {
+            let (name, descending) = match name.strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (name, false),
+            };
+            let column = SortColumn::parse(name.trim())
+                .ok_or_else(|| Error::invalid_field("sort", "invalid"))?;
+
+            if columns.iter().any(|(existing, _)| *existing == column) {
+                return Err(Error::invalid_field("sort", "invalid"));
+            }
+
+            columns.push((column, descending));
+        }
+
+        Ok(Sort(columns))
+    }
+
+    /// The cursor key of a row: the values of each sort column followed by the id.
+    pub fn key(&self, user: &UsersResponse) -> Vec<Value> {
+        self.0
+            .iter()
+            .map(|(column, _)| column.key(user))
+            .chain([Value::from(user.id.to_string())])
+            .collect()
+    }
+
+    /// Append a condition that only matches rows past the cursor. With mixed
+    /// sort orders a row comparison can't be used, so the condition is expanded
+    /// to `(a > $1) OR (a = $1 AND b < $2) OR ...`.
+    pub fn push_keyset(
+        &self,
+        query: &mut QueryBuilder<'_, Postgres>,
+        pagination: &Pagination,
+    ) -> Result<(), Error> {
+        let cursor = match &pagination.cursor {
+            Some(cursor) => cursor,
+            None => return Ok(()),
+        };
+
+        let key: Vec<Value> = cursor.key()?;
+
+        if key.len() != self.0.len() + 1 {
+            return Err(invalid_cursor());
+        }
+
+        let id: Uuid = key[self.0.len()]
+            .as_str()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(invalid_cursor)?;
+
+        query.push(" AND (");
+
+        for i in 0..=self.0.len() {
+            if i > 0 {
+                query.push(" OR ");
+            }
+
+            query.push("(");
+
+            for (j, (column, _)) in self.0[..i].iter().enumerate() {
+                query.push(column.expression()).push(" = ");
+                column.push_key(query, &key[j])?;
+                query.push(" AND ");
+            }
+
+            match self.0.get(i) {
+                Some((column, descending)) => {
+                    query
+                        .push(column.expression())
+                        .push(" ")
+                        .push(pagination.comparator(*descending))
+                        .push(" ");
+                    column.push_key(query, &key[i])?;
+                }
+                None => {
+                    query
+                        .push("u.id ")
+                        .push(pagination.comparator(false))
+                        .push(" ")
+                        .push_bind(id);
+                }
+            }
+
+            query.push(")");
+        }
+
+        query.push(")");
+
+        Ok(())
+    }
+
+    pub fn push_order_by(&self, query: &mut QueryBuilder<'_, Postgres>, pagination: &Pagination) {
+        self.push_order_by_with(query, |descending| pagination.order(descending));
+    }
+
+    /// Append an `ORDER BY` clause for the whole list, for queries that are
+    /// not paginated.
+    pub fn push_unpaginated_order_by(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        self.push_order_by_with(query, |descending| if descending { "DESC" } else { "ASC" });
+    }
+
+    fn push_order_by_with<F>(&self, query: &mut QueryBuilder<'_, Postgres>, order: F)
+    where
+        F: Fn(bool) -> &'static str,
+    {
+        query.push(" ORDER BY ");
+
+        for (column, descending) in &self.0 {
+            query
+                .push(column.expression())
+                .push(" ")
+                .push(order(*descending))
+                .push(", ");
+        }
+
+        query.push("u.id ").push(order(false));
+    }
+}
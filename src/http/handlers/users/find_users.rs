@@ -0,0 +1,114 @@
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, Postgres, QueryBuilder};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::UserFilter;
+use crate::{
+    http::{
+        etag::ETag,
+        extract::{Json, Query},
+        pagination::{Page, PageParams, Pagination},
+        rls::ScopedTx,
+        tenant::TenantScope,
+    },
+    Error,
+};
+
+#[derive(Default, Debug, Clone, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UsersResponse {
+    #[schema(example = "Uuid::default()")]
+    pub id: Uuid,
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1664905980000")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    #[schema(example = "Ziggy")]
+    pub display_name: Option<String>,
+    #[schema(example = "en-GB")]
+    pub locale: Option<String>,
+    #[schema(example = "Europe/London")]
+    pub timezone: Option<String>,
+    /// Free-form details, validated against the configured JSON Schema
+    #[schema(value_type = Object, example = json!({ "band": "The Spiders from Mars" }))]
+    pub metadata: Value,
+    #[schema(example = "/users/a00c9bc7-92ca-413a-97ec-66204314bbca/avatar")]
+    pub avatar_url: Option<String>,
+    #[schema(example = json!(null))]
+    #[serde(with = "ts_milliseconds_option")]
+    pub deactivated_at: Option<DateTime<Utc>>,
+    #[schema(example = json!(null))]
+    #[serde(with = "ts_milliseconds_option")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl UsersResponse {
+    /// The entity tag of this version of the user.
+    pub fn etag(&self) -> ETag {
+        ETag::from_version(self.updated_at.unwrap_or(self.created_at))
+    }
+}
+
+#[utoipa::path(
+  get,
+  path = "/users",
+  responses(
+      (status = 200, description = "List a page of the users in the active organization", body = UsersPage),
+      (status = 400, description = "Invalid token or malformed query", body = Error),
+      (status = 403, description = "Not acting in an organization, or only admins can list inactive users", body = Error),
+      (status = 422, description = "Invalid role, sort, limit or cursor", body = Error),
+      (status = 500, description = "Internal error", body = Error)
+  ),
+  params(PageParams, UserFilter)
+)]
+pub async fn find_users(
+    scope: TenantScope,
+    mut tx: ScopedTx,
+    pagination: Pagination,
+    Query(filter): Query<UserFilter>,
+) -> Result<Json<Page<UsersResponse>>, Error> {
+    if filter.include_inactive == Some(true) && !scope.claims.is_admin() {
+        return Err(Error::Forbidden);
+    }
+
+    let sort = filter.sort()?;
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        // language=PostgreSQL
+        r#"
+          SELECT u.*, a.email
+          FROM app.users AS u
+          LEFT JOIN app_private.accounts AS a
+          ON a.user_id = u.id
+          WHERE TRUE
+      "#,
+    );
+
+    scope.push_conditions(&mut query);
+    filter.push_conditions(&mut query)?;
+    sort.push_keyset(&mut query, &pagination)?;
+    sort.push_order_by(&mut query, &pagination);
+
+    query.push(" LIMIT ").push_bind(pagination.fetch_limit());
+
+    let users = query
+        .build_query_as::<UsersResponse>()
+        .fetch_all(&mut *tx)
+        .await?;
+
+    Ok(Json(Page::new(users, &pagination, |user| sort.key(user))))
+}
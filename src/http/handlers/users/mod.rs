@@ -1,5 +1,37 @@
+use utoipa::{openapi::schema::Schema, ToSchema};
+
+use crate::http::pagination::{page_schema, Page};
+
+mod avatar;
+mod delete_user;
+mod filter;
+mod find_users;
+mod search_users;
+mod update_user;
 mod user_by_id;
-mod users;
 
+pub use avatar::*;
+pub use delete_user::*;
+pub use filter::*;
+pub use find_users::*;
+pub use search_users::*;
+pub use update_user::*;
 pub use user_by_id::*;
-pub use users::*;
+
+/// A page of [`UsersResponse`].
+pub type UsersPage = Page<UsersResponse>;
+
+/// A page of [`UserSearchResult`].
+pub type UserSearchPage = Page<UserSearchResult>;
+
+impl ToSchema for UsersPage {
+    fn schema() -> Schema {
+        page_schema("UsersResponse")
+    }
+}
+
+impl ToSchema for UserSearchPage {
+    fn schema() -> Schema {
+        page_schema("UserSearchResult")
+    }
+}
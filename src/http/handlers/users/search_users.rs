@@ -0,0 +1,155 @@
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    http::{
+        extract::{Json, Query},
+        pagination::{Page, PageParams, Pagination},
+        rls::ScopedTx,
+        tenant::TenantScope,
+    },
+    Error,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    /// The text to look for in names and email addresses
+    #[param(example = "bowie")]
+    pub q: String,
+}
+
+#[derive(Default, Debug, Clone, Serialize, ToSchema, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSearchResult {
+    #[schema(example = "a00c9bc7-92ca-413a-97ec-66204314bbca")]
+    pub id: Uuid,
+    #[schema(example = "David")]
+    pub first_name: Option<String>,
+    #[schema(example = "Bowie")]
+    pub last_name: Option<String>,
+    #[schema(example = "1665856394804")]
+    #[serde(with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "1664905980000")]
+    #[serde(with = "ts_milliseconds_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[schema(example = "major.tom@gmail.com")]
+    pub email: String,
+    /// How well the user matches the search, higher is better
+    #[schema(example = 1.0607927)]
+    pub rank: f32,
+}
+
+#[utoipa::path(
+  get,
+  path = "/users/search",
+  responses(
+      (status = 200, description = "A page of matching users in the active organization, best matches first", body = UserSearchPage),
+      (status = 400, description = "Invalid token or malformed query", body = Error),
+      (status = 403, description = "Not acting in an organization", body = Error),
+      (status = 422, description = "Empty search, invalid limit or cursor", body = Error),
+      (status = 500, description = "Internal error", body = Error)
+  ),
+  params(SearchParams, PageParams)
+)]
+pub async fn search_users(
+    scope: TenantScope,
+    mut tx: ScopedTx,
+    pagination: Pagination,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Page<UserSearchResult>>, Error> {
+    let q = params.q.trim();
+
+    if q.is_empty() {
+        return Err(Error::invalid_field("q", "required"));
+    }
+
+    // Users match on their full text search document, or on trigram word
+    // similarity of their full name or email address to tolerate typos.
+    let mut query = QueryBuilder::<Postgres>::new(
+        // language=PostgreSQL
+        r#"
+          WITH search AS (
+            SELECT input.q, plainto_tsquery('simple', input.q) AS query
+            FROM (SELECT "#,
+    );
+
+    query.push_bind(q.to_string()).push(
+        // language=PostgreSQL
+        r#"::text AS q) AS input
+          )
+          SELECT * FROM (
+            SELECT
+              u.id,
+              u.first_name,
+              u.last_name,
+              u.created_at,
+              u.updated_at,
+              a.email,
+              ts_rank(u.search_document, search.query) + greatest(
+                word_similarity(search.q, coalesce(u.first_name, '') || ' ' || coalesce(u.last_name, '')),
+                word_similarity(search.q, a.email)
+              ) AS rank
+            FROM search, app.users AS u
+            JOIN app_private.accounts AS a
+            ON a.user_id = u.id
+            WHERE u.deactivated_at IS NULL
+            AND u.deleted_at IS NULL
+            AND (
+              u.search_document @@ search.query
+              OR search.q <% (coalesce(u.first_name, '') || ' ' || coalesce(u.last_name, ''))
+              OR search.q <% a.email
+            )"#,
+    );
+
+    scope.push_conditions(&mut query);
+
+    query.push(
+        // language=PostgreSQL
+        r#"
+          ) AS results
+          WHERE TRUE
+      "#,
+    );
+
+    if let Some(cursor) = &pagination.cursor {
+        let (rank, id): (f32, Uuid) = cursor.key()?;
+
+        query
+            .push(" AND (rank ")
+            .push(pagination.comparator(true))
+            .push(" ")
+            .push_bind(rank)
+            .push(" OR (rank = ")
+            .push_bind(rank)
+            .push(" AND id ")
+            .push(pagination.comparator(false))
+            .push(" ")
+            .push_bind(id)
+            .push("))");
+    }
+
+    query
+        .push(" ORDER BY rank ")
+        .push(pagination.order(true))
+        .push(", id ")
+        .push(pagination.order(false))
+        .push(" LIMIT ")
+        .push_bind(pagination.fetch_limit());
+
+    let users = query
+        .build_query_as::<UserSearchResult>()
+        .fetch_all(&mut *tx)
+        .await?;
+
+    Ok(Json(Page::new(users, &pagination, |user| {
+        (user.rank, user.id)
+    })))
+}
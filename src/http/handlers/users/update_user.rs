@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool};
+use unic_langid::LanguageIdentifier;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::UsersResponse;
+use crate::{
+    authz::{AuthzSchema, ObjectRef, SubjectRef},
+    http::{
+        etag::{ETag, IfMatch},
+        extract::{Json, Path, ValidatedJson},
+        jwt::Claims,
+    },
+    metadata::MetadataSchema,
+    Error,
+};
+
+/// A partial update of a user. Omitted fields are left untouched, while
+/// fields set to `null` are cleared.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserBody {
+    #[schema(example = "Ziggy")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub first_name: Option<Option<String>>,
+    #[schema(example = "Stardust")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub last_name: Option<Option<String>>,
+    #[schema(example = "Ziggy")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub display_name: Option<Option<String>>,
+    /// A BCP 47 language tag
+    #[schema(example = "en-GB")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    #[validate(custom = "validate_locale")]
+    pub locale: Option<Option<String>>,
+    /// An IANA timezone name
+    #[schema(example = "Europe/London")]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<Option<String>>,
+    /// Replaces the user's metadata, which must satisfy the configured JSON Schema
+    #[schema(value_type = Object, example = json!({ "band": "The Spiders from Mars" }))]
+    pub metadata: Option<Value>,
+}
+
+fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    locale
+        .parse::<LanguageIdentifier>()
+        .map(drop)
+        .map_err(|_| ValidationError::new("invalid"))
+}
+
+fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    timezone
+        .parse::<chrono_tz::Tz>()
+        .map(drop)
+        .map_err(|_| ValidationError::new("invalid"))
+}
+
+#[utoipa::path(
+  patch,
+  path = "/users/{id}",
+  request_body = UpdateUserBody,
+  responses(
+      (status = 200, description = "User updated, tagged with its new version in the `ETag` header", body = UsersResponse),
+      (status = 400, description = "Invalid JWT", body = Error),
+      (status = 403, description = "Not allowed to update the user", body = Error),
+      (status = 404, description = "User not found", body = Error),
+      (status = 412, description = "The user has changed since the version in `If-Match`", body = Error),
+      (status = 428, description = "The `If-Match` header is missing", body = Error),
+      (status = 422, description = "Invalid display name, locale, timezone or metadata", body = Error),
+      (status = 500, description = "Internal Error", body = Error)
+  ),
+  params(
+      ("id" = Uuid, Path, description = "The user's id"),
+      ("If-Match" = String, Header, description = "The `ETag` of the version being updated")
+  )
+)]
+pub async fn update_user(
+    Extension(pool): Extension<PgPool>,
+    Extension(metadata_schema): Extension<Arc<MetadataSchema>>,
+    Extension(authz_schema): Extension<Arc<AuthzSchema>>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<UpdateUserBody>,
+) -> Result<(ETag, Json<UsersResponse>), Error> {
+    // Besides the user themself and admins, the `editor`s of the user in the
+    // authorization schema may update them.
+    if !claims.can_manage(id)
+        && !authz_schema
+            .check(
+                &pool,
+                &SubjectRef::user(claims.sub),
+                "editor",
+                &ObjectRef::user(id),
+            )
+            .await?
+    {
+        return Err(Error::Forbidden);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if_match.check(&lock_user(&mut tx, id).await?)?;
+
+    let user = apply_update(&mut tx, id, payload, &metadata_schema)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    tx.commit().await?;
+
+    Ok((user.etag(), Json(user)))
+}
+
+/// Lock a user until the end of the transaction and return the entity tag of
+/// its current version, so it can be compared with `If-Match` before a change.
+pub(crate) async fn lock_user<'e, E>(executor: E, id: Uuid) -> Result<ETag, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let version = sqlx::query_scalar::<_, DateTime<Utc>>(
+        // language=PostgreSQL
+        r#"
+          SELECT coalesce(updated_at, created_at)
+          FROM app.users
+          WHERE id = $1 AND deleted_at IS NULL
+          FOR UPDATE
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(ETag::from_version(version))
+}
+
+/// Apply a partial update to a user, returning `None` if the user does not exist.
+/// The `_100_user_updated_at` trigger takes care of bumping `updated_at`, and
+/// the `users_*` check constraints reject malformed display names and metadata.
+pub(crate) async fn apply_update<'e, E>(
+    executor: E,
+    id: Uuid,
+    payload: UpdateUserBody,
+    metadata_schema: &MetadataSchema,
+) -> Result<Option<UsersResponse>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    if let Some(metadata) = &payload.metadata {
+        metadata_schema.validate(metadata)?;
+    }
+
+    let user = sqlx::query_as::<_, UsersResponse>(
+        // language=PostgreSQL
+        r#"
+          WITH u AS (
+            UPDATE app.users
+            SET
+              first_name = CASE WHEN $2 THEN $3 ELSE first_name END,
+              last_name = CASE WHEN $4 THEN $5 ELSE last_name END,
+              display_name = CASE WHEN $6 THEN $7 ELSE display_name END,
+              locale = CASE WHEN $8 THEN $9 ELSE locale END,
+              timezone = CASE WHEN $10 THEN $11 ELSE timezone END,
+              metadata = coalesce($12, metadata)
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+          )
+          SELECT u.*, a.email
+          FROM u
+          LEFT JOIN app_private.accounts AS a
+          ON a.user_id = u.id
+      "#,
+    )
+    .bind(id)
+    .bind(payload.first_name.is_some())
+    .bind(payload.first_name.flatten())
+    .bind(payload.last_name.is_some())
+    .bind(payload.last_name.flatten())
+    .bind(payload.display_name.is_some())
+    .bind(payload.display_name.flatten())
+    .bind(payload.locale.is_some())
+    .bind(payload.locale.flatten())
+    .bind(payload.timezone.is_some())
+    .bind(payload.timezone.flatten())
+    .bind(payload.metadata)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(user)
+}
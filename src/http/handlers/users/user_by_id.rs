@@ -1,10 +1,21 @@
-use axum::{extract::Path, Extension, Json};
+use axum::Extension;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use serde_json::Value;
 use sqlx::{FromRow, PgPool};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::Error;
+use crate::{
+    http::{
+        etag::ETag,
+        extract::{Json, Path},
+        jwt::Claims,
+        rls::ScopedTx,
+        tenant::can_see_user,
+    },
+    Error,
+};
 
 #[derive(Default, Debug, Clone, Serialize, ToSchema, FromRow)]
 #[serde(rename_all = "camelCase")]
@@ -17,14 +28,30 @@ pub struct UserResponse {
     pub last_name: Option<String>,
     #[schema(example = "major.tom@gmail.com")]
     pub email: String,
+    #[schema(example = "Ziggy")]
+    pub display_name: Option<String>,
+    #[schema(example = "en-GB")]
+    pub locale: Option<String>,
+    #[schema(example = "Europe/London")]
+    pub timezone: Option<String>,
+    /// Free-form details, validated against the configured JSON Schema
+    #[schema(value_type = Object, example = json!({ "band": "The Spiders from Mars" }))]
+    pub metadata: Value,
+    #[schema(example = "/users/a00c9bc7-92ca-413a-97ec-66204314bbca/avatar")]
+    pub avatar_url: Option<String>,
+    /// The last time the user changed, used as the version in the `ETag`
+    #[serde(skip)]
+    pub version: DateTime<Utc>,
 }
 
 #[utoipa::path(
   get,
   path = "/users/{id}",
   responses(
-      (status = 200, description = "Get a user", body = UserResponse),
-      (status = 404, description = "User not found", body = Error),
+      (status = 200, description = "Get a user, tagged with its version in the `ETag` header", body = UserResponse),
+      (status = 304, description = "The user has not changed since the version in `If-None-Match`"),
+      (status = 400, description = "Invalid token", body = Error),
+      (status = 404, description = "User not found, or not in the active organization", body = Error),
       (status = 500, description = "Internal Error", body = Error)
   ),
   params(
@@ -33,23 +60,41 @@ pub struct UserResponse {
 )]
 pub async fn find_user_by_id(
     Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    mut tx: ScopedTx,
     Path(id): Path<Uuid>,
-) -> Result<Json<UserResponse>, Error> {
+) -> Result<(ETag, Json<UserResponse>), Error> {
+    // Users outside the tenant are reported missing, like in the listings.
+    if !can_see_user(&pool, &claims, id).await? {
+        return Err(Error::NotFound);
+    }
+
     let user = sqlx::query_as::<_, UserResponse>(
         // language=PostgreSQL
         r#"
-          SELECT u.id, u.first_name, u.last_name, a.email
+          SELECT
+            u.id,
+            u.first_name,
+            u.last_name,
+            a.email,
+            u.display_name,
+            u.locale,
+            u.timezone,
+            u.metadata,
+            u.avatar_url,
+            coalesce(u.updated_at, u.created_at) AS version
           FROM app.users AS u
           LEFT JOIN app_private.accounts AS a
           ON a.user_id = u.id
           WHERE id = $1
+          AND u.deactivated_at IS NULL
+          AND u.deleted_at IS NULL
       "#,
     )
-    .bind(&id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| Error::InvalidToken)?
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
     .ok_or(Error::NotFound)?;
 
-    Ok(Json(user))
+    Ok((ETag::from_version(user.version), Json(user)))
 }
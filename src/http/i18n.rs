@@ -0,0 +1,158 @@
+//! Translations of the messages shown to clients, from the Fluent catalogues
+//! in `locales/`, and the negotiation of the language to answer in.
+
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    http::{header::ACCEPT_LANGUAGE, Request},
+    middleware::Next,
+    response::Response,
+};
+use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use fluent_syntax::ast::Entry;
+use once_cell::sync::Lazy;
+use unic_langid::{langid, LanguageIdentifier};
+
+/// The language messages fall back to, in which every message is written.
+pub const DEFAULT_LOCALE: LanguageIdentifier = langid!("en");
+
+/// The catalogues, embedded at compile time.
+const CATALOGUES: &[(LanguageIdentifier, &str)] = &[
+    (langid!("en"), include_str!("../../locales/en/errors.ftl")),
+    (langid!("fr"), include_str!("../../locales/fr/errors.ftl")),
+    (langid!("es"), include_str!("../../locales/es/errors.ftl")),
+];
+
+static BUNDLES: Lazy<HashMap<LanguageIdentifier, FluentBundle<FluentResource>>> = Lazy::new(|| {
+    CATALOGUES
+        .iter()
+        .map(|(locale, source)| {
+            let resource = FluentResource::try_new(source.to_string())
+                .unwrap_or_else(|(_, errors)| panic!("Invalid {locale} catalogue: {errors:?}"));
+
+            let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+            // Isolation marks around arguments have no use in JSON.
+            bundle.set_use_isolating(false);
+            bundle
+                .add_resource(resource)
+                .unwrap_or_else(|errors| panic!("Invalid {locale} catalogue: {errors:?}"));
+
+            (locale.clone(), bundle)
+        })
+        .collect()
+});
+
+tokio::task_local! {
+    static LOCALE: LanguageIdentifier;
+}
+
+/// The languages there is a catalogue for.
+pub fn locales() -> impl Iterator<Item = &'static LanguageIdentifier> {
+    CATALOGUES.iter().map(|(locale, _)| locale)
+}
+
+/// The language of the request being handled, or the default outside of a
+/// request.
+pub fn locale() -> LanguageIdentifier {
+    LOCALE.try_with(Clone::clone).unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Translate a message, or one of its attributes, if the catalogue of the
+/// language has it.
+pub fn translate(locale: &LanguageIdentifier, id: &str, attribute: Option<&str>) -> Option<String> {
+    let bundle = BUNDLES.get(locale)?;
+    let message = bundle.get_message(id)?;
+    let pattern = match attribute {
+        Some(attribute) => message.get_attribute(attribute)?.value(),
+        None => message.value()?,
+    };
+
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, None, &mut errors);
+
+    errors.is_empty().then(|| text.into_owned())
+}
+
+/// Translate a message into the language of the request, falling back to
+/// the default language.
+pub fn message(id: &str, attribute: Option<&str>) -> Option<String> {
+    translate(&locale(), id, attribute).or_else(|| translate(&DEFAULT_LOCALE, id, attribute))
+}
+
+/// The ids of the messages in the catalogue of a language, and of their
+/// attributes as `id.attribute`.
+pub fn message_ids(locale: &LanguageIdentifier) -> BTreeSet<String> {
+    let source = CATALOGUES
+        .iter()
+        .find_map(|(candidate, source)| (candidate == locale).then_some(*source))
+        .unwrap_or_default();
+    let resource = fluent_syntax::parser::parse(source).unwrap_or_else(|(resource, _)| resource);
+
+    resource
+        .body
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Message(message) => Some(message),
+            _ => None,
+        })
+        .flat_map(|message| {
+            let id = message.id.name;
+
+            std::iter::once(id.to_string()).chain(
+                message
+                    .attributes
+                    .iter()
+                    .map(move |attribute| format!("{id}.{}", attribute.id.name)),
+            )
+        })
+        .collect()
+}
+
+/// Pick the language to answer in from an `Accept-Language` header, trying
+/// the languages in the order of their weights.
+pub fn negotiate(accept_language: &str) -> LanguageIdentifier {
+    let mut requested = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let locale = parts.next()?.trim().parse::<LanguageIdentifier>().ok()?;
+            let quality = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                Some(quality) => quality.trim().parse::<f32>().ok()?,
+                None => 1.0,
+            };
+
+            (quality > 0.0).then_some((locale, quality))
+        })
+        .collect::<Vec<_>>();
+
+    // The sort is stable, keeping the order of equally weighted languages.
+    requested.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let requested = requested
+        .into_iter()
+        .map(|(locale, _)| locale)
+        .collect::<Vec<_>>();
+    let available = locales().cloned().collect::<Vec<_>>();
+
+    negotiate_languages(
+        &requested,
+        &available,
+        Some(&DEFAULT_LOCALE),
+        NegotiationStrategy::Lookup,
+    )
+    .first()
+    .map_or(DEFAULT_LOCALE, |locale| (*locale).clone())
+}
+
+/// Render the messages of a request in the language negotiated from its
+/// `Accept-Language` header.
+pub async fn localize<B>(req: Request<B>, next: Next<B>) -> Response {
+    let locale = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(DEFAULT_LOCALE, negotiate);
+
+    LOCALE.scope(locale, next.run(req)).await
+}
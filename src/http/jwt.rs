@@ -1,4 +1,4 @@
-use std::{fmt, ops::Add};
+use std::{fmt, ops::Add, sync::Arc};
 
 use axum::{
     async_trait,
@@ -7,11 +7,18 @@ use axum::{
     TypedHeader,
 };
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, EncodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{http::error::Error, KEYS};
+use crate::{
+    audit,
+    http::error::Error,
+    permissions::{PermissionCache, PermissionSet},
+    KEYS,
+};
 
 /// Keys used for encoding and decoding tokens
 pub struct Keys {
@@ -28,9 +35,10 @@ impl Keys {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Role {
     Admin,
+    User,
     Anonymous,
 }
 
@@ -38,6 +46,7 @@ impl fmt::Display for Role {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let role = match self {
             Self::Admin => "(admin)",
+            Self::User => "(user)",
             Self::Anonymous => "(anonymous)",
         };
 
@@ -49,6 +58,7 @@ impl From<String> for Role {
     fn from(role: String) -> Self {
         match role.as_str() {
             "admin" => Self::Admin,
+            "user" => Self::User,
             "anonymous" => Self::Anonymous,
             _ => {
                 tracing::error!("Invalid role {role:?}");
@@ -61,23 +71,84 @@ impl From<String> for Role {
 /// The claims object declares the parameters of the users session.
 /// Sub: The subscribers id
 /// Role: Their priviliges
-/// Exp: The expiration date of the session token
-#[derive(Debug, Serialize, Deserialize)]
+/// Exp: The expiration date of the session token, in seconds
+/// Act: The admin acting on behalf of the subscriber, if the token was issued
+/// through impersonation
+/// Org id: The organization the subscriber is acting in, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,
     pub role: Role,
     pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+/// The actor claim (RFC 8693) naming the admin behind an impersonated session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: Uuid,
 }
 
 impl Claims {
     pub fn new(sub: Uuid, role: Role) -> Self {
-        let exp = Utc::now().add(Duration::minutes(15)).timestamp_millis();
+        let exp = Utc::now().add(Duration::minutes(15)).timestamp();
+
+        Claims {
+            sub,
+            role,
+            exp,
+            org_id: None,
+            act: None,
+        }
+    }
+
+    /// The same claims, acting in the given organization.
+    pub fn with_org(self, org_id: Option<Uuid>) -> Self {
+        Claims { org_id, ..self }
+    }
+
+    /// Claims for an admin acting as another user. These are only valid for
+    /// five minutes and are never paired with a refresh token.
+    pub fn impersonate(sub: Uuid, role: Role, admin: Uuid) -> Self {
+        let exp = Utc::now().add(Duration::minutes(5)).timestamp();
+
+        Claims {
+            sub,
+            role,
+            exp,
+            org_id: None,
+            act: Some(Actor { sub: admin }),
+        }
+    }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.act.is_some()
+    }
+
+    /// Whether the session belongs to an admin acting as themself.
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin && !self.is_impersonated()
+    }
+
+    /// Whether the session may modify the given user, i.e. it belongs to that
+    /// user or to an admin acting as themself.
+    pub fn can_manage(&self, user_id: Uuid) -> bool {
+        self.sub == user_id || self.is_admin()
+    }
 
-        Claims { sub, role, exp }
+    /// The expiration date in milliseconds, as reported to clients.
+    pub fn expires_in(&self) -> i64 {
+        self.exp * 1000
     }
 }
 
-/// Middleware to extract the claims object into a handler
+/// Middleware to extract the claims object into a handler.
+/// Every request made with an impersonated token is recorded in the audit log.
+/// The claims are kept in the request extensions, so extracting them again
+/// doesn't decode the token or record the request twice.
 #[async_trait]
 impl<B> FromRequest<B> for Claims
 where
@@ -89,14 +160,139 @@ where
     where
         Self: Send + Sync,
     {
+        if let Some(claims) = req.extensions().get::<Claims>() {
+            return Ok(claims.clone());
+        }
+
         let TypedHeader(Authorization(bearer)) =
             TypedHeader::<Authorization<Bearer>>::from_request(req)
                 .await
                 .map_err(|_| Error::InvalidToken)?;
 
-        let token_data = decode(bearer.token(), &KEYS.decoding, &Validation::default())
-            .map_err(|_| Error::InvalidToken)?;
+        let token_data = decode::<Claims>(
+            bearer.token(),
+            &KEYS.decoding,
+            &Validation::new(Algorithm::HS512),
+        )
+        .map_err(|_| Error::InvalidToken)?;
+
+        let claims = token_data.claims;
+
+        if let Some(actor) = &claims.act {
+            let pool = req
+                .extensions()
+                .get::<PgPool>()
+                .ok_or(Error::InternalError)?;
+
+            audit::record(
+                pool,
+                Some(actor.sub),
+                Some(claims.sub),
+                "impersonation.request",
+                json!({ "method": req.method().as_str(), "path": req.uri().path() }),
+            )
+            .await?;
+        }
+
+        req.extensions_mut().insert(claims.clone());
+
+        Ok(claims)
+    }
+}
+
+/// Extracts the claims of an admin acting as themself.
+pub struct Admin(pub Claims);
+
+#[async_trait]
+impl<B> FromRequest<B> for Admin
+where
+    B: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request(req).await?;
+
+        if !claims.is_admin() {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(Admin(claims))
+    }
+}
+
+/// Extracts the claims of a user acting as themself, guarding sensitive
+/// endpoints such as a password change from impersonated sessions.
+pub struct NotImpersonated(pub Claims);
+
+#[async_trait]
+impl<B> FromRequest<B> for NotImpersonated
+where
+    B: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request(req).await?;
+
+        if claims.is_impersonated() {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(NotImpersonated(claims))
+    }
+}
+
+/// Extracts the claims together with the permissions the user holds through
+/// their groups. Admins acting as themselves hold every permission.
+pub struct Permissions {
+    pub claims: Claims,
+    permissions: PermissionSet,
+}
+
+impl Permissions {
+    pub fn has(&self, permission: &str) -> bool {
+        self.claims.is_admin() || self.permissions.contains(permission)
+    }
+
+    /// The permissions granted through groups, not counting those of admins.
+    pub fn granted(&self) -> &PermissionSet {
+        &self.permissions
+    }
+
+    pub fn require(&self, permission: &str) -> Result<(), Error> {
+        if self.has(permission) {
+            Ok(())
+        } else {
+            Err(Error::Forbidden)
+        }
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for Permissions
+where
+    B: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request(req).await?;
+
+        let pool = req
+            .extensions()
+            .get::<PgPool>()
+            .ok_or(Error::InternalError)?;
+        let cache = req
+            .extensions()
+            .get::<Arc<PermissionCache>>()
+            .ok_or(Error::InternalError)?;
+
+        let permissions = cache.get(pool, claims.sub, claims.org_id).await?;
 
-        Ok(token_data.claims)
+        Ok(Permissions {
+            claims,
+            permissions,
+        })
     }
 }
@@ -1,26 +1,49 @@
-use crate::config::Config;
+use crate::{
+    authz::AuthzSchema,
+    blob::{LocalBlobStore, SharedBlobStore},
+    config::Config,
+    jobs,
+    mail::{LogMailer, SharedMailer},
+    metadata::MetadataSchema,
+    permissions::PermissionCache,
+};
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{any, delete, get, post, put},
     Extension, Router, Server,
 };
 use error::Error;
 use sqlx::PgPool;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 use tower::ServiceBuilder;
-use tower_http::cors::CorsLayer;
+use tower_http::{cors::CorsLayer, limit::RequestBodyLimitLayer};
 
-use self::handlers::{accounts, auth, get_openapi, users};
+use self::handlers::{
+    accounts, admin, auth, authz, get_openapi, groups, me, oauth, orgs, passkeys, users,
+};
 
+pub mod correlation;
 pub mod error;
+pub mod etag;
+pub mod extract;
 pub mod handlers;
+pub mod i18n;
 pub mod jwt;
+pub mod pagination;
+pub mod rls;
+pub mod tenant;
 
 pub async fn serve(pool: PgPool, config: Config) -> Result<(), Error> {
     let addr = SocketAddr::from(([127, 0, 0, 1], config.port));
 
+    let services = Services::from_config(&config)?;
+
+    jobs::spawn(pool.clone(), services.blob_store.clone(), &config);
+
     Server::bind(&addr)
         .serve(
-            routes(pool)
+            routes_with(pool, services)
                 .layer(ServiceBuilder::new().layer(CorsLayer::permissive()))
                 .into_make_service(),
         )
@@ -29,14 +52,170 @@ pub async fn serve(pool: PgPool, config: Config) -> Result<(), Error> {
     Ok(())
 }
 
+/// The external services handlers depend on, swappable for tests.
+#[derive(Clone)]
+pub struct Services {
+    pub mailer: SharedMailer,
+    pub blob_store: SharedBlobStore,
+    pub metadata_schema: Arc<MetadataSchema>,
+    pub permission_cache: Arc<PermissionCache>,
+    pub authz_schema: Arc<AuthzSchema>,
+}
+
+impl Services {
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        let metadata_schema = match &config.metadata_schema {
+            Some(path) => MetadataSchema::from_file(path)?,
+            None => MetadataSchema::permissive(),
+        };
+
+        let authz_schema = match &config.authz_schema {
+            Some(path) => AuthzSchema::from_file(path)?,
+            None => AuthzSchema::default(),
+        };
+
+        Ok(Services {
+            blob_store: Arc::new(LocalBlobStore::new(&config.upload_dir)),
+            metadata_schema: Arc::new(metadata_schema),
+            authz_schema: Arc::new(authz_schema),
+            ..Services::default()
+        })
+    }
+}
+
+impl Default for Services {
+    fn default() -> Self {
+        Services {
+            mailer: Arc::new(LogMailer),
+            blob_store: Arc::new(LocalBlobStore::new("uploads")),
+            metadata_schema: Arc::new(MetadataSchema::permissive()),
+            permission_cache: Arc::new(PermissionCache::default()),
+            authz_schema: Arc::new(AuthzSchema::default()),
+        }
+    }
+}
+
 pub fn routes(pool: PgPool) -> Router {
+    routes_with(pool, Services::default())
+}
+
+pub fn routes_with(pool: PgPool, services: Services) -> Router {
+    // Layering the routes directly stops axum from setting `Allow` on its
+    // `405` responses, so the layers wrap them as a fallback instead.
+    Router::new()
+        .fallback(api())
+        .layer(middleware::from_fn(etag::conditional_get))
+        .layer(Extension(pool))
+        .layer(Extension(services.mailer))
+        .layer(Extension(services.blob_store))
+        .layer(Extension(services.metadata_schema))
+        .layer(Extension(services.permission_cache))
+        .layer(Extension(services.authz_schema))
+        .layer(middleware::from_fn(error::method_not_allowed))
+        .layer(middleware::from_fn(i18n::localize))
+        .layer(middleware::from_fn(correlation::correlate))
+}
+
+/// The routes of the API, before any layers are applied.
+fn api() -> Router {
     Router::new()
         .route("/", get(get_openapi))
         .route("/users", get(users::find_users))
-        .route("/users/:id", get(users::find_user_by_id))
+        .route("/users/search", get(users::search_users))
+        .route(
+            "/users/:id",
+            get(users::find_user_by_id)
+                .patch(users::update_user)
+                .delete(users::delete_user),
+        )
+        .route("/users/:id/avatar", get(users::find_avatar))
+        .route("/me", get(me::find_me).patch(me::update_me))
+        .route("/me/email", post(me::request_email_change))
+        .route("/me/avatar", put(me::upload_avatar))
+        .route("/me/permissions", get(me::find_my_permissions))
+        .route("/me/export", post(me::request_export))
+        .route("/me/exports/:id", get(me::find_export))
+        .route("/me/exports/:id/download", get(me::download_export))
+        .route(
+            "/me/erase",
+            post(me::request_erasure).delete(me::cancel_erasure),
+        )
+        .route("/orgs", get(orgs::find_my_orgs).post(orgs::create_org))
+        .route(
+            "/orgs/:id/members",
+            get(orgs::find_members).post(orgs::add_member),
+        )
+        .route("/orgs/:id/members/:user_id", delete(orgs::remove_member))
+        .route(
+            "/orgs/:id/invitations",
+            get(orgs::find_invitations).post(orgs::create_invitation),
+        )
+        .route(
+            "/orgs/:id/invitations/:invitation_id",
+            delete(orgs::revoke_invitation),
+        )
+        .route("/invitations/accept", post(orgs::accept_invitation))
+        .route(
+            "/groups",
+            get(groups::find_groups).post(groups::create_group),
+        )
+        .route(
+            "/groups/:id",
+            get(groups::find_group_by_id)
+                .patch(groups::update_group)
+                .delete(groups::delete_group),
+        )
+        .route("/groups/:id/members", get(groups::find_group_members))
+        .route(
+            "/groups/:id/members/:user_id",
+            put(groups::add_group_member).delete(groups::remove_group_member),
+        )
+        .route("/authz/check", post(authz::check))
+        .route(
+            "/authz/tuples",
+            get(authz::find_tuples).post(authz::write_tuples),
+        )
         .route("/accounts/register", post(accounts::register))
+        .route("/accounts/password", post(accounts::change_password))
+        .route(
+            "/accounts/email/confirm",
+            post(accounts::confirm_email_change),
+        )
         .route("/auth/authorize", post(auth::authorize))
         .route("/auth/revalidate", post(auth::revalidate))
-        .fallback(get(handlers::not_found))
-        .layer(Extension(pool))
+        .route("/auth/switch-org", post(auth::switch_org))
+        .route(
+            "/auth/passkeys/register/start",
+            post(passkeys::start_registration),
+        )
+        .route(
+            "/auth/passkeys/register/finish",
+            post(passkeys::finish_registration),
+        )
+        .route(
+            "/auth/passkeys/login/start",
+            post(passkeys::start_authentication),
+        )
+        .route(
+            "/auth/passkeys/login/finish",
+            post(passkeys::finish_authentication),
+        )
+        .route("/oauth/device/code", post(oauth::device_code))
+        .route("/oauth/token", post(oauth::token))
+        .route(
+            "/device",
+            get(oauth::find_device).post(oauth::approve_device),
+        )
+        .route("/admin/users/export", get(admin::export_users))
+        .route(
+            "/admin/users/import",
+            post(admin::import_users)
+                .layer(DefaultBodyLimit::disable())
+                .layer(RequestBodyLimitLayer::new(admin::MAX_IMPORT_SIZE))
+                .layer(middleware::from_fn(error::payload_too_large)),
+        )
+        .route("/admin/users/:id/impersonate", post(admin::impersonate))
+        .route("/admin/users/:id/deactivate", post(admin::deactivate_user))
+        .route("/admin/users/:id/restore", post(admin::restore_user))
+        .fallback(any(handlers::not_found))
 }
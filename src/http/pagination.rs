@@ -0,0 +1,214 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, Query, RequestParts},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{
+    openapi::{
+        schema::{ArrayBuilder, ObjectBuilder, Schema, SchemaType},
+        Ref,
+    },
+    IntoParams,
+};
+
+use crate::Error;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// The query parameters accepted by paginated list endpoints.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// The maximum number of items to return, between 1 and 100
+    #[param(example = 20)]
+    pub limit: Option<i64>,
+    /// An opaque cursor taken from `nextCursor` or `prevCursor` of a previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    #[serde(rename = "n")]
+    Next,
+    #[serde(rename = "p")]
+    Prev,
+}
+
+/// A position in a keyset ordered list. The key holds the values of the
+/// ordering columns of the row the cursor points past.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "d")]
+    pub direction: Direction,
+    #[serde(rename = "k")]
+    pub key: Value,
+}
+
+/// The error for a cursor that was not taken from a page of the same list.
+pub fn invalid_cursor() -> Error {
+    Error::invalid_field("cursor", "invalid")
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let json =
+            base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).map_err(|_| invalid_cursor())?;
+
+        serde_json::from_slice(&json).map_err(|_| invalid_cursor())
+    }
+
+    /// Deserialize the key into the type of the ordering columns.
+    pub fn key<K: DeserializeOwned>(&self) -> Result<K, Error> {
+        serde_json::from_value(self.key.clone()).map_err(|_| invalid_cursor())
+    }
+}
+
+/// Middleware to extract the page a client asked for into a handler.
+#[derive(Debug)]
+pub struct Pagination {
+    pub limit: i64,
+    pub cursor: Option<Cursor>,
+}
+
+impl Pagination {
+    pub fn direction(&self) -> Direction {
+        self.cursor
+            .as_ref()
+            .map_or(Direction::Next, |cursor| cursor.direction)
+    }
+
+    /// The number of rows to fetch. One more row than the limit is fetched to
+    /// find out whether there is another page.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// The SQL sort order to fetch a column sorted in the given order in.
+    /// Previous pages are fetched in reverse and flipped back by [`Page::new`].
+    pub fn order(&self, descending: bool) -> &'static str {
+        match (self.direction(), descending) {
+            (Direction::Next, false) | (Direction::Prev, true) => "ASC",
+            (Direction::Next, true) | (Direction::Prev, false) => "DESC",
+        }
+    }
+
+    /// The operator to compare a column sorted in the given order with the
+    /// cursor key.
+    pub fn comparator(&self, descending: bool) -> &'static str {
+        match (self.direction(), descending) {
+            (Direction::Next, false) | (Direction::Prev, true) => ">",
+            (Direction::Next, true) | (Direction::Prev, false) => "<",
+        }
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for Pagination
+where
+    B: Send,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<PageParams>::from_request(req)
+            .await
+            .map_err(Error::from_rejection)?;
+
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(Error::invalid_field("limit", "range"));
+        }
+
+        let cursor = params.cursor.as_deref().map(Cursor::decode).transpose()?;
+
+        Ok(Pagination { limit, cursor })
+    }
+}
+
+/// A page of a keyset paginated list. The modules listing items document
+/// their pages with a type alias whose schema is [`page_schema`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The cursor of the following page, if there is one
+    pub next_cursor: Option<String>,
+    /// The cursor of the preceding page, if there is one
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows fetched with [`Pagination::fetch_limit`] and
+    /// [`Pagination::order`]. `key` returns the values of the ordering columns
+    /// of a row, in the same order the query sorts by them.
+    pub fn new<K, F>(mut rows: Vec<T>, pagination: &Pagination, key: F) -> Self
+    where
+        K: Serialize,
+        F: Fn(&T) -> K,
+    {
+        let has_more = rows.len() as i64 > pagination.limit;
+        rows.truncate(pagination.limit as usize);
+
+        let cursor = |direction, row: Option<&T>| {
+            row.map(|row| {
+                Cursor {
+                    direction,
+                    key: serde_json::to_value(key(row)).unwrap_or_default(),
+                }
+                .encode()
+            })
+        };
+
+        let (has_next, has_prev) = match pagination.direction() {
+            Direction::Next => (has_more, pagination.cursor.is_some()),
+            Direction::Prev => {
+                rows.reverse();
+                (true, has_more)
+            }
+        };
+
+        Page {
+            next_cursor: has_next
+                .then(|| cursor(Direction::Next, rows.last()))
+                .flatten(),
+            prev_cursor: has_prev
+                .then(|| cursor(Direction::Prev, rows.first()))
+                .flatten(),
+            items: rows,
+        }
+    }
+}
+
+/// The schema of a [`Page`] of the items of the schema named `item`.
+pub fn page_schema(item: &str) -> Schema {
+    let cursor = |description| {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .description(Some(description))
+    };
+
+    ObjectBuilder::new()
+        .property(
+            "items",
+            ArrayBuilder::new().items(Ref::from_schema_name(item)),
+        )
+        .required("items")
+        .property(
+            "nextCursor",
+            cursor("The cursor of the following page, if there is one"),
+        )
+        .property(
+            "prevCursor",
+            cursor("The cursor of the preceding page, if there is one"),
+        )
+        .into()
+}
@@ -0,0 +1,120 @@
+use std::ops::{Deref, DerefMut};
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, RequestParts},
+    http::header::AUTHORIZATION,
+};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{http::jwt::Claims, Error};
+
+/// A transaction that runs as `app_user`, or as `app_anonymous` without
+/// claims, with the claims available to the row-level security policies
+/// through `current_setting('jwt.claims.*')`. Queries on it only see the rows
+/// the session may see, even without a `WHERE` clause restricting them.
+///
+/// Handlers authorised by who the user is go through it. Those authorised by
+/// a permission, the authorization schema or a token sent by email still use
+/// the pool, as the policies can't tell what these grant, and restrict their
+/// queries themselves.
+pub struct ScopedTx {
+    pub claims: Option<Claims>,
+    tx: Transaction<'static, Postgres>,
+}
+
+impl ScopedTx {
+    /// Begin a transaction scoped to the given claims. The role and settings
+    /// are local to the transaction, so they never leak to other requests
+    /// sharing the connection.
+    pub async fn begin(pool: &PgPool, claims: Option<Claims>) -> Result<Self, Error> {
+        let mut tx = pool.begin().await?;
+
+        let role = match &claims {
+            Some(_) => "app_user",
+            None => "app_anonymous",
+        };
+
+        // Setting `role` locally is `SET LOCAL ROLE`, but takes a bound parameter.
+        sqlx::query(
+            // language=PostgreSQL
+            r#"
+              SELECT
+                set_config('role', $1, true),
+                set_config('jwt.claims.sub', $2, true),
+                set_config('jwt.claims.role', $3, true),
+                set_config('jwt.claims.org_id', $4, true)
+          "#,
+        )
+        .bind(role)
+        .bind(
+            claims
+                .as_ref()
+                .map(|claims| claims.sub.to_string())
+                .unwrap_or_default(),
+        )
+        .bind(match &claims {
+            Some(claims) if claims.is_admin() => "admin",
+            Some(_) => "user",
+            None => "anonymous",
+        })
+        .bind(
+            claims
+                .as_ref()
+                .and_then(|claims| claims.org_id)
+                .map(|org_id| org_id.to_string())
+                .unwrap_or_default(),
+        )
+        .execute(&mut tx)
+        .await?;
+
+        Ok(ScopedTx { claims, tx })
+    }
+
+    pub async fn commit(self) -> Result<(), Error> {
+        self.tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl Deref for ScopedTx {
+    type Target = Transaction<'static, Postgres>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl DerefMut for ScopedTx {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tx
+    }
+}
+
+/// Extracts a transaction scoped to the claims of the request. Requests
+/// without an `Authorization` header get an anonymous transaction, while an
+/// invalid token is rejected rather than silently treated as anonymous.
+#[async_trait]
+impl<B> FromRequest<B> for ScopedTx
+where
+    B: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let claims = if req.headers().contains_key(AUTHORIZATION) {
+            Some(Claims::from_request(req).await?)
+        } else {
+            None
+        };
+
+        let pool = req
+            .extensions()
+            .get::<PgPool>()
+            .ok_or(Error::InternalError)?
+            .clone();
+
+        ScopedTx::begin(&pool, claims).await
+    }
+}
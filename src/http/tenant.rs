@@ -0,0 +1,133 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, RequestParts},
+};
+use sqlx::{PgExecutor, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{http::jwt::Claims, Error};
+
+/// The organization new access tokens for a user act in: the one they last
+/// switched to if they are still a member, otherwise the one they joined first.
+pub async fn active_org<'e, E>(executor: E, user_id: Uuid) -> Result<Option<Uuid>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let org_id = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"
+          SELECT m.organization_id
+          FROM app.memberships AS m
+          JOIN app_private.accounts AS a
+          ON a.user_id = m.user_id
+          WHERE m.user_id = $1
+          ORDER BY (m.organization_id = a.active_org_id) IS TRUE DESC, m.created_at, m.organization_id
+          LIMIT 1
+      "#,
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(org_id)
+}
+
+/// The role of a user in an organization, if they are a member.
+pub async fn org_role<'e, E>(
+    executor: E,
+    org_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<String>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let role = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT role FROM app.memberships WHERE organization_id = $1 AND user_id = $2"#,
+    )
+    .bind(org_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(role)
+}
+
+/// Whether a session may look up a user: themself, anyone for admins acting
+/// as themselves, and otherwise the members of the organization it acts in,
+/// as in a [`TenantScope`].
+pub async fn can_see_user(pool: &PgPool, claims: &Claims, user_id: Uuid) -> Result<bool, Error> {
+    if user_id == claims.sub {
+        return Ok(true);
+    }
+
+    let org_id = match claims.org_id {
+        Some(org_id) => org_id,
+        None => return Ok(claims.is_admin()),
+    };
+
+    if org_role(pool, org_id, claims.sub).await?.is_none() && !claims.is_admin() {
+        return Ok(false);
+    }
+
+    Ok(org_role(pool, org_id, user_id).await?.is_some())
+}
+
+/// Extracts the tenant a list request is scoped to. Sessions acting in an
+/// organization only see its members, admins acting as themselves without an
+/// organization see every user, and everyone else is turned away.
+pub struct TenantScope {
+    pub claims: Claims,
+    pub org_id: Option<Uuid>,
+}
+
+impl TenantScope {
+    /// Append a condition restricting `app.users AS u` to the tenant.
+    pub fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(org_id) = self.org_id {
+            query
+                .push(" AND EXISTS (SELECT 1 FROM app.memberships AS m WHERE m.user_id = u.id AND m.organization_id = ")
+                .push_bind(org_id)
+                .push(")");
+        }
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for TenantScope
+where
+    B: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: &'_ mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request(req).await?;
+
+        let org_id = match claims.org_id {
+            Some(org_id) => org_id,
+            None if claims.is_admin() => {
+                return Ok(TenantScope {
+                    claims,
+                    org_id: None,
+                })
+            }
+            None => return Err(Error::Forbidden),
+        };
+
+        let pool = req
+            .extensions()
+            .get::<PgPool>()
+            .ok_or(Error::InternalError)?;
+
+        // Access tokens outlive memberships, so check the user still belongs
+        // to the organization their token was issued for.
+        if org_role(pool, org_id, claims.sub).await?.is_none() && !claims.is_admin() {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(TenantScope {
+            claims,
+            org_id: Some(org_id),
+        })
+    }
+}
@@ -0,0 +1,266 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Connection, PgExecutor, PgPool, Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{audit, http::handlers::accounts::RegisterBody, Error};
+
+/// The number of rows inserted per transaction unless told otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// The file formats users can be imported from and exported to. Imports use
+/// the field names of `POST /accounts/register`: `firstName`, `lastName`,
+/// `email` and `password`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl FileFormat {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next().map(str::trim) {
+            Some("text/csv") => Some(FileFormat::Csv),
+            Some("application/x-ndjson" | "application/ndjson") => Some(FileFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FileFormat::Csv => "text/csv",
+            FileFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Csv => "csv",
+            FileFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "csv" => Some(FileFormat::Csv),
+            "ndjson" | "jsonl" => Some(FileFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    /// Validate and insert every row in a single transaction, then roll it
+    /// back instead of committing.
+    pub dry_run: bool,
+    pub batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            dry_run: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RowStatus {
+    /// The user was registered
+    Created,
+    /// The user would have been registered, but this was a dry run
+    Valid,
+    Failed,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RowReport {
+    /// The 1-based position of the row in the file, not counting the CSV header
+    #[schema(example = 1)]
+    pub row: usize,
+    #[schema(example = "bark.ruffalo@gmail.com")]
+    pub email: Option<String>,
+    pub status: RowStatus,
+    /// The id of the registered user. Dry runs report the id that would have been used
+    pub user_id: Option<Uuid>,
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    #[schema(example = false)]
+    pub dry_run: bool,
+    #[schema(example = 2)]
+    pub total: usize,
+    #[schema(example = 1)]
+    pub succeeded: usize,
+    #[schema(example = 1)]
+    pub failed: usize,
+    pub rows: Vec<RowReport>,
+}
+
+/// Parse every row of a file, keeping rows that cannot be read as errors so
+/// that they show up in the report.
+pub fn parse(format: FileFormat, bytes: &[u8]) -> Vec<Result<RegisterBody, String>> {
+    match format {
+        FileFormat::Csv => csv::Reader::from_reader(bytes)
+            .deserialize::<RegisterBody>()
+            .map(|row| row.map_err(|err| err.to_string()))
+            .collect(),
+        FileFormat::Ndjson => String::from_utf8_lossy(bytes)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str::<RegisterBody>(line).map_err(|err| err.to_string()))
+            .collect(),
+    }
+}
+
+/// Register the users of the parsed rows, `batch_size` rows per transaction.
+/// Each row is inserted under its own savepoint, so a failing row does not
+/// take the rest of its batch down with it. A dry run nests the batches in a
+/// single transaction that is rolled back, so that rows clashing with those
+/// of earlier batches fail like they would for real.
+pub async fn import_users(
+    pool: &PgPool,
+    rows: Vec<Result<RegisterBody, String>>,
+    options: ImportOptions,
+) -> Result<ImportReport, Error> {
+    let total = rows.len();
+    let mut reports = Vec::with_capacity(total);
+    let mut rows = rows.into_iter().enumerate().peekable();
+    let mut dry_run = if options.dry_run {
+        Some(pool.begin().await?)
+    } else {
+        None
+    };
+
+    while rows.peek().is_some() {
+        let mut tx = match &mut dry_run {
+            Some(dry_run) => dry_run.begin().await?,
+            None => pool.begin().await?,
+        };
+
+        for (index, row) in rows.by_ref().take(options.batch_size.max(1)) {
+            let mut report = RowReport {
+                row: index + 1,
+                email: row.as_ref().ok().map(|body| body.email.clone()),
+                status: RowStatus::Failed,
+                user_id: None,
+                error: None,
+            };
+
+            match row {
+                Ok(body) => match register(&mut tx, body).await? {
+                    Ok(user_id) => {
+                        report.status = if options.dry_run {
+                            RowStatus::Valid
+                        } else {
+                            RowStatus::Created
+                        };
+                        report.user_id = Some(user_id);
+                    }
+                    Err(error) => report.error = Some(error),
+                },
+                Err(error) => report.error = Some(error),
+            }
+
+            reports.push(report);
+        }
+
+        tx.commit().await?;
+    }
+
+    if let Some(dry_run) = dry_run {
+        dry_run.rollback().await?;
+    }
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.status == RowStatus::Failed)
+        .count();
+
+    Ok(ImportReport {
+        dry_run: options.dry_run,
+        total,
+        succeeded: total - failed,
+        failed,
+        rows: reports,
+    })
+}
+
+/// Record an import in the audit log, whether it was run through the API or
+/// the command line. Dry runs change nothing, so they are not recorded.
+pub async fn record<'e, E>(
+    executor: E,
+    actor: Option<Uuid>,
+    report: &ImportReport,
+) -> Result<(), Error>
+where
+    E: PgExecutor<'e>,
+{
+    if !report.dry_run {
+        audit::record(
+            executor,
+            actor,
+            None,
+            "user.import",
+            json!({ "total": report.total, "succeeded": report.succeeded }),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Register a single row under a savepoint. Problems with the row itself,
+/// including the constraints it breaks, are returned as the inner error, while
+/// other database failures abort the import.
+async fn register(
+    tx: &mut Transaction<'_, Postgres>,
+    body: RegisterBody,
+) -> Result<Result<Uuid, String>, Error> {
+    if let Err(errors) = body.validate() {
+        return Ok(Err(errors.to_string()));
+    }
+
+    let mut savepoint = tx.begin().await?;
+
+    let result = sqlx::query_scalar::<_, Option<Uuid>>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.register_user($1, $2, $3, $4)"#,
+    )
+    .bind(body.first_name)
+    .bind(body.last_name)
+    .bind(body.email)
+    .bind(body.password)
+    .fetch_one(&mut savepoint)
+    .await;
+
+    match result {
+        Ok(Some(id)) => {
+            savepoint.commit().await?;
+            Ok(Ok(id))
+        }
+        // `app.register_user` is strict, so it registers nobody when a name is missing.
+        Ok(None) => Ok(Err("First and last name are required".into())),
+        Err(err) => match Error::from(err) {
+            err @ (Error::EmailTaken
+            | Error::Conflict
+            | Error::InvalidValue
+            | Error::InvalidReference) => Ok(Err(err.to_string())),
+            err => Err(err),
+        },
+    }
+}
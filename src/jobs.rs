@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use sqlx::{postgres::types::PgInterval, PgPool};
+
+use crate::{blob::SharedBlobStore, config::Config, privacy, Error};
+
+/// How often the scheduled jobs run.
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Spawn the background task that runs scheduled maintenance jobs.
+pub fn spawn(pool: PgPool, blob_store: SharedBlobStore, config: &Config) {
+    let retention_days = config.purge_retention_days;
+
+    tokio::spawn(async move {
+        match privacy::resume_pending_exports(&pool, &blob_store).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Resumed {} pending data exports", count),
+            Err(err) => tracing::error!("Unable to resume pending data exports: {}", err),
+        }
+
+        let mut interval = tokio::time::interval(INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match purge_deleted_users(&pool, &blob_store, retention_days).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Purged {} deleted users", count),
+                Err(err) => tracing::error!("Unable to purge deleted users: {}", err),
+            }
+
+            match privacy::erase_due_users(&pool, &blob_store).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Erased {} users", count),
+                Err(err) => tracing::error!("Unable to erase users: {}", err),
+            }
+
+            match privacy::remove_expired_exports(&pool, &blob_store).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Removed {} expired data exports", count),
+                Err(err) => tracing::error!("Unable to remove expired data exports: {}", err),
+            }
+
+            match remove_expired_device_codes(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Removed {} expired device codes", count),
+                Err(err) => tracing::error!("Unable to remove expired device codes: {}", err),
+            }
+
+            match remove_expired_challenges(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Removed {} expired WebAuthn challenges", count),
+                Err(err) => {
+                    tracing::error!("Unable to remove expired WebAuthn challenges: {}", err)
+                }
+            }
+        }
+    });
+}
+
+/// Permanently remove users that were deleted more than `retention_days` ago,
+/// along with their avatars and data exports, returning how many were removed.
+pub async fn purge_deleted_users(
+    pool: &PgPool,
+    blob_store: &SharedBlobStore,
+    retention_days: u32,
+) -> Result<i64, Error> {
+    let retention = PgInterval {
+        months: 0,
+        days: retention_days as i32,
+        microseconds: 0,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // The users are locked so that none is restored between listing their
+    // blobs and removing them.
+    let keys = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+            WITH purged AS (
+              SELECT id, avatar_key FROM app.users WHERE deleted_at < now() - $1 FOR UPDATE
+            )
+            SELECT avatar_key FROM purged WHERE avatar_key IS NOT NULL
+            UNION ALL
+            SELECT e.blob_key
+            FROM app_private.data_exports AS e
+            JOIN purged AS p
+            ON p.id = e.user_id
+            WHERE e.blob_key IS NOT NULL
+        "#,
+    )
+    .bind(&retention)
+    .fetch_all(&mut tx)
+    .await?;
+
+    let count = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT app_private.purge_deleted_users($1)"#,
+    )
+    .bind(retention)
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    privacy::delete_blobs(blob_store, keys).await;
+
+    Ok(count)
+}
+
+/// Remove the device authorization requests that expired more than a day ago,
+/// returning how many were removed. They are kept for a while so that devices
+/// still polling are told that their code expired rather than that it is unknown.
+pub async fn remove_expired_device_codes(pool: &PgPool) -> Result<u64, Error> {
+    let result = sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app_private.device_codes WHERE expires_at < now() - INTERVAL '1 day'"#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Remove the WebAuthn ceremonies that were started but never finished,
+/// returning how many were removed.
+pub async fn remove_expired_challenges(pool: &PgPool) -> Result<u64, Error> {
+    let result = sqlx::query(
+        // language=PostgreSQL
+        r#"DELETE FROM app_private.webauthn_challenges WHERE expires_at <= now()"#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -2,9 +2,19 @@ use std::env;
 
 use http::jwt::Keys;
 use once_cell::sync::Lazy;
+use webauthn_rs::prelude::{Url, Webauthn, WebauthnBuilder};
 
+pub mod audit;
+pub mod authz;
+pub mod blob;
 pub mod config;
 pub mod http;
+pub mod import;
+pub mod jobs;
+pub mod mail;
+pub mod metadata;
+pub mod permissions;
+pub mod privacy;
 pub mod test_utils;
 
 pub use http::error::Error;
@@ -13,3 +23,16 @@ static KEYS: Lazy<Keys> = Lazy::new(|| {
     let secret = env::var("JWT_SECRET").unwrap_or_else(|_| panic!("JWT_SECRET not set"));
     Keys::new(secret.as_bytes())
 });
+
+static PUBLIC_URL: Lazy<String> =
+    Lazy::new(|| env::var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:3000".into()));
+
+static WEBAUTHN: Lazy<Webauthn> = Lazy::new(|| {
+    let rp_id = env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".into());
+    let origin =
+        Url::parse(&PUBLIC_URL).unwrap_or_else(|_| panic!("PUBLIC_URL is not a valid URL"));
+
+    WebauthnBuilder::new(&rp_id, &origin)
+        .and_then(|builder| builder.rp_name("cdb").build())
+        .unwrap_or_else(|err| panic!("Invalid WebAuthn configuration: {err}"))
+});
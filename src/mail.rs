@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+
+use axum::async_trait;
+
+use crate::Error;
+
+/// An email to deliver to a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers emails on behalf of the application.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), Error>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+/// A mailer that writes emails to the log instead of delivering them.
+#[derive(Debug, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), Error> {
+        tracing::info!(to = %email.to, subject = %email.subject, "{}", email.body);
+
+        Ok(())
+    }
+}
+
+/// A mailer that keeps every email in memory so tests can inspect them.
+#[derive(Debug, Default)]
+pub struct CaptureMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl CaptureMailer {
+    /// Every email sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+
+    /// Every email sent so far to the given recipient, oldest first.
+    pub fn sent_to(&self, to: &str) -> Vec<Email> {
+        self.sent()
+            .into_iter()
+            .filter(|email| email.to == to)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Mailer for CaptureMailer {
+    async fn send(&self, email: Email) -> Result<(), Error> {
+        self.sent
+            .lock()
+            .map_err(|err| {
+                tracing::error!(%err, "Unable to capture an email");
+                Error::InternalError
+            })?
+            .push(email);
+
+        Ok(())
+    }
+}
@@ -7,7 +7,10 @@
 
 use std::env;
 
-use cdb_api::{config::Config, http};
+use cdb_api::{
+    config::{Command, Config},
+    http, import,
+};
 use clap::Parser;
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
@@ -54,7 +57,50 @@ async fn main() {
         tracing::error!("Unable to run database migrations: {}", err);
     }
 
+    if let Some(Command::Import {
+        file,
+        format,
+        dry_run,
+        batch_size,
+    }) = config.command
+    {
+        let format = format
+            .or_else(|| {
+                file.extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(import::FileFormat::from_extension)
+            })
+            .unwrap_or_else(|| panic!("Unable to tell the format of {}", file.display()));
+        let bytes = std::fs::read(&file)
+            .unwrap_or_else(|err| panic!("Unable to read {}: {}", file.display(), err));
+        let options = import::ImportOptions {
+            dry_run,
+            batch_size,
+        };
+
+        let report = match import::import_users(&pool, import::parse(format, &bytes), options).await
+        {
+            Ok(report) => report,
+            Err(err) => {
+                tracing::error!("Unable to import users: {}", err);
+                std::process::exit(1);
+            }
+        };
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+
+        if let Err(err) = import::record(&pool, None, &report).await {
+            tracing::error!("Unable to record the import: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     if let Err(err) = http::serve(pool, config).await {
-        tracing::error!("Unable to start server: {}", err);
+        tracing::error!("Unable to start server: {:?}", err);
     }
 }
@@ -0,0 +1,49 @@
+use std::{fs, path::Path};
+
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+
+use crate::Error;
+
+/// The JSON Schema user metadata is validated against.
+pub struct MetadataSchema {
+    schema: JSONSchema,
+}
+
+impl MetadataSchema {
+    pub fn new(schema: &Value) -> Result<Self, Error> {
+        let schema = JSONSchema::compile(schema).map_err(|err| {
+            tracing::error!("Invalid metadata schema: {}", err);
+            Error::InternalError
+        })?;
+
+        Ok(MetadataSchema { schema })
+    }
+
+    /// Load the schema from a JSON file.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let schema = fs::read(path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Value>(&json).ok())
+            .ok_or_else(|| {
+                tracing::error!("Unable to read metadata schema from {}", path.display());
+                Error::InternalError
+            })?;
+
+        Self::new(&schema)
+    }
+
+    /// A schema accepting any object, used when none is configured.
+    pub fn permissive() -> Self {
+        Self::new(&json!({ "type": "object" })).unwrap_or_else(|_| unreachable!())
+    }
+
+    /// Metadata must be an object that satisfies the schema.
+    pub fn validate(&self, metadata: &Value) -> Result<(), Error> {
+        if metadata.is_object() && self.schema.is_valid(metadata) {
+            Ok(())
+        } else {
+            Err(Error::invalid_field("metadata", "invalid"))
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Permissions granted to users through the groups they belong to.
+//!
+//! A permission granted to a group is held by the members of that group and
+//! of every group nested below it. Groups are either global or belong to an
+//! organization, whose groups only apply to sessions acting in it. Resolving
+//! that takes a recursive query, so the effective permissions of each user
+//! in each organization are cached. Any change to groups
+//! clears the whole cache, as moving one group can change the permissions of
+//! many users. Entries also expire after [`CACHE_TTL`], which bounds how stale
+//! another instance of the application can be.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// How long the permissions of a user are cached for.
+pub const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// The permissions the groups of an organization may grant. The handlers
+/// checking them only act on the members of the organization, whereas the
+/// others, such as `authz:manage`, reach across organizations.
+pub const ORGANIZATION_PERMISSIONS: &[&str] =
+    &["groups:manage", "users:deactivate", "users:export"];
+
+pub type PermissionSet = Arc<BTreeSet<String>>;
+
+/// Resolve every permission a user acting in an organization holds through
+/// their global groups, the groups of that organization, and the ancestors
+/// of those groups.
+pub async fn effective_permissions<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    org_id: Option<Uuid>,
+) -> Result<PermissionSet, Error>
+where
+    E: PgExecutor<'e>,
+{
+    // UNION rather than UNION ALL stops the recursion if groups ever form a
+    // cycle, which the handlers prevent.
+    let permissions = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+          WITH RECURSIVE user_groups AS (
+            SELECT g.id, g.parent_id
+            FROM app.group_members AS m
+            JOIN app.groups AS g
+            ON g.id = m.group_id
+            WHERE m.user_id = $1
+            AND (g.organization_id IS NULL OR g.organization_id = $2)
+            UNION
+            SELECT g.id, g.parent_id
+            FROM app.groups AS g
+            JOIN user_groups AS ug
+            ON g.id = ug.parent_id
+          )
+          SELECT DISTINCT p.permission
+          FROM app.group_permissions AS p
+          JOIN user_groups AS ug
+          ON ug.id = p.group_id
+      "#,
+    )
+    .bind(user_id)
+    .bind(org_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(Arc::new(permissions.into_iter().collect()))
+}
+
+/// Resolve every permission a group grants its members, including those it
+/// inherits from its ancestors.
+pub async fn group_permissions<'e, E>(
+    executor: E,
+    group_id: Uuid,
+) -> Result<BTreeSet<String>, Error>
+where
+    E: PgExecutor<'e>,
+{
+    let permissions = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+          WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM app.groups WHERE id = $1
+            UNION
+            SELECT g.id, g.parent_id
+            FROM app.groups AS g
+            JOIN ancestors AS a
+            ON g.id = a.parent_id
+          )
+          SELECT DISTINCT p.permission
+          FROM app.group_permissions AS p
+          JOIN ancestors AS a
+          ON a.id = p.group_id
+      "#,
+    )
+    .bind(group_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(permissions.into_iter().collect())
+}
+
+/// A user, and the organization they act in.
+type CacheKey = (Uuid, Option<Uuid>);
+
+/// An in-memory cache of the effective permissions of each user, in each
+/// organization they act in.
+#[derive(Debug, Default)]
+pub struct PermissionCache {
+    entries: RwLock<HashMap<CacheKey, (Instant, PermissionSet)>>,
+    generation: AtomicU64,
+}
+
+impl PermissionCache {
+    /// The permissions of a user acting in an organization, resolved from
+    /// the database when they are not cached or have expired.
+    pub async fn get<'e, E>(
+        &self,
+        executor: E,
+        user_id: Uuid,
+        org_id: Option<Uuid>,
+    ) -> Result<PermissionSet, Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let cached = self
+            .entries
+            .read()
+            .map_err(|err| {
+                tracing::error!(%err, "Unable to read the permission cache");
+                Error::InternalError
+            })?
+            .get(&(user_id, org_id))
+            .filter(|(cached_at, _)| cached_at.elapsed() < CACHE_TTL)
+            .map(|(_, permissions)| permissions.clone());
+
+        if let Some(permissions) = cached {
+            return Ok(permissions);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let permissions = effective_permissions(executor, user_id, org_id).await?;
+        let mut entries = self.entries.write().map_err(|err| {
+            tracing::error!(%err, "Unable to write to the permission cache");
+            Error::InternalError
+        })?;
+
+        // Permissions read before an invalidation may already be stale.
+        if self.generation.load(Ordering::SeqCst) == generation {
+            entries.insert((user_id, org_id), (Instant::now(), permissions.clone()));
+        }
+
+        Ok(permissions)
+    }
+
+    /// Forget every cached permission, after groups, their permissions or
+    /// their members changed.
+    pub fn invalidate(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            entries.clear();
+        }
+    }
+}
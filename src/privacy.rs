@@ -0,0 +1,202 @@
+//! Data exports and erasure, to meet our obligations to give users their
+//! data and to forget them.
+//!
+//! # Erasure policy
+//!
+//! A user asks to be erased with `POST /me/erase`. Nothing is removed during
+//! a cooling-off period of [`COOLING_OFF_DAYS`] days, in which the account
+//! keeps working and the request can be withdrawn with `DELETE /me/erase`.
+//! Once the period is over, the scheduled jobs erase the user:
+//!
+//! - `app.users` and `app_private.accounts` rows are deleted, along with every
+//!   row tied to them: passkeys, WebAuthn challenges, device authorizations,
+//!   pending email changes, data exports, memberships of organizations and
+//!   groups, invitations addressed to the user, relation tuples naming them
+//!   and the erasure request itself.
+//! - The avatar and any data export archives are removed from the blob store.
+//! - Audit log entries are kept, since they record what was done to other
+//!   accounts too, but are anonymised: the user is removed as actor and
+//!   subject, and the entry metadata, which may hold personal data such as
+//!   email addresses, is cleared.
+//! - An anonymous `user.erased` audit entry records that an erasure happened.
+
+use serde_json::Value;
+use sqlx::{postgres::types::PgInterval, PgPool};
+use uuid::Uuid;
+
+use crate::{audit, blob::SharedBlobStore, Error};
+
+/// The number of days between an erasure request and the erasure.
+pub const COOLING_OFF_DAYS: i32 = 14;
+
+pub fn cooling_off() -> PgInterval {
+    PgInterval {
+        months: 0,
+        days: COOLING_OFF_DAYS,
+        microseconds: 0,
+    }
+}
+
+/// Produce the archive of a data export and store it, marking the export as
+/// ready, or as failed if anything goes wrong.
+pub async fn run_export(pool: PgPool, blob_store: SharedBlobStore, export_id: Uuid, user_id: Uuid) {
+    let key = format!("exports/{user_id}/{export_id}.json");
+
+    let result = async {
+        let archive = sqlx::query_scalar::<_, Value>(
+            // language=PostgreSQL
+            r#"SELECT app_private.export_user($1)"#,
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await?;
+
+        let bytes = serde_json::to_vec_pretty(&archive)?;
+        blob_store.put(&key, bytes.into()).await?;
+
+        Ok::<_, Error>(())
+    }
+    .await;
+
+    let (status, blob_key) = match result {
+        Ok(()) => ("ready", Some(key)),
+        Err(err) => {
+            tracing::error!("Unable to export data of user `{}`: {}", user_id, err);
+            ("failed", None)
+        }
+    };
+
+    let updated = sqlx::query(
+        // language=PostgreSQL
+        r#"
+            UPDATE app_private.data_exports
+            SET status = $2, blob_key = $3, completed_at = now()
+            WHERE id = $1
+        "#,
+    )
+    .bind(export_id)
+    .bind(status)
+    .bind(blob_key)
+    .execute(&pool)
+    .await;
+
+    if let Err(err) = updated {
+        tracing::error!("Unable to complete export `{}`: {}", export_id, err);
+    }
+}
+
+/// Remove expired data exports and their archives, returning how many were removed.
+pub async fn remove_expired_exports(
+    pool: &PgPool,
+    blob_store: &SharedBlobStore,
+) -> Result<i64, Error> {
+    let keys = sqlx::query_scalar::<_, Option<String>>(
+        // language=PostgreSQL
+        r#"DELETE FROM app_private.data_exports WHERE expires_at <= now() RETURNING blob_key"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let count = keys.len() as i64;
+    delete_blobs(blob_store, keys.into_iter().flatten()).await;
+
+    Ok(count)
+}
+
+/// Start again the exports that were still pending when the server stopped,
+/// since they are produced by a task of the process that accepted the request.
+/// Returns how many were started.
+pub async fn resume_pending_exports(
+    pool: &PgPool,
+    blob_store: &SharedBlobStore,
+) -> Result<i64, Error> {
+    let pending = sqlx::query_as::<_, (Uuid, Uuid)>(
+        // language=PostgreSQL
+        r#"SELECT id, user_id FROM app_private.data_exports WHERE status = 'pending'"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (export_id, user_id) in &pending {
+        tokio::spawn(run_export(
+            pool.clone(),
+            blob_store.clone(),
+            *export_id,
+            *user_id,
+        ));
+    }
+
+    Ok(pending.len() as i64)
+}
+
+/// Erase the users whose cooling-off period is over, returning how many were
+/// erased. A user that can't be erased is logged and retried on the next run,
+/// without holding up the others.
+pub async fn erase_due_users(pool: &PgPool, blob_store: &SharedBlobStore) -> Result<i64, Error> {
+    let due = sqlx::query_scalar::<_, Uuid>(
+        // language=PostgreSQL
+        r#"SELECT user_id FROM app_private.erasure_requests WHERE erase_after <= now()"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut count = 0;
+
+    for user_id in due {
+        match erase_user(pool, blob_store, user_id).await {
+            Ok(()) => count += 1,
+            Err(err) => tracing::error!("Unable to erase user `{}`: {}", user_id, err),
+        }
+    }
+
+    Ok(count)
+}
+
+async fn erase_user(
+    pool: &PgPool,
+    blob_store: &SharedBlobStore,
+    user_id: Uuid,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    let keys = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"
+            SELECT avatar_key FROM app.users WHERE id = $1 AND avatar_key IS NOT NULL
+            UNION ALL
+            SELECT blob_key FROM app_private.data_exports WHERE user_id = $1 AND blob_key IS NOT NULL
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut tx)
+    .await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"SELECT app_private.erase_user($1)"#,
+    )
+    .bind(user_id)
+    .execute(&mut tx)
+    .await?;
+
+    audit::record(&mut tx, None, None, "user.erased", serde_json::json!({})).await?;
+
+    tx.commit().await?;
+
+    delete_blobs(blob_store, keys).await;
+
+    Ok(())
+}
+
+/// Remove blobs that are no longer referenced. Failures only waste space, so
+/// they are logged rather than returned.
+pub(crate) async fn delete_blobs(
+    blob_store: &SharedBlobStore,
+    keys: impl IntoIterator<Item = String>,
+) {
+    for key in keys {
+        if let Err(err) = blob_store.delete(&key).await {
+            tracing::warn!("Unable to delete blob `{}`: {}", key, err);
+        }
+    }
+}
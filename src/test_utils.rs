@@ -1,11 +1,24 @@
+use std::{future::Future, panic::Location};
 
 use axum::{
     body::{Body, BoxBody, HttpBody},
-    http::{header::CONTENT_TYPE, request, Request, Response},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, ETAG},
+        request, Request, Response,
+    },
+    Router,
 };
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::http::error::PROBLEM_JSON;
 
 pub trait RequestBuilderExt {
     fn json(self, json: serde_json::Value) -> Request<Body>;
+    fn form(self, form: &str) -> Request<Body>;
+    fn multipart(self, field: &str, content_type: &str, bytes: &[u8]) -> Request<Body>;
     fn empty_body(self) -> Request<Body>;
 }
 
@@ -18,20 +31,38 @@ impl RequestBuilderExt for request::Builder {
             .expect("failed to buld request")
     }
 
+    fn form(self, form: &str) -> Request<Body> {
+        self.header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(form.to_string()))
+            .expect("failed to build request")
+    }
+
+    fn multipart(self, field: &str, content_type: &str, bytes: &[u8]) -> Request<Body> {
+        let boundary = "cdb-test-boundary";
+        let mut body = format!(
+            "--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"{field}\"; filename=\"{field}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        self.header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(Body::from(body))
+        .expect("failed to build request")
+    }
+
     fn empty_body(self) -> Request<Body> {
         self.body(Body::empty()).expect("failed to build request")
     }
 }
 
-#[track_caller]
-pub async fn response_json(resp: &mut Response<BoxBody>) -> serde_json::Value {
-    assert_eq!(
-        resp.headers()
-            .get(CONTENT_TYPE)
-            .expect("expected Content-Type"),
-        "application/json"
-    );
-
+/// Read the whole body of a response as text.
+pub async fn response_text(resp: &mut Response<BoxBody>) -> String {
     let body = resp.body_mut();
     let mut bytes = Vec::new();
 
@@ -40,5 +71,84 @@ pub async fn response_json(resp: &mut Response<BoxBody>) -> serde_json::Value {
         bytes.extend_from_slice(&chunk[..]);
     }
 
-    serde_json::from_slice(&bytes).expect("Failed to read response body as json")
+    String::from_utf8(bytes).expect("Failed to read response body as text")
+}
+
+/// Read the whole body of a JSON response. Failures are reported at the
+/// caller, which `#[track_caller]` can only see outside of the future.
+#[track_caller]
+pub fn response_json(resp: &mut Response<BoxBody>) -> impl Future<Output = serde_json::Value> + '_ {
+    let caller = Location::caller();
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .expect("expected Content-Type");
+
+    assert!(
+        content_type == "application/json" || content_type == PROBLEM_JSON,
+        "expected a JSON Content-Type, got {content_type:?}"
+    );
+
+    async move {
+        let body = resp.body_mut();
+        let mut bytes = Vec::new();
+
+        while let Some(res) = body.data().await {
+            let chunk =
+                res.unwrap_or_else(|err| panic!("error reading response body at {caller}: {err}"));
+            bytes.extend_from_slice(&chunk[..]);
+        }
+
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|err| panic!("Failed to read response body as json at {caller}: {err}"))
+    }
+}
+
+/// Authorize with the given credentials and return the issued access token.
+pub async fn access_token(app: &mut Router, email: &str, password: &str) -> String {
+    let request = Request::post("/auth/authorize").json(json! {{
+        "clientId": email,
+        "clientSecret": password
+    }});
+
+    let mut res = app
+        .oneshot(request)
+        .await
+        .expect("failed to send authorize request");
+    let json = response_json(&mut res).await;
+
+    json["accessToken"]
+        .as_str()
+        .expect("Expecting an access token")
+        .to_string()
+}
+
+/// Look up the id of the user registered with the given email.
+pub async fn user_id(pool: &PgPool, email: &str) -> sqlx::Result<Uuid> {
+    sqlx::query_scalar(
+        // language=PostgreSQL
+        r#"SELECT user_id FROM app_private.accounts WHERE email = $1"#,
+    )
+    .bind(email)
+    .fetch_one(pool)
+    .await
+}
+
+/// Fetch the resource at the given URI with an access token and return its
+/// `ETag`.
+pub async fn etag(app: &mut Router, uri: &str, token: &str) -> String {
+    let res = app
+        .oneshot(
+            Request::get(uri)
+                .header(AUTHORIZATION, format!("Bearer {token}"))
+                .empty_body(),
+        )
+        .await
+        .expect("failed to send request");
+
+    res.headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .expect("Expecting an ETag")
+        .to_string()
 }
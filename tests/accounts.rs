@@ -1,4 +1,4 @@
-use axum::http::Request;
+use axum::http::{Request, StatusCode};
 
 use cdb_api::{http::routes, test_utils::*};
 use eyre::Result;
@@ -38,3 +38,23 @@ async fn test_register(pool: PgPool) -> Result<()> {
 
     Ok(())
 }
+
+#[sqlx::test(fixtures("users"))]
+async fn test_register_taken_email(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+
+    let request = Request::post("/accounts/register").json(json! {{
+        "firstName": "Sleepy",
+        "lastName": "Gary",
+        "email": "sleepy.g@yahoo.com",
+        "password": "thisIsMyPassword"
+    }});
+
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    assert_eq!(json["code"], "email_taken");
+
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use std::borrow::BorrowMut;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH},
+        Request, StatusCode,
+    },
+};
+use cdb_api::{
+    http::{handlers::admin::MAX_IMPORT_SIZE, routes},
+    test_utils::*,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_impersonate(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+
+    let request = Request::post(format!("/admin/users/{charlie}/impersonate"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "reason": "Reproducing a bug report" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json.get("refreshToken").is_none());
+
+    let impersonated = json["accessToken"]
+        .as_str()
+        .expect("Expecting access token");
+
+    let request = Request::post("/accounts/password")
+        .header(AUTHORIZATION, format!("Bearer {impersonated}"))
+        .json(json! {{ "currentPassword": "kittenmittons", "newPassword": "milksteak" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let actions = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT action FROM app_private.audit_log WHERE subject_id = $1 ORDER BY created_at"#,
+    )
+    .bind(charlie)
+    .fetch_all(&pool)
+    .await?;
+
+    assert_eq!(actions, ["impersonation.start", "impersonation.request"]);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_impersonate_requires_admin(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+
+    let token = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+
+    let request = Request::post(format!("/admin/users/{dee}/impersonate"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "reason": "Birds are spies" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_deactivate_and_restore(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+
+    let original = etag(&mut app, &format!("/users/{charlie}"), &token).await;
+
+    let request = Request::post(format!("/admin/users/{charlie}/deactivate"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    let request = Request::post(format!("/admin/users/{charlie}/deactivate"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let deactivated = res.headers()[ETAG].clone();
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json["deactivatedAt"].is_i64());
+
+    let request = Request::post("/auth/authorize").json(json! {{
+        "clientId": "wildcard@paddys.com",
+        "clientSecret": "kittenmittons"
+    }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::get("/users")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"].as_array().map(Vec::len), Some(1));
+
+    let request = Request::get("/users?include_inactive=true")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"].as_array().map(Vec::len), Some(2));
+
+    let request = Request::post(format!("/admin/users/{charlie}/restore"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    let request = Request::post(format!("/admin/users/{charlie}/restore"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, deactivated)
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let token = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+
+    assert!(!token.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_import_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let csv = "firstName,lastName,email,password\n\
+               Frank,Reynolds,frank@paddys.com,rumham123\n\
+               Dennis,Reynolds,not-an-email,goldengod\n\
+               Charlie,Kelly,wildcard@paddys.com,kittenmittons\n\
+               Mac,McDonald,mac@paddys.com\n";
+    let import = |query: &'static str| {
+        Request::post(format!("/admin/users/import{query}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header(CONTENT_TYPE, "text/csv")
+            .body(Body::from(csv))
+            .expect("failed to build request")
+    };
+
+    let mut res = app.borrow_mut().oneshot(import("?dry_run=true")).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["succeeded"], 1);
+    assert_eq!(
+        json["rows"]
+            .as_array()
+            .map(|rows| rows.iter().map(|row| row["status"].clone()).collect()),
+        Some(vec![
+            json!("valid"),
+            json!("failed"),
+            json!("failed"),
+            json!("failed")
+        ])
+    );
+    assert!(user_id(&pool, "frank@paddys.com").await.is_err());
+
+    let mut res = app.borrow_mut().oneshot(import("?batch_size=0")).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response_json(&mut res).await["errors"][0]["field"],
+        "batch_size"
+    );
+
+    let mut res = app.borrow_mut().oneshot(import("?batch_size=2")).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["rows"][0]["status"], "created");
+    assert_eq!(json["rows"][2]["error"], "Email address is already in use");
+    assert_eq!(
+        json["rows"][0]["userId"],
+        user_id(&pool, "frank@paddys.com").await?.to_string()
+    );
+
+    let ndjson = r#"{"firstName":"Mac","lastName":"McDonald","email":"mac@paddys.com","password":"karatekid"}"#;
+    let request = Request::post("/admin/users/import?format=ndjson")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::from(ndjson))?;
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["succeeded"], 1);
+
+    let twice = r#"{"firstName":"Ronald","lastName":"McDonald","email":"ronald@paddys.com","password":"karatekid"}
+{"firstName":"Ronald","lastName":"McDonald","email":"ronald@paddys.com","password":"karatekid"}"#;
+    let request = Request::post("/admin/users/import?format=ndjson&dry_run=true&batch_size=1")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::from(twice))?;
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["rows"][0]["status"], "valid");
+    assert_eq!(
+        json["rows"][1]["status"], "failed",
+        "Expecting duplicates across batches to be reported by dry runs"
+    );
+
+    // Rows breaking other constraints are reported without aborting the import.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"ALTER TABLE app.users ADD CONSTRAINT users_no_rum_ham CHECK (first_name <> 'Rum')"#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let rows = r#"{"firstName":"Rum","lastName":"Ham","email":"rum.ham@paddys.com","password":"karatekid"}
+{"firstName":"Ronald","lastName":"McDonald","email":"ronald@paddys.com","password":"karatekid"}"#;
+    let request = Request::post("/admin/users/import?format=ndjson")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::from(rows))?;
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["rows"][0]["status"], "failed");
+    assert_eq!(json["rows"][1]["status"], "created");
+
+    let imports = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT count(*) FROM app_private.audit_log WHERE action = 'user.import'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(
+        imports, 3,
+        "Expecting every import but dry runs to be audited"
+    );
+
+    for (length, body) in [
+        (Some("20000000"), Vec::new()),
+        (None, vec![b'\n'; MAX_IMPORT_SIZE + 1]),
+    ] {
+        let mut request = Request::post("/admin/users/import?format=ndjson")
+            .header(AUTHORIZATION, format!("Bearer {token}"));
+
+        if let Some(length) = length {
+            request = request.header(CONTENT_LENGTH, length);
+        }
+
+        let mut res = app
+            .borrow_mut()
+            .oneshot(request.body(Body::from(body))?)
+            .await?;
+
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response_json(&mut res).await["code"], "payload_too_large");
+    }
+
+    let charlie = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+    let request = Request::post("/admin/users/import?format=ndjson")
+        .header(AUTHORIZATION, format!("Bearer {charlie}"))
+        .body(Body::from(ndjson))?;
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_export_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let export = |query: &str| {
+        Request::get(format!("/admin/users/export{query}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body()
+    };
+
+    let mut res = app
+        .borrow_mut()
+        .oneshot(export("?format=csv&sort=email"))
+        .await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[CONTENT_TYPE], "text/csv");
+
+    let csv = response_text(&mut res).await;
+    let lines = csv.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("id,firstName,lastName,email,"));
+    assert!(lines[1].contains("sweet.dee@paddys.com"));
+    assert!(lines[2].contains("wildcard@paddys.com"));
+
+    let mut res = app
+        .borrow_mut()
+        .oneshot(export("?format=ndjson&role=user"))
+        .await?;
+    let ndjson = response_text(&mut res).await;
+    let users = ndjson
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["email"], "wildcard@paddys.com");
+
+    let res = app.borrow_mut().oneshot(export("?format=xml")).await?;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations", "admins"))]
+async fn test_export_inactive_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let export = |query: &str| {
+        Request::get(format!("/admin/users/export{query}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body()
+    };
+
+    let res = app
+        .borrow_mut()
+        .oneshot(export("?format=csv&include_inactive=true"))
+        .await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Exporting users is a permission that can be granted, unlike seeing
+    // inactive users.
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          WITH g AS (
+            INSERT INTO app.groups (name) VALUES ('Exporters') RETURNING id
+          ), p AS (
+            INSERT INTO app.group_permissions (group_id, permission)
+            SELECT g.id, 'users:export' FROM g
+          )
+          INSERT INTO app.group_members (group_id, user_id)
+          SELECT g.id, a.user_id FROM g, app_private.accounts AS a
+          WHERE a.email = 'sleepy.g@yahoo.com'
+      "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let sleepy_token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    for (query, expected) in [
+        ("?format=csv", StatusCode::OK),
+        ("?format=csv&include_inactive=true", StatusCode::FORBIDDEN),
+    ] {
+        let request = Request::get(format!("/admin/users/export{query}"))
+            .header(AUTHORIZATION, format!("Bearer {sleepy_token}"))
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected, "Exporting with {query}");
+    }
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use axum::http::Request;
+use axum::http::{Request, StatusCode};
 
 use cdb_api::{http::routes, test_utils::*};
 use eyre::Result;
@@ -55,3 +55,42 @@ async fn test_revalidate(pool: PgPool) -> Result<()> {
 
     Ok(())
 }
+
+#[sqlx::test(fixtures("users"))]
+async fn test_invalid_credentials(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          UPDATE app.users SET deactivated_at = now()
+          WHERE id = (SELECT user_id FROM app_private.accounts WHERE email = 'kikos.delivery.service@gmail.com')
+      "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    for (email, password) in [
+        ("sleepy.g@yahoo.com", "wrong"),
+        ("nobody@yahoo.com", "test"),
+        ("kikos.delivery.service@gmail.com", "awoo"),
+    ] {
+        let request = Request::post("/auth/authorize").json(json! {{
+            "clientId": email,
+            "clientSecret": password
+        }});
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{email}");
+        assert_eq!(response_json(&mut res).await["code"], "invalid_credentials");
+    }
+
+    let request = Request::post("/auth/revalidate").json(json! {{
+        "refreshToken": "0b6e4ab4-6d4e-4bd6-9d9d-31c5d3e0aa2b"
+    }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::borrow::BorrowMut;
+
+use axum::http::{
+    header::{AUTHORIZATION, IF_MATCH},
+    Request, StatusCode,
+};
+use cdb_api::{
+    authz::{write_tuple, ObjectRef, RelationTuple, SubjectRef},
+    http::routes,
+    test_utils::*,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn test_user_editors(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let sleepy = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let sleepy_token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+
+    // Owning the organization of a user is not enough to edit them, only a
+    // direct `editor` tuple is.
+    for (grant, expected) in [(false, StatusCode::FORBIDDEN), (true, StatusCode::OK)] {
+        if grant {
+            write_tuple(
+                &pool,
+                &RelationTuple {
+                    object: ObjectRef::user(kiko),
+                    relation: "editor".to_string(),
+                    subject: SubjectRef::user(sleepy),
+                },
+            )
+            .await?;
+        }
+
+        let etag = etag(&mut app, &format!("/users/{kiko}"), &sleepy_token).await;
+        let request = Request::patch(format!("/users/{kiko}"))
+            .header(AUTHORIZATION, format!("Bearer {sleepy_token}"))
+            .header(IF_MATCH, etag)
+            .json(json! {{ "displayName": "Bubbles" }});
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+    }
+
+    for (body, expected) in [
+        (
+            json! {{ "relation": "viewer", "object": format!("user:{sleepy}") }},
+            json!(true),
+        ),
+        (
+            json! {{ "relation": "editor", "object": format!("user:{sleepy}") }},
+            json!(false),
+        ),
+    ] {
+        let request = Request::post("/authz/check")
+            .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+            .json(body);
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(response_json(&mut res).await["allowed"], expected);
+    }
+
+    for (body, expected, field) in [
+        (
+            json! {{ "subject": format!("user:{sleepy}"), "relation": "viewer", "object": format!("user:{kiko}") }},
+            StatusCode::FORBIDDEN,
+            json!(null),
+        ),
+        (
+            json! {{ "relation": "stalker", "object": format!("user:{sleepy}") }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            json!("relation"),
+        ),
+        (
+            json! {{ "relation": "viewer", "object": "sleepy" }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            json!("object"),
+        ),
+    ] {
+        let request = Request::post("/authz/check")
+            .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+            .json(body);
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+        assert_eq!(response_json(&mut res).await["errors"][0]["field"], field);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_org_owners_cannot_edit_their_members(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/orgs")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Krusty Krab" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let org_id = response_json(&mut res).await["id"].clone();
+
+    let request = Request::post(format!(
+        "/orgs/{}/invitations",
+        org_id.as_str().unwrap_or_default()
+    ))
+    .header(AUTHORIZATION, format!("Bearer {token}"))
+    .json(json! {{ "email": "kikos.delivery.service@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let invitation = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT token FROM app_private.invitations"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+    let request = Request::post("/invitations/accept").json(json! {{ "token": invitation }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // A new token acts in the organization, whose owner can now see Kiko.
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let etag = etag(&mut app, &format!("/users/{kiko}"), &token).await;
+    let request = Request::patch(format!("/users/{kiko}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, etag)
+        .json(json! {{ "displayName": "Pwned" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations", "admins"))]
+async fn test_relation_tuples(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+    let org_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.organizations WHERE name = 'Bikini Bottom'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let charlie_token = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+
+    let tuples = json!([
+        { "object": "document:readme", "relation": "org", "subject": format!("org:{org_id}") },
+        { "object": "document:readme", "relation": "owner", "subject": format!("user:{charlie}") }
+    ]);
+
+    for (token, body, expected) in [
+        (
+            &charlie_token,
+            json! {{ "writes": tuples }},
+            StatusCode::FORBIDDEN,
+        ),
+        (
+            &token,
+            json! {{ "writes": [{ "object": "document:readme", "relation": "janitor", "subject": format!("user:{charlie}") }] }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (&token, json! {{ "writes": tuples }}, StatusCode::NO_CONTENT),
+    ] {
+        let request = Request::post("/authz/tuples")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(body);
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+    }
+
+    let request = Request::post("/authz/tuples")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "deletes": [tuples[0].clone(), { "object": "readme", "relation": "viewer", "subject": "user:nobody" }] }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response_json(&mut res).await["errors"][0]["field"],
+        "deletes[1].object"
+    );
+
+    let request = Request::get("/authz/tuples?object=document:readme")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(response_json(&mut res).await, tuples);
+
+    for (token, relation, expected) in [
+        (&kiko_token, "viewer", true),
+        (&kiko_token, "editor", false),
+        (&charlie_token, "editor", true),
+    ] {
+        let request = Request::post("/authz/check")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(json! {{ "relation": relation, "object": "document:readme" }});
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(response_json(&mut res).await["allowed"], json!(expected));
+    }
+
+    let request = Request::delete(format!(
+        "/orgs/{org_id}/members/{}",
+        user_id(&pool, "kikos.delivery.service@gmail.com").await?
+    ))
+    .header(AUTHORIZATION, format!("Bearer {token}"))
+    .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::post("/authz/check")
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .json(json! {{ "relation": "viewer", "object": "document:readme" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        response_json(&mut res).await["allowed"],
+        json!(false),
+        "Expecting former members to lose access"
+    );
+
+    Ok(())
+}
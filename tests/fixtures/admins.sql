@@ -0,0 +1,8 @@
+BEGIN;
+
+SELECT app.register_user('Dee', 'Reynolds', 'sweet.dee@paddys.com', 'birdlady');
+SELECT app.register_user('Charlie', 'Kelly', 'wildcard@paddys.com', 'kittenmittons');
+
+UPDATE app_private.accounts SET role = 'admin' WHERE email = 'sweet.dee@paddys.com';
+
+END;
@@ -0,0 +1,11 @@
+BEGIN;
+
+INSERT INTO app.organizations (name) VALUES ('Bikini Bottom');
+
+INSERT INTO app.memberships (organization_id, user_id, role)
+SELECT o.id, a.user_id, CASE WHEN a.email = 'sleepy.g@yahoo.com' THEN 'owner' ELSE 'member' END
+FROM app.organizations AS o, app_private.accounts AS a
+WHERE o.name = 'Bikini Bottom'
+AND a.email IN ('sleepy.g@yahoo.com', 'kikos.delivery.service@gmail.com');
+
+END;
@@ -0,0 +1,269 @@
+use std::borrow::BorrowMut;
+
+use axum::http::{
+    header::{AUTHORIZATION, IF_MATCH},
+    Request, StatusCode,
+};
+use cdb_api::{http::routes, test_utils::*};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[sqlx::test(fixtures("admins"))]
+async fn test_group_permissions(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let charlie_token = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+
+    let request = Request::get("/me/permissions")
+        .header(AUTHORIZATION, format!("Bearer {charlie_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(response_json(&mut res).await["permissions"], json!([]));
+
+    let request = Request::post("/groups")
+        .header(AUTHORIZATION, format!("Bearer {charlie_token}"))
+        .json(json! {{ "name": "The Gang" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::post("/groups")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Staff", "permissions": ["users:export"] }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let staff = response_json(&mut res).await["id"].clone();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let request = Request::post("/groups")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(
+            json! {{ "name": "Janitors", "parentId": staff, "permissions": ["users:deactivate"] }},
+        );
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let janitors = response_json(&mut res).await["id"].clone();
+    let janitors = janitors.as_str().unwrap_or_default();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    for (body, expected, code) in [
+        (
+            json! {{ "name": "Staff" }},
+            StatusCode::CONFLICT,
+            "conflict",
+        ),
+        (
+            json! {{ "name": "Bar", "permissions": ["Rum Ham"] }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_value",
+        ),
+        (
+            json! {{ "name": "Bar", "parentId": "0b6e4ab4-6d4e-4bd6-9d9d-31c5d3e0aa2b" }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_reference",
+        ),
+    ] {
+        let request = Request::post("/groups")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(body);
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+        assert_eq!(response_json(&mut res).await["code"], code);
+    }
+
+    let request = Request::patch(format!("/groups/{}", staff.as_str().unwrap_or_default()))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "parentId": janitors }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        res.status(),
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "Expecting a group not to nest in its own descendant"
+    );
+    assert_eq!(
+        response_json(&mut res).await["errors"][0]["field"],
+        "parentId"
+    );
+
+    let request = Request::put(format!("/groups/{janitors}/members/{charlie}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::get("/me/permissions")
+        .header(AUTHORIZATION, format!("Bearer {charlie_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        response_json(&mut res).await["permissions"],
+        json!(["users:deactivate", "users:export"]),
+        "Expecting the permissions of the parent group to be inherited"
+    );
+
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+    let request = Request::post(format!("/admin/users/{dee}/deactivate"))
+        .header(AUTHORIZATION, format!("Bearer {charlie_token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        res.status(),
+        StatusCode::NOT_FOUND,
+        "Expecting only administrators to reach users outside of their organization"
+    );
+
+    let request = Request::delete(format!("/groups/{janitors}/members/{charlie}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::post(format!("/admin/users/{dee}/restore"))
+        .header(AUTHORIZATION, format!("Bearer {charlie_token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations", "admins"))]
+async fn test_organization_groups(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+
+    sqlx::query(
+        r#"
+          WITH g AS (
+            INSERT INTO app.groups (name, organization_id)
+            SELECT 'Managers', id FROM app.organizations WHERE name = 'Bikini Bottom'
+            RETURNING id
+          ), p AS (
+            INSERT INTO app.group_permissions (group_id, permission)
+            SELECT g.id, p FROM g, unnest(ARRAY['groups:manage', 'users:deactivate']) AS p
+          )
+          INSERT INTO app.group_members (group_id, user_id)
+          SELECT g.id, a.user_id FROM g, app_private.accounts AS a
+          WHERE a.email = 'sleepy.g@yahoo.com'
+      "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let dee_token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+
+    for (permissions, expected) in [
+        (json!(["authz:manage"]), StatusCode::UNPROCESSABLE_ENTITY),
+        (json!(["users:import"]), StatusCode::UNPROCESSABLE_ENTITY),
+        (json!(["users:export"]), StatusCode::FORBIDDEN),
+    ] {
+        let request = Request::post("/groups")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(json! {{ "name": "Admins", "permissions": permissions }});
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected, "Granting {permissions}");
+    }
+
+    let request = Request::post("/groups")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Janitors", "permissions": ["users:deactivate"] }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let janitors = response_json(&mut res).await["id"].clone();
+    let janitors = janitors.as_str().unwrap_or_default();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    for (user, expected) in [
+        (charlie, StatusCode::NOT_FOUND),
+        (kiko, StatusCode::NO_CONTENT),
+    ] {
+        let request = Request::put(format!("/groups/{janitors}/members/{user}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+    }
+
+    let request = Request::get("/me/permissions")
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        response_json(&mut res).await["permissions"],
+        json!(["users:deactivate"])
+    );
+
+    let request = Request::get("/groups")
+        .header(AUTHORIZATION, format!("Bearer {dee_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        response_json(&mut res).await,
+        json!([]),
+        "Expecting the groups of organizations not to be global"
+    );
+
+    let request = Request::patch(format!("/groups/{janitors}"))
+        .header(AUTHORIZATION, format!("Bearer {dee_token}"))
+        .json(json! {{ "name": "Admins" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    for (user, expected) in [
+        (charlie, StatusCode::NOT_FOUND),
+        (dee, StatusCode::NOT_FOUND),
+    ] {
+        let request = Request::post(format!("/admin/users/{user}/deactivate"))
+            .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(
+            res.status(),
+            expected,
+            "Expecting users outside to be out of reach"
+        );
+    }
+
+    sqlx::query(
+        r#"
+          INSERT INTO app.memberships (organization_id, user_id, role)
+          SELECT id, $1, 'member' FROM app.organizations WHERE name = 'Bikini Bottom'
+      "#,
+    )
+    .bind(dee)
+    .execute(&pool)
+    .await?;
+
+    for (user, expected) in [(dee, StatusCode::FORBIDDEN), (kiko, StatusCode::OK)] {
+        let request = Request::post(format!("/admin/users/{user}/deactivate"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header(IF_MATCH, "*")
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::{borrow::BorrowMut, collections::BTreeSet};
+
+use axum::http::{
+    header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE},
+    Request, StatusCode,
+};
+use cdb_api::{
+    http::{error::VALIDATION_CODES, i18n, routes},
+    test_utils::*,
+    Error,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[test]
+fn test_catalogues_complete() {
+    let english = i18n::message_ids(&i18n::DEFAULT_LOCALE);
+
+    for code in Error::CODES {
+        assert!(english.contains(*code), "Missing message for {code}");
+
+        // Bad requests and bodies are explained by the rejection, in English.
+        if !matches!(*code, "bad_request" | "invalid_body") {
+            assert!(
+                english.contains(&format!("{code}.detail")),
+                "Missing detail for {code}"
+            );
+        }
+    }
+
+    let validators = english
+        .iter()
+        .filter_map(|id| id.strip_prefix("validation-"))
+        .collect::<BTreeSet<_>>();
+
+    assert_eq!(
+        validators,
+        VALIDATION_CODES.iter().copied().collect::<BTreeSet<_>>(),
+        "The validation messages differ from the validation codes"
+    );
+
+    for locale in i18n::locales() {
+        let ids = i18n::message_ids(locale);
+
+        assert_eq!(
+            ids.symmetric_difference(&english).collect::<Vec<_>>(),
+            Vec::<&String>::new(),
+            "The {locale} catalogue differs from the English one"
+        );
+
+        for id in &ids {
+            let (id, attribute) = match id.split_once('.') {
+                Some((id, attribute)) => (id, Some(attribute)),
+                None => (id.as_str(), None),
+            };
+
+            assert!(i18n::translate(locale, id, attribute).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_negotiate() {
+    let cases = [
+        ("fr-CA,fr;q=0.9,en;q=0.8", "fr"),
+        ("de, es;q=0.5, fr;q=0.4", "es"),
+        ("en;q=0.1, es", "es"),
+        ("fr;q=0, de", "en"),
+        ("*", "en"),
+        ("", "en"),
+    ];
+
+    for (accept_language, expected) in cases {
+        assert_eq!(
+            i18n::negotiate(accept_language).to_string(),
+            expected,
+            "Negotiating {accept_language:?}"
+        );
+    }
+}
+
+#[sqlx::test]
+async fn test_localised_problems(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+
+    let request = Request::get("/nowhere")
+        .header(ACCEPT_LANGUAGE, "es-MX,es;q=0.9")
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers()[CONTENT_LANGUAGE], "es");
+
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["title"], "No encontrado");
+    assert_eq!(json["detail"], "El recurso solicitado no existe.");
+    assert_eq!(json["code"], "not_found");
+
+    let request = Request::post("/accounts/register")
+        .header(ACCEPT_LANGUAGE, "fr")
+        .json(json! {{
+            "email": "not an email",
+            "password": "short"
+        }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["detail"], "Un ou plusieurs champs sont invalides.");
+    assert_eq!(json["errors"][0]["message"], "Adresse e-mail invalide");
+    assert_eq!(json["errors"][1]["message"], "Trop court ou trop long");
+
+    let request = Request::get("/nowhere")
+        .header(ACCEPT_LANGUAGE, "de-DE")
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.headers()[CONTENT_LANGUAGE], "en");
+    assert_eq!(response_json(&mut res).await["title"], "Not found");
+
+    Ok(())
+}
@@ -0,0 +1,379 @@
+use std::{borrow::BorrowMut, sync::Arc};
+
+use axum::{
+    body::HttpBody,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
+        Request, StatusCode,
+    },
+};
+use cdb_api::{
+    blob::LocalBlobStore,
+    http::{routes, routes_with, Services},
+    mail::CaptureMailer,
+    metadata::MetadataSchema,
+    test_utils::*,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[sqlx::test(fixtures("users"))]
+async fn test_find_me(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::get("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["email"], "sleepy.g@yahoo.com");
+    assert_eq!(json["emailVerified"], false);
+    assert_eq!(json["roles"], json!(["user"]));
+    assert_eq!(json["mfaEnabled"], false);
+    assert!(
+        json["lastLogin"].is_i64(),
+        "Expecting the login to be recorded"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_update_me(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let original = etag(&mut app, &format!("/users/{id}"), &token).await;
+
+    let request = Request::patch("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "firstName": "Wakeful" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    let request = Request::patch("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .json(json! {{ "firstName": "Wakeful" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_ne!(res.headers()[ETAG], original.as_str());
+    assert_eq!(json["firstName"], "Wakeful");
+    assert_eq!(json["lastName"], "Gary");
+
+    let request = Request::patch("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .json(json! {{ "firstName": "Sleepy" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_find_me_requires_token(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+
+    let request = Request::get("/me").empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_change_email(pool: PgPool) -> Result<()> {
+    let mailer = Arc::new(CaptureMailer::default());
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            mailer: mailer.clone(),
+            ..Services::default()
+        },
+    );
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let original = etag(&mut app, &format!("/users/{id}"), &token).await;
+
+    let request = Request::post("/me/email")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "email": "kikos.delivery.service@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let request = Request::post("/me/email")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "email": "sleepy.gary@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(mailer.sent_to("sleepy.g@yahoo.com").len(), 1);
+
+    let confirmation = mailer.sent_to("sleepy.gary@gmail.com");
+    let change_token = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT token FROM app_private.email_changes WHERE user_id = $1"#,
+    )
+    .bind(id)
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(confirmation.len(), 1);
+    assert!(confirmation[0].body.contains(&change_token.to_string()));
+
+    let request = Request::post("/accounts/email/confirm").json(json! {{ "token": change_token }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let token = access_token(&mut app, "sleepy.gary@gmail.com", "test").await;
+    let request = Request::get("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["email"], "sleepy.gary@gmail.com");
+    assert_eq!(json["emailVerified"], true);
+    assert_ne!(
+        etag(&mut app, &format!("/users/{id}"), &token).await,
+        original,
+        "Expecting the new address to be a new version of the user"
+    );
+
+    let request = Request::post("/accounts/email/confirm").json(json! {{ "token": change_token }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_change_email_to_taken_address(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/me/email")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "email": "sleepy.gary@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    let request = Request::post("/accounts/register").json(json! {{
+        "firstName": "Gary",
+        "lastName": "Snail",
+        "email": "sleepy.gary@gmail.com",
+        "password": "hunter22"
+    }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let change_token = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT token FROM app_private.email_changes WHERE user_id = $1"#,
+    )
+    .bind(id)
+    .fetch_one(&pool)
+    .await?;
+
+    let request = Request::post("/accounts/email/confirm").json(json! {{ "token": change_token }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_update_profile_details(pool: PgPool) -> Result<()> {
+    let metadata_schema = MetadataSchema::new(&json!({
+        "type": "object",
+        "properties": { "pronouns": { "type": "string" } },
+        "additionalProperties": false
+    }))?;
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            metadata_schema: Arc::new(metadata_schema),
+            ..Services::default()
+        },
+    );
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::patch("/me")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, "*")
+        .json(json! {{
+            "displayName": "Gary",
+            "locale": "en-US",
+            "timezone": "America/Chicago",
+            "metadata": { "pronouns": "he/him" }
+        }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let request = Request::get(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["displayName"], "Gary");
+    assert_eq!(json["locale"], "en-US");
+    assert_eq!(json["timezone"], "America/Chicago");
+    assert_eq!(json["metadata"], json!({ "pronouns": "he/him" }));
+    assert_eq!(json["avatarUrl"], serde_json::Value::Null);
+
+    for (field, invalid) in [("timezone", "Middle/Earth"), ("locale", "not a locale")] {
+        let request = Request::patch("/me")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header(IF_MATCH, "*")
+            .json(json! {{ field: invalid }});
+        let mut res = app.borrow_mut().oneshot(request).await?;
+        let json = response_json(&mut res).await;
+
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["errors"][0]["field"], field);
+        assert_eq!(json["errors"][0]["code"], "invalid");
+    }
+
+    for (invalid, expected, code) in [
+        (
+            json! {{ "metadata": { "shoeSize": 11 } }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+        ),
+        (
+            json! {{ "metadata": [] }},
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+        ),
+    ] {
+        let request = Request::patch("/me")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header(IF_MATCH, "*")
+            .json(invalid);
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), expected);
+        assert_eq!(response_json(&mut res).await["code"], code);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_upload_avatar(pool: PgPool) -> Result<()> {
+    let uploads = std::env::temp_dir().join(format!("cdb-uploads-{}", uuid::Uuid::new_v4()));
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            blob_store: Arc::new(LocalBlobStore::new(&uploads)),
+            ..Services::default()
+        },
+    );
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    let request = Request::put("/me/avatar")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .multipart("avatar", "image/png", b"GIF89a, but called a PNG");
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let request = Request::put("/me/avatar")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .multipart(
+            "avatar",
+            "image/png",
+            &[png.as_slice(), &[0; 3 * 1024 * 1024]].concat(),
+        );
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    for _ in 0..2 {
+        let request = Request::put("/me/avatar")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .multipart("avatar", "image/png", png);
+        let mut res = app.borrow_mut().oneshot(request).await?;
+        let json = response_json(&mut res).await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(json["avatarUrl"], format!("/users/{id}/avatar"));
+    }
+
+    let stored = std::fs::read_dir(uploads.join("avatars").join(id.to_string()))?.count();
+
+    assert_eq!(stored, 1, "Expecting the replaced avatar to be removed");
+
+    let request = Request::get(format!("/users/{id}/avatar")).empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::get(format!("/users/{id}/avatar"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[CONTENT_TYPE], "image/png");
+    assert_eq!(
+        res.body_mut().data().await.transpose()?.as_deref(),
+        Some(png.as_slice())
+    );
+
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let request = Request::get(format!("/users/{id}/avatar"))
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        res.status(),
+        StatusCode::NOT_FOUND,
+        "Expecting avatars to be hidden outside of the organization"
+    );
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deleted_at = now() WHERE id = $1"#,
+    )
+    .bind(id)
+    .execute(&pool)
+    .await?;
+
+    let request = Request::put("/me/avatar")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .multipart("avatar", "image/png", png);
+    let res = app.borrow_mut().oneshot(request).await?;
+    let stored = std::fs::read_dir(uploads.join("avatars").join(id.to_string()))?.count();
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(stored, 1, "Expecting a rejected avatar to be removed");
+
+    std::fs::remove_dir_all(uploads)?;
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::borrow::BorrowMut;
+
+use axum::http::{header::AUTHORIZATION, Request, StatusCode};
+use cdb_api::{http::routes, jobs, test_utils::*};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+const GRANT_TYPE: &str = "urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code";
+
+#[sqlx::test(fixtures("users"))]
+async fn test_device_flow(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    let request = Request::post("/oauth/device/code").form("client_id=cdb-cli");
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    let device_code = json["device_code"].as_str().expect("Expecting device code");
+    let user_code = json["user_code"].as_str().expect("Expecting user code");
+    let verification_uri = json["verification_uri_complete"]
+        .as_str()
+        .expect("Expecting verification URI");
+    let verification_path =
+        &verification_uri[verification_uri.find("/device").unwrap_or_default()..];
+    let poll = format!("grant_type={GRANT_TYPE}&device_code={device_code}&client_id=cdb-cli");
+
+    let request = Request::post("/oauth/token").form(&poll);
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"], "authorization_pending");
+
+    let request = Request::post("/oauth/token").form(&poll);
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["error"], "slow_down");
+
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    // The verification URI shows the request being approved.
+    let request = Request::get(verification_path)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["userCode"], user_code);
+    assert_eq!(json["clientId"], "cdb-cli");
+
+    let request = Request::post("/device")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "userCode": user_code, "approve": true }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app_private.device_codes SET last_polled_at = now() - INTERVAL '1 minute'"#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let request = Request::post("/oauth/token").form(&poll);
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["tokenType"], "Bearer");
+    json.get("refreshToken").expect("Expecting refresh token");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_device_flow_unknown_code(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+
+    let request = Request::post("/oauth/token").form(&format!(
+        "grant_type={GRANT_TYPE}&device_code=nope&client_id=cdb-cli"
+    ));
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["error"], "invalid_grant");
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_device_flow_deactivated_user(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    let request = Request::post("/oauth/device/code").form("client_id=cdb-cli");
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    let device_code = json["device_code"].as_str().expect("Expecting device code");
+    let user_code = json["user_code"].as_str().expect("Expecting user code");
+    let poll = format!("grant_type={GRANT_TYPE}&device_code={device_code}&client_id=cdb-cli");
+
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/device")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "userCode": user_code, "approve": true }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deactivated_at = now()"#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let request = Request::post("/oauth/token").form(&poll);
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"], "invalid_grant");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_remove_expired_device_codes(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+
+    for _ in 0..3 {
+        let request = Request::post("/oauth/device/code").form("client_id=cdb-cli");
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // Codes that just expired are kept so that devices learn why polling fails.
+    for (age, device_code) in [
+        ("2 days", "min(device_code)"),
+        ("1 minute", "max(device_code)"),
+    ] {
+        sqlx::query(&format!(
+            // language=PostgreSQL
+            r#"
+              UPDATE app_private.device_codes SET expires_at = now() - INTERVAL '{age}'
+              WHERE device_code = (SELECT {device_code} FROM app_private.device_codes)
+          "#
+        ))
+        .execute(&pool)
+        .await?;
+    }
+
+    assert_eq!(jobs::remove_expired_device_codes(&pool).await?, 1);
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT count(*) FROM app_private.device_codes"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(remaining, 2);
+
+    Ok(())
+}
@@ -0,0 +1,467 @@
+use std::{borrow::BorrowMut, sync::Arc};
+
+use axum::http::{header::AUTHORIZATION, Request, StatusCode};
+use cdb_api::{
+    http::{routes, routes_with, Services},
+    mail::CaptureMailer,
+    test_utils::*,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+#[sqlx::test(fixtures("users"))]
+async fn test_organizations(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::get("/users")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::post("/orgs")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Krusty Krab" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let org = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(org["role"], "owner");
+
+    let request = Request::get("/orgs")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json, json!([org]));
+
+    // A new token acts in the organization, which only has its owner so far.
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let request = Request::get("/users")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(json["items"][0]["email"], "sleepy.g@yahoo.com");
+
+    let org_id = org["id"].as_str().unwrap_or_default();
+    let members = format!("/orgs/{org_id}/members");
+
+    // Users outside the organization stay out of its sight until they join.
+    let find_kiko = || {
+        Request::get(format!("/users/{kiko}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body()
+    };
+    let res = app.borrow_mut().oneshot(find_kiko()).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let request = Request::post(format!("/orgs/{org_id}/invitations"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "email": "kikos.delivery.service@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let invitation = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT token FROM app_private.invitations WHERE email = 'kikos.delivery.service@gmail.com'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+    let request = Request::post("/invitations/accept").json(json! {{ "token": invitation }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = app.borrow_mut().oneshot(find_kiko()).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let request = Request::get(&members)
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json.as_array().map(Vec::len), Some(2));
+
+    let request = Request::post(format!("/orgs/{org_id}/invitations"))
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .json(json! {{ "email": "patrick@rock.com", "role": "owner" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "admins"))]
+async fn test_add_member(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/orgs")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Krusty Krab" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let org_id = response_json(&mut res).await["id"].clone();
+    let members = format!("/orgs/{}/members", org_id.as_str().unwrap_or_default());
+
+    let request = Request::post(&members)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "userId": kiko, "role": "chef" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["errors"][0]["field"], "role");
+
+    let add_kiko = || {
+        Request::post(&members)
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(json! {{ "userId": kiko }})
+    };
+    let mut res = app.borrow_mut().oneshot(add_kiko()).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(json["email"], "kikos.delivery.service@gmail.com");
+    assert_eq!(json["role"], "member");
+
+    let res = app.borrow_mut().oneshot(add_kiko()).await?;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let action = sqlx::query_scalar::<_, String>(
+        // language=PostgreSQL
+        r#"SELECT action FROM app_private.audit_log WHERE subject_id = $1"#,
+    )
+    .bind(kiko)
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(action, "organization.member.add");
+
+    // Members can't add anyone.
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let request = Request::post(&members)
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .json(json! {{ "userId": dee }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Nor can users outside the organization.
+    let wildcard_token = access_token(&mut app, "wildcard@paddys.com", "kittenmittons").await;
+    let request = Request::post(&members)
+        .header(AUTHORIZATION, format!("Bearer {wildcard_token}"))
+        .json(json! {{ "userId": dee }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn test_switch_org(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let sleepy = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let kiko = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/orgs")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "name": "Krusty Krab" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let org_id = response_json(&mut res).await["id"].clone();
+
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let request = Request::post("/auth/switch-org")
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .json(json! {{ "orgId": org_id }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::post("/auth/switch-org")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "orgId": org_id }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["orgId"], org_id);
+
+    // Both the switched token and new logins act in the new organization.
+    let switched = json["accessToken"].as_str().unwrap_or_default().to_string();
+    let relogged = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    for token in [switched, relogged] {
+        let request = Request::get("/users/search?q=kiko")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body();
+        let mut res = app.borrow_mut().oneshot(request).await?;
+        let json = response_json(&mut res).await;
+
+        assert_eq!(json["items"], json!([]));
+    }
+
+    let bikini_bottom = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.organizations WHERE name = 'Bikini Bottom'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let request = Request::delete(format!("/orgs/{bikini_bottom}/members/{sleepy}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    // An organization must keep an owner.
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let request = Request::delete(format!("/orgs/{bikini_bottom}/members/{kiko}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // Kiko's token was issued for an organization they no longer belong to.
+    let request = Request::get("/users")
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn test_invitations(pool: PgPool) -> Result<()> {
+    let mailer = Arc::new(CaptureMailer::default());
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            mailer: mailer.clone(),
+            ..Services::default()
+        },
+    );
+    let org_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.organizations WHERE name = 'Bikini Bottom'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+    let invitations = format!("/orgs/{org_id}/invitations");
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let kiko_token = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+
+    let request = Request::post(&invitations)
+        .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+        .json(json! {{ "email": "patrick@rock.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::post(&invitations)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "email": "KIKOS.delivery.service@gmail.com" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    for email in ["patrick@rock.com", "squidward@tiki.com"] {
+        let request = Request::post(&invitations)
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .json(json! {{ "email": email, "role": "admin" }});
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(mailer.sent_to(email).len(), 1);
+    }
+
+    let invitation_token = |email: &'static str| {
+        let pool = pool.clone();
+
+        async move {
+            sqlx::query_scalar::<_, uuid::Uuid>(
+                // language=PostgreSQL
+                r#"SELECT token FROM app_private.invitations WHERE email = $1"#,
+            )
+            .bind(email)
+            .fetch_one(&pool)
+            .await
+        }
+    };
+    let patrick = invitation_token("patrick@rock.com").await?;
+    let squidward = invitation_token("squidward@tiki.com").await?;
+
+    assert!(mailer.sent_to("patrick@rock.com")[0]
+        .body
+        .contains(&patrick.to_string()));
+
+    assert!(!mailer.sent_to("patrick@rock.com")[0]
+        .body
+        .contains("7 days"));
+
+    let request = Request::post("/invitations/accept").json(json! {{ "token": patrick }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(
+        res.status(),
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "Expecting registration details for a new address"
+    );
+    assert_eq!(json["errors"][0]["field"], "firstName");
+    assert_eq!(json["errors"][0]["code"], "required");
+    assert_eq!(json["errors"][2]["field"], "password");
+
+    let request = Request::post("/invitations/accept").json(json! {{
+        "token": patrick,
+        "firstName": "Patrick",
+        "lastName": "Star",
+        "password": "ilovejellyfishing"
+    }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["registered"], true);
+    assert_eq!(json["role"], "admin");
+
+    let patrick_token = access_token(&mut app, "patrick@rock.com", "ilovejellyfishing").await;
+    let request = Request::get("/me")
+        .header(AUTHORIZATION, format!("Bearer {patrick_token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(response_json(&mut res).await["emailVerified"], true);
+
+    let request = Request::get(&invitations)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+    let id = json
+        .as_array()
+        .and_then(|invitations| {
+            invitations
+                .iter()
+                .find(|invitation| invitation["email"] == "squidward@tiki.com")
+        })
+        .and_then(|invitation| invitation["id"].as_str())
+        .expect("Expecting the open invitation to be listed");
+
+    for id in [id.to_string(), uuid::Uuid::new_v4().to_string()] {
+        let request = Request::delete(format!("{invitations}/{id}"))
+            .header(AUTHORIZATION, format!("Bearer {kiko_token}"))
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(
+            res.status(),
+            StatusCode::FORBIDDEN,
+            "Expecting members to learn nothing about invitations"
+        );
+    }
+
+    let request = Request::delete(format!("{invitations}/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    for token in [patrick, squidward] {
+        let request = Request::post("/invitations/accept").json(json! {{
+            "token": token,
+            "firstName": "Squidward",
+            "lastName": "Tentacles",
+            "password": "clarinet1"
+        }});
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    let request = Request::get(&invitations)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+    let mut statuses: Vec<_> = json
+        .as_array()
+        .map(|invitations| {
+            invitations
+                .iter()
+                .map(|invitation| invitation["status"].clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    statuses.sort_by_key(ToString::to_string);
+
+    assert_eq!(statuses, vec![json!("accepted"), json!("revoked")]);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations", "admins"))]
+async fn test_accept_invitation_inactive_account(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+
+    let invitation = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app_private.invitations (organization_id, email)
+          SELECT id, 'wildcard@paddys.com' FROM app.organizations WHERE name = 'Bikini Bottom'
+          RETURNING token
+      "#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deactivated_at = now() WHERE id = $1"#,
+    )
+    .bind(charlie)
+    .execute(&pool)
+    .await?;
+
+    let request = Request::post("/invitations/accept").json(json! {{ "token": invitation }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    assert_eq!(response_json(&mut res).await["code"], "conflict");
+
+    let memberships = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT count(*) FROM app.memberships WHERE user_id = $1"#,
+    )
+    .bind(charlie)
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(memberships, 0, "Expecting deactivated users not to join");
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::borrow::BorrowMut;
+
+use axum::http::{header::AUTHORIZATION, Request, StatusCode};
+use cdb_api::{http::routes, jobs, test_utils::*};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use webauthn_authenticator_rs::{softpasskey::SoftPasskey, WebauthnAuthenticator};
+use webauthn_rs::prelude::{CreationChallengeResponse, RequestChallengeResponse, Url};
+
+#[sqlx::test(fixtures("users"))]
+async fn test_passkey_registration_and_login(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+    let origin = Url::parse("http://localhost:3000")?;
+
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let request = Request::post("/auth/passkeys/register/start")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    let options: CreationChallengeResponse = serde_json::from_value(json["options"].clone())?;
+    let credential = authenticator
+        .do_registration(origin.clone(), options)
+        .expect("Expecting the authenticator to create a credential");
+
+    let request = Request::post("/auth/passkeys/register/finish")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{
+            "challengeId": json["challengeId"],
+            "name": "Soft passkey",
+            "credential": credential
+        }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["name"], "Soft passkey");
+
+    let request =
+        Request::post("/auth/passkeys/login/start").json(json! {{ "email": "sleepy.g@yahoo.com" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    let options: RequestChallengeResponse = serde_json::from_value(json["options"].clone())?;
+    let assertion = authenticator
+        .do_authentication(origin.clone(), options)
+        .expect("Expecting the authenticator to sign the challenge");
+
+    let request = Request::post("/auth/passkeys/login/finish").json(json! {{
+        "challengeId": json["challengeId"],
+        "credential": assertion
+    }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["tokenType"], "Bearer");
+
+    let request =
+        Request::post("/auth/passkeys/login/start").json(json! {{ "email": "sleepy.g@yahoo.com" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    let options: RequestChallengeResponse = serde_json::from_value(json["options"].clone())?;
+    let assertion = authenticator
+        .do_authentication(origin, options)
+        .expect("Expecting the authenticator to sign the challenge");
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deactivated_at = now()"#,
+    )
+    .execute(&pool)
+    .await?;
+
+    let request = Request::post("/auth/passkeys/login/finish").json(json! {{
+        "challengeId": json["challengeId"],
+        "credential": assertion
+    }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(
+        res.status(),
+        StatusCode::UNAUTHORIZED,
+        "Expecting users deactivated during the login to be refused"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_passkey_login_without_passkeys(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+
+    // Accounts without passkeys can't be told apart from unknown addresses.
+    for email in ["sleepy.g@yahoo.com", "patrick@rock.com"] {
+        let request = Request::post("/auth/passkeys/login/start").json(json! {{ "email": email }});
+        let mut res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response_json(&mut res).await["code"], "invalid_credentials");
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_remove_expired_challenges(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    for _ in 0..2 {
+        let request = Request::post("/auth/passkeys/register/start")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body();
+        let res = app.borrow_mut().oneshot(request).await?;
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          UPDATE app_private.webauthn_challenges SET expires_at = now()
+          WHERE id = (SELECT id FROM app_private.webauthn_challenges LIMIT 1)
+      "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    assert_eq!(jobs::remove_expired_challenges(&pool).await?, 1);
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT count(*) FROM app_private.webauthn_challenges"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(remaining, 1);
+
+    Ok(())
+}
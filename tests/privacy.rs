@@ -0,0 +1,274 @@
+use std::{borrow::BorrowMut, sync::Arc, time::Duration};
+
+use axum::http::{header::AUTHORIZATION, Request, StatusCode};
+use cdb_api::{
+    blob::{LocalBlobStore, SharedBlobStore},
+    http::{routes_with, Services},
+    privacy,
+    test_utils::*,
+};
+use eyre::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+fn blob_store() -> (std::path::PathBuf, SharedBlobStore) {
+    let root = std::env::temp_dir().join(format!("cdb-uploads-{}", uuid::Uuid::new_v4()));
+
+    (root.clone(), Arc::new(LocalBlobStore::new(root)))
+}
+
+/// Invite Sleepy to an organization, add them to a group and name them in a
+/// relation tuple.
+async fn seed_relations(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        // language=PostgreSQL
+        r#"
+          WITH o AS (
+            INSERT INTO app.organizations (name) VALUES ('Bikini Bottom') RETURNING id
+          ), i AS (
+            INSERT INTO app_private.invitations (organization_id, email)
+            SELECT id, 'Sleepy.G@yahoo.com' FROM o
+          ), g AS (
+            INSERT INTO app.groups (name) VALUES ('Snails') RETURNING id
+          ), m AS (
+            INSERT INTO app.group_members (group_id, user_id)
+            SELECT g.id, a.user_id FROM g, app_private.accounts AS a
+            WHERE a.email = 'sleepy.g@yahoo.com'
+          )
+          INSERT INTO app_private.relation_tuples (object_type, object_id, relation, subject_type, subject_id)
+          SELECT 'user', a.user_id::TEXT, 'editor', 'user', a.user_id::TEXT
+          FROM app_private.accounts AS a
+          WHERE a.email = 'sleepy.g@yahoo.com'
+      "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_export(pool: PgPool) -> Result<()> {
+    let (root, blob_store) = blob_store();
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            blob_store,
+            ..Services::default()
+        },
+    );
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    seed_relations(&pool).await?;
+
+    let request = Request::post("/me/export")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(json["status"], "pending");
+
+    let id = json["id"].as_str().expect("Expecting an export id");
+    let mut export = json.clone();
+
+    for _ in 0..50 {
+        if export["status"] != "pending" {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let request = Request::get(format!("/me/exports/{id}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .empty_body();
+        let mut res = app.borrow_mut().oneshot(request).await?;
+        export = response_json(&mut res).await;
+    }
+
+    assert_eq!(export["status"], "ready");
+
+    let download_url = export["downloadUrl"]
+        .as_str()
+        .expect("Expecting a download URL");
+    let request = Request::get(download_url)
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let archive = response_json(&mut res).await;
+
+    assert_eq!(archive["profile"]["first_name"], "Sleepy");
+    assert_eq!(archive["account"]["email"], "sleepy.g@yahoo.com");
+    assert!(archive["account"].get("hashed_password").is_none());
+    assert_eq!(archive["audit_log"][0]["action"], "export.requested");
+    assert_eq!(
+        archive["invitations"][0]["organization_name"],
+        "Bikini Bottom"
+    );
+    assert!(archive["invitations"][0].get("token").is_none());
+    assert_eq!(archive["groups"][0]["group_name"], "Snails");
+    assert_eq!(archive["relations"][0]["relation"], "editor");
+
+    let other = access_token(&mut app, "kikos.delivery.service@gmail.com", "awoo").await;
+    let request = Request::get(download_url)
+        .header(AUTHORIZATION, format!("Bearer {other}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_resume_pending_exports(pool: PgPool) -> Result<()> {
+    let (_, blob_store) = blob_store();
+
+    // An export left pending by a server that stopped before producing it.
+    let id = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"
+          INSERT INTO app_private.data_exports (user_id)
+          SELECT user_id FROM app_private.accounts WHERE email = 'sleepy.g@yahoo.com'
+          RETURNING id
+      "#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(
+        privacy::resume_pending_exports(&pool, &blob_store).await?,
+        1
+    );
+
+    let status = || {
+        sqlx::query_scalar::<_, String>(
+            // language=PostgreSQL
+            r#"SELECT status FROM app_private.data_exports WHERE id = $1"#,
+        )
+        .bind(id)
+        .fetch_one(&pool)
+    };
+
+    for _ in 0..50 {
+        if status().await? != "pending" {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(status().await?, "ready");
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_erase(pool: PgPool) -> Result<()> {
+    let (_, blob_store) = blob_store();
+    let mut app = routes_with(
+        pool.clone(),
+        Services {
+            blob_store: blob_store.clone(),
+            ..Services::default()
+        },
+    );
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    seed_relations(&pool).await?;
+
+    let request = Request::post("/me/erase")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "password": "wrong" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::post("/me/erase")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "password": "test" }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert!(json["eraseAfter"].is_i64());
+
+    // Nothing is erased during the cooling-off period.
+    assert_eq!(privacy::erase_due_users(&pool, &blob_store).await?, 0);
+
+    let request = Request::delete("/me/erase")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::post("/me/erase")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "password": "test" }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app_private.erasure_requests SET erase_after = now() WHERE user_id = $1"#,
+    )
+    .bind(id)
+    .execute(&pool)
+    .await?;
+
+    assert_eq!(privacy::erase_due_users(&pool, &blob_store).await?, 1);
+
+    let request = Request::get(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let identifiable = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"
+            SELECT count(*)
+            FROM app_private.audit_log
+            WHERE actor_id = $1 OR subject_id = $1 OR metadata <> '{}'
+        "#,
+    )
+    .bind(id)
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(identifiable, 0);
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"
+            SELECT (SELECT count(*) FROM app_private.invitations)
+              + (SELECT count(*) FROM app.group_members)
+              + (SELECT count(*) FROM app_private.relation_tuples)
+        "#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(
+        remaining, 0,
+        "Expecting invitations, groups and relations to be erased"
+    );
+
+    let request = Request::post("/auth/authorize").json(json! {{
+        "clientId": "sleepy.g@yahoo.com",
+        "clientSecret": "test"
+    }});
+    let res = app.borrow_mut().oneshot(request).await?;
+
+    assert!(!res.status().is_success());
+
+    Ok(())
+}
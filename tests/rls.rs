@@ -0,0 +1,124 @@
+use cdb_api::{
+    http::{
+        jwt::{Claims, Role},
+        rls::ScopedTx,
+    },
+    test_utils::*,
+};
+use eyre::Result;
+use sqlx::PgPool;
+
+async fn count_users(pool: &PgPool, claims: Option<Claims>) -> Result<i64> {
+    let mut tx = ScopedTx::begin(pool, claims).await?;
+
+    // No WHERE clause: the policies alone decide what is visible.
+    let count = sqlx::query_scalar::<_, i64>(
+        // language=PostgreSQL
+        r#"SELECT count(*) FROM app.users"#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    Ok(count)
+}
+
+#[sqlx::test(fixtures("users", "organizations", "admins"))]
+async fn test_scoped_transaction(pool: PgPool) -> Result<()> {
+    let sleepy = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+    let charlie = user_id(&pool, "wildcard@paddys.com").await?;
+    let org_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        // language=PostgreSQL
+        r#"SELECT id FROM app.organizations WHERE name = 'Bikini Bottom'"#,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(count_users(&pool, None).await?, 0);
+    assert_eq!(
+        count_users(&pool, Some(Claims::new(sleepy, Role::User))).await?,
+        1
+    );
+    assert_eq!(
+        count_users(
+            &pool,
+            Some(Claims::new(sleepy, Role::User).with_org(Some(org_id)))
+        )
+        .await?,
+        2
+    );
+    assert_eq!(
+        count_users(
+            &pool,
+            Some(Claims::new(charlie, Role::User).with_org(Some(org_id)))
+        )
+        .await?,
+        1,
+        "Expecting a claimed organization the user doesn't belong to to be ignored"
+    );
+    assert_eq!(
+        count_users(&pool, Some(Claims::new(dee, Role::Admin))).await?,
+        4
+    );
+
+    let mut tx = ScopedTx::begin(&pool, Some(Claims::new(charlie, Role::User))).await?;
+    let updated = sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET first_name = 'Dayman' WHERE id = $1"#,
+    )
+    .bind(sleepy)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    assert_eq!(updated, 0);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "admins"))]
+async fn test_scoped_exports(pool: PgPool) -> Result<()> {
+    let sleepy = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let dee = user_id(&pool, "sweet.dee@paddys.com").await?;
+
+    sqlx::query(
+        // language=PostgreSQL
+        r#"INSERT INTO app_private.data_exports (user_id) VALUES ($1), ($1), ($2)"#,
+    )
+    .bind(sleepy)
+    .bind(dee)
+    .execute(&pool)
+    .await?;
+
+    for (claims, expected) in [
+        (Claims::new(sleepy, Role::User), 2),
+        (Claims::new(dee, Role::Admin), 1),
+    ] {
+        let mut tx = ScopedTx::begin(&pool, Some(claims)).await?;
+        let count = sqlx::query_scalar::<_, i64>(
+            // language=PostgreSQL
+            r#"SELECT count(*) FROM app_private.data_exports"#,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        assert_eq!(
+            count, expected,
+            "Expecting exports to only show to their user"
+        );
+    }
+
+    let mut tx = ScopedTx::begin(&pool, Some(Claims::new(sleepy, Role::User))).await?;
+    let deleted = sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET deleted_at = now() WHERE id = $1"#,
+    )
+    .bind(dee)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    assert_eq!(deleted, 0);
+
+    Ok(())
+}
@@ -1,8 +1,15 @@
 use std::borrow::BorrowMut;
 
-use axum::http::{Request, StatusCode};
+use axum::{
+    body::Body,
+    http::{
+        header::{ALLOW, AUTHORIZATION, CONTENT_TYPE},
+        Request, StatusCode,
+    },
+};
 use cdb_api::{http::routes, test_utils::*};
 use eyre::Result;
+use serde_json::json;
 use sqlx::PgPool;
 use tower::ServiceExt;
 
@@ -10,9 +17,120 @@ use tower::ServiceExt;
 async fn test_root(pool: PgPool) -> Result<()> {
     let mut app = routes(pool);
     let request = Request::get("/").empty_body();
-    let res = app.borrow_mut().oneshot(request).await?;
+    let mut res = app.borrow_mut().oneshot(request).await?;
 
     assert_eq!(res.status(), StatusCode::OK);
 
+    let json = response_json(&mut res).await;
+    let schemas = &json["components"]["schemas"];
+
+    assert_eq!(
+        schemas["UsersPage"]["properties"]["items"]["items"]["$ref"],
+        "#/components/schemas/UsersResponse"
+    );
+    assert_eq!(
+        schemas["UserSearchPage"]["properties"]["items"]["items"]["$ref"],
+        "#/components/schemas/UserSearchResult"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_problem_details(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let correlation_id = "0b6e4ab4-6d4e-4bd6-9d9d-31c5d3e0aa2b";
+    let request = Request::get("/nowhere")
+        .header("x-correlation-id", correlation_id)
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers()[CONTENT_TYPE], "application/problem+json");
+    assert_eq!(res.headers()["x-correlation-id"], correlation_id);
+
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["type"], "urn:cdb:error:not_found");
+    assert_eq!(json["status"], 404);
+    assert_eq!(json["code"], "not_found");
+    assert_eq!(json["correlationId"], correlation_id);
+
+    let request = Request::post("/accounts/register").json(json! {{
+        "email": "not an email",
+        "password": "short"
+    }});
+    let mut res = app.borrow_mut().oneshot(request).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "validation_failed");
+    assert_eq!(
+        json["errors"],
+        json!([
+            { "field": "email", "code": "email", "params": {}, "message": "Not a valid email address" },
+            { "field": "password", "code": "length", "params": { "min": 8 }, "message": "Too short or too long" }
+        ])
+    );
+    assert!(
+        res.headers().contains_key("x-correlation-id"),
+        "Expecting a correlation id to be generated"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn test_router_errors(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let requests = [
+        (
+            Request::post("/accounts/register")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from("{ not json")),
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+        ),
+        (
+            Request::post("/accounts/register").body(Body::from("{}")),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+        ),
+        (
+            Ok(Request::post("/accounts/register")
+                .json(json! {{ "email": "plankton@chumbucket.com" }})),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_body",
+        ),
+        (
+            Ok(Request::get("/users/plankton/avatar")
+                .header(AUTHORIZATION, format!("Bearer {token}"))
+                .empty_body()),
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+        ),
+        (
+            Ok(Request::post("/nowhere").empty_body()),
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+    ];
+
+    for (request, status, code) in requests {
+        let mut res = app.borrow_mut().oneshot(request?).await?;
+
+        assert_eq!(res.status(), status);
+        assert_eq!(response_json(&mut res).await["code"], code);
+    }
+
+    let request = Request::delete("/").empty_body();
+    let mut res = app.borrow_mut().oneshot(request).await?;
+
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers()[ALLOW], "GET,HEAD");
+    assert_eq!(response_json(&mut res).await["code"], "method_not_allowed");
+
     Ok(())
 }
@@ -1,45 +1,376 @@
-use std::borrow::BorrowMut;
+use std::{borrow::BorrowMut, sync::Arc};
 
-use axum::http::Request;
-use cdb_api::{http::routes, test_utils::*};
+use axum::http::{
+    header::{AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH},
+    Request, StatusCode,
+};
+use cdb_api::{
+    blob::{LocalBlobStore, SharedBlobStore},
+    http::routes,
+    jobs,
+    test_utils::*,
+};
 use eyre::Result;
+use serde_json::json;
 use sqlx::PgPool;
 use tower::ServiceExt;
 
-#[sqlx::test(fixtures("users"))]
+#[sqlx::test(fixtures("users", "organizations"))]
 async fn get_users(pool: PgPool) -> Result<()> {
     let mut app = routes(pool);
-    let req = Request::get("/users").empty_body();
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let req = Request::get("/users")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
     let mut res = app.borrow_mut().oneshot(req).await?;
     let json = response_json(&mut res).await;
 
-    let first_user = json.get(0).expect("Expecting a first user");
-    let second_user = json.get(1).expect("Expecting a second user");
+    let users = json["items"].as_array().expect("Expecting a list of users");
+    // Both users are registered in the same transaction, so they share a
+    // creation date and are ordered by id.
+    let find = |first_name: &str| {
+        users
+            .iter()
+            .find(|user| user["firstName"] == first_name)
+            .cloned()
+            .unwrap_or_else(|| panic!("Expecting a user named {first_name}"))
+    };
 
+    assert_eq!(users.len(), 2);
     assert_eq!(
-        first_user
-            .get("firstName")
-            .expect("Expecting the first user to have a first name"),
-        "Sleepy"
-    );
-    assert_eq!(
-        first_user
+        find("Sleepy")
             .get("lastName")
             .expect("Expecting the first user to have a last name"),
         "Gary"
     );
     assert_eq!(
-        second_user
-            .get("firstName")
-            .expect("Expecting the second user to have a first name"),
-        "Kiko"
-    );
-    assert_eq!(
-        second_user
+        find("Kiko")
             .get("lastName")
             .expect("Expecting the second user to have a last name"),
         "Bato-de Botton"
     );
+    assert_eq!(json["nextCursor"], serde_json::Value::Null);
+    assert_eq!(json["prevCursor"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn paginate_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let req = Request::get("/users?limit=1")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let first_page = response_json(&mut res).await;
+
+    assert_eq!(first_page["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(first_page["prevCursor"], serde_json::Value::Null);
+
+    let next = first_page["nextCursor"]
+        .as_str()
+        .expect("Expecting a next cursor");
+    let req = Request::get(format!("/users?limit=1&cursor={next}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let second_page = response_json(&mut res).await;
+
+    assert_eq!(second_page["items"].as_array().map(Vec::len), Some(1));
+    assert_ne!(second_page["items"][0]["id"], first_page["items"][0]["id"]);
+    assert_eq!(second_page["nextCursor"], serde_json::Value::Null);
+
+    let prev = second_page["prevCursor"]
+        .as_str()
+        .expect("Expecting a previous cursor");
+    let req = Request::get(format!("/users?limit=1&cursor={prev}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"], first_page["items"]);
+    assert_eq!(json["prevCursor"], serde_json::Value::Null);
+
+    let req = Request::get("/users?cursor=garbage")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response_json(&mut res).await["errors"][0]["field"],
+        "cursor"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn filter_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let req = Request::get("/users?email_domain=YAHOO.com")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(json["items"][0]["email"], "sleepy.g@yahoo.com");
+
+    let req = Request::get("/users?verified=true")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"], json!([]));
+
+    let req = Request::get("/users?role=wizard")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response_json(&mut res).await["errors"][0]["field"], "role");
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn sort_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let req = Request::get("/users?sort=-first_name,created_at&limit=1")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"][0]["firstName"], "Sleepy");
+
+    let next = json["nextCursor"]
+        .as_str()
+        .expect("Expecting a next cursor");
+    let req = Request::get(format!(
+        "/users?sort=-first_name,created_at&limit=1&cursor={next}"
+    ))
+    .header(AUTHORIZATION, format!("Bearer {token}"))
+    .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"][0]["firstName"], "Kiko");
+    assert_eq!(json["nextCursor"], serde_json::Value::Null);
+
+    let req = Request::get("/users?sort=hashed_password")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response_json(&mut res).await["errors"][0]["field"], "sort");
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn update_user(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let etag = etag(&mut app, &format!("/users/{id}"), &token).await;
+
+    let req = Request::patch(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, etag)
+        .json(json! {{ "lastName": "Garrison" }});
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["firstName"], "Sleepy");
+    assert_eq!(json["lastName"], "Garrison");
+    assert_ne!(json["updatedAt"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn update_other_user_is_forbidden(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let id = user_id(&pool, "kikos.delivery.service@gmail.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let req = Request::patch(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "firstName": null }});
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = Request::delete(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn conditional_requests(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let id = user_id(&pool, "sleepy.g@yahoo.com").await?;
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+    let original = etag(&mut app, &format!("/users/{id}"), &token).await;
+
+    let req = Request::get(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_NONE_MATCH, &original)
+        .empty_body();
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let req = Request::patch(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(json! {{ "firstName": "Gary" }});
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    let req = Request::patch(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .json(json! {{ "firstName": "Gary" }});
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_ne!(res.headers()[ETAG], original.as_str());
+
+    let req = Request::patch(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, &original)
+        .json(json! {{ "firstName": "Sleepy" }});
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    let req = Request::get(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_NONE_MATCH, &original)
+        .empty_body();
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("admins"))]
+async fn delete_user_as_admin(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool.clone());
+    let id = user_id(&pool, "wildcard@paddys.com").await?;
+    let token = access_token(&mut app, "sweet.dee@paddys.com", "birdlady").await;
+    let etag = etag(&mut app, &format!("/users/{id}"), &token).await;
+
+    let req = Request::delete(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header(IF_MATCH, etag)
+        .empty_body();
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let req = Request::get(format!("/users/{id}"))
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let req = Request::post("/auth/authorize").json(json! {{
+        "clientId": "wildcard@paddys.com",
+        "clientSecret": "kittenmittons"
+    }});
+    let res = app.borrow_mut().oneshot(req).await?;
+
+    assert!(!res.status().is_success());
+
+    let accounts = || {
+        sqlx::query_scalar::<_, i64>(
+            // language=PostgreSQL
+            r#"SELECT count(*) FROM app_private.accounts WHERE user_id = $1"#,
+        )
+        .bind(id)
+        .fetch_one(&pool)
+    };
+
+    assert_eq!(accounts().await?, 1);
+
+    let blob_store: SharedBlobStore = Arc::new(LocalBlobStore::new(
+        std::env::temp_dir().join(format!("cdb-uploads-{}", uuid::Uuid::new_v4())),
+    ));
+    let avatar = format!("avatars/{id}/avatar.png");
+    blob_store.put(&avatar, "png".into()).await?;
+    sqlx::query(
+        // language=PostgreSQL
+        r#"UPDATE app.users SET avatar_key = $2 WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(&avatar)
+    .execute(&pool)
+    .await?;
+
+    jobs::purge_deleted_users(&pool, &blob_store, 0).await?;
+
+    assert_eq!(accounts().await?, 0);
+    assert_eq!(
+        blob_store.get(&avatar).await?,
+        None,
+        "Expecting the avatar of a purged user to be removed"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users", "organizations"))]
+async fn search_users(pool: PgPool) -> Result<()> {
+    let mut app = routes(pool);
+    let token = access_token(&mut app, "sleepy.g@yahoo.com", "test").await;
+
+    let req = Request::get("/users/search?q=slepy")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(json["items"][0]["firstName"], "Sleepy");
+
+    let req = Request::get("/users/search?q=delivry")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+    let json = response_json(&mut res).await;
+
+    assert_eq!(json["items"][0]["firstName"], "Kiko");
+
+    let req = Request::get("/users/search?q=%20")
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .empty_body();
+    let mut res = app.borrow_mut().oneshot(req).await?;
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response_json(&mut res).await["errors"][0]["field"], "q");
 
     Ok(())
 }